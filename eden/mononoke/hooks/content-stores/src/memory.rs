@@ -5,6 +5,7 @@
  * GNU General Public License version 2.
  */
 
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 
 use anyhow::format_err;
@@ -16,6 +17,7 @@ use context::CoreContext;
 use mononoke_types::ChangesetId;
 use mononoke_types::ContentId;
 use mononoke_types::MPath;
+use mononoke_types::MPathElement;
 
 use crate::ErrorKind;
 use crate::FileChange;
@@ -50,6 +52,7 @@ impl From<u64> for InMemoryFileText {
 #[derive(Clone)]
 pub struct InMemoryFileContentManager {
     id_to_text: HashMap<ContentId, InMemoryFileText>,
+    bookmark_paths: HashMap<BookmarkName, Vec<MPath>>,
 }
 
 #[async_trait]
@@ -82,6 +85,21 @@ impl FileContentManager for InMemoryFileContentManager {
             })
     }
 
+    async fn get_file_prefix<'a>(
+        &'a self,
+        _ctx: &'a CoreContext,
+        id: ContentId,
+        size: usize,
+    ) -> Result<Bytes, ErrorKind> {
+        match self.id_to_text.get(&id) {
+            Some(InMemoryFileText::Present(bytes)) => Ok(bytes.slice(..size.min(bytes.len()))),
+            Some(InMemoryFileText::Elided(_)) => {
+                Err(format_err!("Content of {} is elided", id).into())
+            }
+            None => Err(ErrorKind::ContentIdNotFound(id)),
+        }
+    }
+
     async fn find_content<'a>(
         &'a self,
         _ctx: &'a CoreContext,
@@ -94,6 +112,37 @@ impl FileContentManager for InMemoryFileContentManager {
         )
     }
 
+    async fn list_directories<'a>(
+        &'a self,
+        _ctx: &'a CoreContext,
+        bookmark: BookmarkName,
+        paths: Vec<Option<MPath>>,
+    ) -> Result<HashMap<Option<MPath>, Vec<MPathElement>>, ErrorKind> {
+        let files = match self.bookmark_paths.get(&bookmark) {
+            Some(files) => files,
+            None => return Ok(HashMap::new()),
+        };
+        let mut directories = HashMap::new();
+        for path in paths {
+            let depth = path.as_ref().map_or(0, MPath::num_components);
+            let mut is_directory = path.is_none();
+            let mut names = BTreeSet::new();
+            for file in files {
+                if !MPath::is_prefix_of_opt(path.as_ref(), file) {
+                    continue;
+                }
+                if let Some(name) = file.into_iter().nth(depth) {
+                    is_directory = true;
+                    names.insert(name.clone());
+                }
+            }
+            if is_directory {
+                directories.insert(path, names.into_iter().collect());
+            }
+        }
+        Ok(directories)
+    }
+
     async fn file_changes<'a>(
         &'a self,
         _ctx: &'a CoreContext,
@@ -123,10 +172,61 @@ impl InMemoryFileContentManager {
     pub fn new() -> InMemoryFileContentManager {
         InMemoryFileContentManager {
             id_to_text: HashMap::new(),
+            bookmark_paths: HashMap::new(),
         }
     }
 
     pub fn insert(&mut self, key: ContentId, text: impl Into<InMemoryFileText>) {
         self.id_to_text.insert(key, text.into());
     }
+
+    /// Add a file at `path` to the tree of `bookmark`, for `list_directories`.
+    pub fn insert_path(&mut self, bookmark: BookmarkName, path: MPath) {
+        self.bookmark_paths.entry(bookmark).or_default().push(path);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use fbinit::FacebookInit;
+    use tokio::runtime::Runtime;
+
+    use super::*;
+
+    #[fbinit::test]
+    fn test_list_directories(fb: FacebookInit) {
+        let rt = Runtime::new().unwrap();
+        let ctx = CoreContext::test_mock(fb);
+        let main = BookmarkName::new("main").unwrap();
+        let path = |s: &str| MPath::new(s).unwrap();
+        let element = |s: &str| MPathElement::new(s.into()).unwrap();
+
+        let mut store = InMemoryFileContentManager::new();
+        store.insert_path(main.clone(), path("README"));
+        store.insert_path(main.clone(), path("dir/a"));
+        store.insert_path(main.clone(), path("dir/sub/b"));
+
+        let ret = rt
+            .block_on(store.list_directories(
+                &ctx,
+                main,
+                vec![
+                    None,
+                    Some(path("dir")),
+                    Some(path("README")),
+                    Some(path("x")),
+                ],
+            ))
+            .unwrap();
+        assert_eq!(ret.len(), 2);
+        assert_eq!(ret[&None], vec![element("README"), element("dir")]);
+        assert_eq!(ret[&Some(path("dir"))], vec![element("a"), element("sub")]);
+
+        // A missing bookmark is an empty tree.
+        let other = BookmarkName::new("other").unwrap();
+        let ret = rt
+            .block_on(store.list_directories(&ctx, other, vec![None]))
+            .unwrap();
+        assert!(ret.is_empty());
+    }
 }
@@ -22,6 +22,7 @@ use futures::stream::TryStreamExt;
 use futures_util::future::TryFutureExt;
 use manifest::Diff;
 use manifest::Entry;
+use manifest::Manifest;
 use manifest::ManifestOps;
 use mercurial_derived_data::MappedHgChangesetId;
 use mercurial_types::FileType;
@@ -30,6 +31,7 @@ use mercurial_types::HgManifestId;
 use mononoke_types::ChangesetId;
 use mononoke_types::ContentId;
 use mononoke_types::MPath;
+use mononoke_types::MPathElement;
 use mononoke_types::ManifestUnodeId;
 use repo_blobstore::ArcRepoBlobstore;
 use repo_blobstore::RepoBlobstore;
@@ -76,6 +78,23 @@ impl FileContentManager for RepoFileContentManager {
             .map(Option::Some)
     }
 
+    async fn get_file_prefix<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        id: ContentId,
+        size: usize,
+    ) -> Result<Bytes, ErrorKind> {
+        if size == 0 {
+            // filestore::peek does not support empty prefixes. Still check
+            // that the content exists.
+            self.get_file_size(ctx, id).await?;
+            return Ok(Bytes::new());
+        }
+        filestore::peek(&self.repo_blobstore, ctx, &id.into(), size)
+            .await?
+            .ok_or(ErrorKind::ContentIdNotFound(id))
+    }
+
     async fn find_content<'a>(
         &'a self,
         ctx: &'a CoreContext,
@@ -113,6 +132,43 @@ impl FileContentManager for RepoFileContentManager {
             .await
     }
 
+    async fn list_directories<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        bookmark: BookmarkName,
+        paths: Vec<Option<MPath>>,
+    ) -> Result<HashMap<Option<MPath>, Vec<MPathElement>>, ErrorKind> {
+        let changeset_id = match self
+            .bookmarks
+            .get(ctx.clone(), &bookmark)
+            .await
+            .with_context(|| format!("Error fetching bookmark: {}", bookmark))?
+        {
+            Some(changeset_id) => changeset_id,
+            // A bookmark that is being created has no existing paths.
+            None => return Ok(HashMap::new()),
+        };
+
+        let master_mf = derive_hg_manifest(
+            ctx,
+            &self.repo_derived_data,
+            &self.repo_blobstore,
+            changeset_id,
+        )
+        .await?;
+        master_mf
+            .find_entries(ctx.clone(), self.repo_blobstore.clone(), paths)
+            .map_ok(|(path, entry)| async move {
+                let names = resolve_directory_names(ctx, &self.repo_blobstore, entry).await?;
+                Ok(names.map(|names| (path, names)))
+            })
+            .try_buffer_unordered(100)
+            .try_filter_map(future::ok)
+            .try_collect::<HashMap<_, _>>()
+            .map_err(ErrorKind::from)
+            .await
+    }
+
     async fn file_changes<'a>(
         &'a self,
         ctx: &'a CoreContext,
@@ -286,6 +342,22 @@ async fn derive_unode_manifest(
     Ok(unode_mf)
 }
 
+async fn resolve_directory_names(
+    ctx: &CoreContext,
+    blobstore: &RepoBlobstore,
+    entry: Entry<HgManifestId, (FileType, HgFileNodeId)>,
+) -> Result<Option<Vec<MPathElement>>, ErrorKind> {
+    match entry {
+        Entry::Tree(manifest_id) => manifest_id
+            .load(ctx, blobstore)
+            .map_ok(|manifest| Some(manifest.list().map(|(name, _entry)| name).collect()))
+            .await
+            .with_context(|| format!("Error loading manifest: {}", manifest_id))
+            .map_err(ErrorKind::from),
+        Entry::Leaf(_) => Ok(None),
+    }
+}
+
 async fn resolve_content_id(
     ctx: &CoreContext,
     blobstore: &RepoBlobstore,
@@ -15,6 +15,7 @@ use context::CoreContext;
 use mononoke_types::ChangesetId;
 use mononoke_types::ContentId;
 use mononoke_types::MPath;
+use mononoke_types::MPathElement;
 
 use crate::ErrorKind;
 
@@ -32,6 +33,15 @@ pub trait FileContentManager: Send + Sync {
         id: ContentId,
     ) -> Result<Option<Bytes>, ErrorKind>;
 
+    /// Fetch up to `size` bytes from the start of the file. Unlike
+    /// `get_file_text`, this returns the content of binary files too.
+    async fn get_file_prefix<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        id: ContentId,
+        size: usize,
+    ) -> Result<Bytes, ErrorKind>;
+
     async fn find_content<'a>(
         &'a self,
         ctx: &'a CoreContext,
@@ -39,6 +49,17 @@ pub trait FileContentManager: Send + Sync {
         paths: Vec<MPath>,
     ) -> Result<HashMap<MPath, PathContent>, ErrorKind>;
 
+    /// List the names of the entries in the given directories at
+    /// `bookmark`. `None` is the root directory. Paths that are not
+    /// directories at `bookmark` are left out of the result. A missing
+    /// `bookmark` is treated as an empty tree.
+    async fn list_directories<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        bookmark: BookmarkName,
+        paths: Vec<Option<MPath>>,
+    ) -> Result<HashMap<Option<MPath>, Vec<MPathElement>>, ErrorKind>;
+
     async fn file_changes<'a>(
         &'a self,
         ctx: &'a CoreContext,
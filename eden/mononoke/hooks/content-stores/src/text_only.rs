@@ -16,6 +16,7 @@ use context::CoreContext;
 use mononoke_types::ChangesetId;
 use mononoke_types::ContentId;
 use mononoke_types::MPath;
+use mononoke_types::MPathElement;
 
 use crate::ErrorKind;
 use crate::FileChange;
@@ -72,6 +73,15 @@ impl<T: FileContentManager + 'static> FileContentManager for TextOnlyFileContent
         }))
     }
 
+    async fn get_file_prefix<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        id: ContentId,
+        size: usize,
+    ) -> Result<Bytes, ErrorKind> {
+        self.inner.get_file_prefix(ctx, id, size).await
+    }
+
     async fn find_content<'a>(
         &'a self,
         ctx: &'a CoreContext,
@@ -81,6 +91,15 @@ impl<T: FileContentManager + 'static> FileContentManager for TextOnlyFileContent
         self.inner.find_content(ctx, bookmark, paths).await
     }
 
+    async fn list_directories<'a>(
+        &'a self,
+        ctx: &'a CoreContext,
+        bookmark: BookmarkName,
+        paths: Vec<Option<MPath>>,
+    ) -> Result<HashMap<Option<MPath>, Vec<MPathElement>>, ErrorKind> {
+        self.inner.list_directories(ctx, bookmark, paths).await
+    }
+
     async fn file_changes<'a>(
         &'a self,
         ctx: &'a CoreContext,
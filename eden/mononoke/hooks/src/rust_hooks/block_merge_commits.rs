@@ -0,0 +1,218 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bookmarks::BookmarkName;
+use context::CoreContext;
+use mononoke_types::BonsaiChangeset;
+use permission_checker::MononokeIdentity;
+use permission_checker::MononokeIdentitySet;
+use regex::Regex;
+
+use crate::ChangesetHook;
+use crate::CrossRepoPushSource;
+use crate::FileContentManager;
+use crate::HookConfig;
+use crate::HookExecution;
+use crate::HookRejectionInfo;
+use crate::PushAuthoredBy;
+
+#[derive(Default)]
+pub struct BlockMergeCommitsBuilder {
+    bookmark_regexes: Vec<String>,
+    allowed_identities: Vec<String>,
+}
+
+impl BlockMergeCommitsBuilder {
+    pub fn set_from_config(mut self, config: &HookConfig) -> Self {
+        if let Some(v) = config.string_lists.get("bookmark_regexes") {
+            self = self.bookmark_regexes(v)
+        }
+        if let Some(v) = config.string_lists.get("allowed_identities") {
+            self = self.allowed_identities(v)
+        }
+        self
+    }
+
+    /// Only block merges to bookmarks matching one of the regexes. If not
+    /// set, merges are blocked for all bookmarks the hook runs for.
+    pub fn bookmark_regexes(mut self, strs: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.bookmark_regexes = strs.into_iter().map(|s| String::from(s.as_ref())).collect();
+        self
+    }
+
+    /// Identities, in the `TYPE:data` form, that are allowed to push merges.
+    pub fn allowed_identities(mut self, strs: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.allowed_identities = strs.into_iter().map(|s| String::from(s.as_ref())).collect();
+        self
+    }
+
+    pub fn build(self) -> Result<BlockMergeCommits> {
+        Ok(BlockMergeCommits {
+            bookmark_regexes: self
+                .bookmark_regexes
+                .iter()
+                .map(|s| Regex::new(s))
+                .collect::<Result<Vec<_>, _>>()
+                .context("Failed to create regex for bookmark_regexes")?,
+            allowed_identities: self
+                .allowed_identities
+                .iter()
+                .map(|s| s.parse::<MononokeIdentity>())
+                .collect::<Result<_>>()
+                .context("Failed to parse allowed_identities")?,
+        })
+    }
+}
+
+/// Rejects commits with more than one parent, to keep bookmarks linear.
+pub struct BlockMergeCommits {
+    bookmark_regexes: Vec<Regex>,
+    allowed_identities: MononokeIdentitySet,
+}
+
+impl BlockMergeCommits {
+    pub fn builder() -> BlockMergeCommitsBuilder {
+        BlockMergeCommitsBuilder::default()
+    }
+
+    fn applies_to_bookmark(&self, bookmark: &BookmarkName) -> bool {
+        self.bookmark_regexes.is_empty()
+            || self
+                .bookmark_regexes
+                .iter()
+                .any(|regex| regex.is_match(bookmark.as_str()))
+    }
+
+    fn is_allowed(&self, identities: &MononokeIdentitySet) -> bool {
+        identities
+            .iter()
+            .any(|id| self.allowed_identities.contains(id))
+    }
+}
+
+#[async_trait]
+impl ChangesetHook for BlockMergeCommits {
+    async fn run<'this: 'cs, 'ctx: 'this, 'cs, 'fetcher: 'cs>(
+        &'this self,
+        ctx: &'ctx CoreContext,
+        bookmark: &BookmarkName,
+        changeset: &'cs BonsaiChangeset,
+        _content_manager: &'fetcher dyn FileContentManager,
+        _cross_repo_push_source: CrossRepoPushSource,
+        push_authored_by: PushAuthoredBy,
+    ) -> Result<HookExecution> {
+        if push_authored_by.service() {
+            return Ok(HookExecution::Accepted);
+        }
+        if !changeset.is_merge() || !self.applies_to_bookmark(bookmark) {
+            return Ok(HookExecution::Accepted);
+        }
+        if self.is_allowed(ctx.metadata().identities()) {
+            return Ok(HookExecution::Accepted);
+        }
+
+        Ok(HookExecution::Rejected(HookRejectionInfo::new_long(
+            "Merge commits are not allowed",
+            format!(
+                "Commit has {} parents, but merge commits are not allowed on bookmark {}. Rebase your changes instead.",
+                changeset.parents().count(),
+                bookmark,
+            ),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::anyhow;
+    use anyhow::Error;
+    use blobstore::Loadable;
+    use borrowed::borrowed;
+    use fbinit::FacebookInit;
+    use hooks_content_stores::RepoFileContentManager;
+    use tests_utils::BasicTestRepo;
+    use tests_utils::CreateCommitContext;
+
+    use super::*;
+
+    #[fbinit::test]
+    async fn test_block_merge_commits(fb: FacebookInit) -> Result<(), Error> {
+        let ctx = CoreContext::test_mock(fb);
+        let repo: BasicTestRepo = test_repo_factory::build_empty(fb)?;
+        borrowed!(ctx, repo);
+
+        let p1 = CreateCommitContext::new_root(ctx, repo)
+            .add_file("a", "a")
+            .commit()
+            .await?;
+        let p2 = CreateCommitContext::new_root(ctx, repo)
+            .add_file("b", "b")
+            .commit()
+            .await?;
+        let merge = CreateCommitContext::new(ctx, repo, vec![p1, p2])
+            .commit()
+            .await?;
+        let p1 = p1.load(ctx, &repo.repo_blobstore).await?;
+        let merge = merge.load(ctx, &repo.repo_blobstore).await?;
+
+        let content_manager = RepoFileContentManager::new(&repo);
+        let hook = BlockMergeCommits::builder()
+            .bookmark_regexes(["^release/"])
+            .build()?;
+
+        for (bcs, bookmark, accepted) in [
+            (&merge, "release/1", false),
+            (&merge, "main", true),
+            (&p1, "release/1", true),
+        ] {
+            let hook_execution = hook
+                .run(
+                    ctx,
+                    &BookmarkName::new(bookmark)?,
+                    bcs,
+                    &content_manager,
+                    CrossRepoPushSource::NativeToThisRepo,
+                    PushAuthoredBy::User,
+                )
+                .await?;
+            match (hook_execution, accepted) {
+                (HookExecution::Accepted, true) => {}
+                (HookExecution::Rejected(info), false) => {
+                    assert!(info.long_description.contains("Commit has 2 parents"));
+                }
+                (execution, _) => {
+                    return Err(anyhow!("unexpected {:?} for {}", execution, bookmark));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_allowed_identities() -> Result<()> {
+        let hook = BlockMergeCommits::builder()
+            .allowed_identities(["SERVICE_IDENTITY:merger"])
+            .build()?;
+        let mut identities = MononokeIdentitySet::new();
+        identities.insert(MononokeIdentity::new("USER", "alice"));
+        assert!(!hook.is_allowed(&identities));
+        identities.insert(MononokeIdentity::new("SERVICE_IDENTITY", "merger"));
+        assert!(hook.is_allowed(&identities));
+
+        assert!(
+            BlockMergeCommits::builder()
+                .allowed_identities(["merger"])
+                .build()
+                .is_err()
+        );
+        Ok(())
+    }
+}
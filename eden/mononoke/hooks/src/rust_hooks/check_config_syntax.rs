@@ -0,0 +1,238 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use context::CoreContext;
+use globset::GlobBuilder;
+use globset::GlobSet;
+use globset::GlobSetBuilder;
+use metaconfig_types::HookConfig;
+use mononoke_types::BasicFileChange;
+use mononoke_types::MPath;
+use serde::Deserialize;
+
+use crate::CrossRepoPushSource;
+use crate::FileContentManager;
+use crate::FileHook;
+use crate::HookExecution;
+use crate::HookRejectionInfo;
+use crate::PushAuthoredBy;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    fn name(self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Yaml => "YAML",
+            Format::Toml => "TOML",
+        }
+    }
+
+    /// Parse `content`, and return the syntax error if there is one.
+    fn check(self, content: &[u8]) -> Option<String> {
+        match self {
+            Format::Json => serde_json::from_slice::<serde_json::Value>(content)
+                .err()
+                .map(|e| e.to_string()),
+            Format::Yaml => serde_yaml::Deserializer::from_slice(content)
+                .find_map(|document| serde_yaml::Value::deserialize(document).err())
+                .map(|e| e.to_string()),
+            Format::Toml => match std::str::from_utf8(content) {
+                Ok(content) => toml::from_str::<toml::Value>(content)
+                    .err()
+                    .map(|e| e.to_string()),
+                Err(e) => Some(e.to_string()),
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CheckConfigSyntaxBuilder {
+    json_globs: Vec<String>,
+    yaml_globs: Vec<String>,
+    toml_globs: Vec<String>,
+}
+
+impl CheckConfigSyntaxBuilder {
+    pub fn set_from_config(mut self, config: &HookConfig) -> Self {
+        if let Some(v) = config.string_lists.get("json_globs") {
+            self = self.json_globs(v)
+        }
+        if let Some(v) = config.string_lists.get("yaml_globs") {
+            self = self.yaml_globs(v)
+        }
+        if let Some(v) = config.string_lists.get("toml_globs") {
+            self = self.toml_globs(v)
+        }
+        self
+    }
+
+    /// Globs, like "**/*.json", of paths that must be valid JSON.
+    pub fn json_globs(mut self, strs: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.json_globs = strs.into_iter().map(|s| String::from(s.as_ref())).collect();
+        self
+    }
+
+    /// Globs of paths that must be valid YAML.
+    pub fn yaml_globs(mut self, strs: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.yaml_globs = strs.into_iter().map(|s| String::from(s.as_ref())).collect();
+        self
+    }
+
+    /// Globs of paths that must be valid TOML.
+    pub fn toml_globs(mut self, strs: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.toml_globs = strs.into_iter().map(|s| String::from(s.as_ref())).collect();
+        self
+    }
+
+    pub fn build(self) -> Result<CheckConfigSyntax> {
+        if self.json_globs.is_empty() && self.yaml_globs.is_empty() && self.toml_globs.is_empty() {
+            return Err(anyhow!(
+                "Failed to initialize check_config_syntax hook. None of 'json_globs', 'yaml_globs', or 'toml_globs' is set."
+            ));
+        }
+        Ok(CheckConfigSyntax {
+            formats: vec![
+                (build_glob_set(&self.json_globs)?, Format::Json),
+                (build_glob_set(&self.yaml_globs)?, Format::Yaml),
+                (build_glob_set(&self.toml_globs)?, Format::Toml),
+            ],
+        })
+    }
+}
+
+/// Globs match the whole path, and `*` does not match `/`.
+fn build_glob_set(globs: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(
+            GlobBuilder::new(glob)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Failed to parse glob '{}'", glob))?,
+        );
+    }
+    Ok(builder.build()?)
+}
+
+/// Hook to reject JSON, YAML, and TOML files with syntax errors, before they
+/// break tools that read them.
+///
+/// Files that cannot be fetched as text, like large files, are not checked.
+pub struct CheckConfigSyntax {
+    /// The first matching glob set decides the format.
+    formats: Vec<(GlobSet, Format)>,
+}
+
+impl CheckConfigSyntax {
+    pub fn builder() -> CheckConfigSyntaxBuilder {
+        CheckConfigSyntaxBuilder::default()
+    }
+
+    fn format_for_path(&self, path: &MPath) -> Option<Format> {
+        let path = path.to_vec();
+        let path = std::str::from_utf8(&path).ok()?;
+        self.formats
+            .iter()
+            .find(|(globs, _)| globs.is_match(path))
+            .map(|(_, format)| *format)
+    }
+}
+
+#[async_trait]
+impl FileHook for CheckConfigSyntax {
+    async fn run<'this: 'change, 'ctx: 'this, 'change, 'fetcher: 'change, 'path: 'change>(
+        &'this self,
+        ctx: &'ctx CoreContext,
+        content_manager: &'fetcher dyn FileContentManager,
+        change: Option<&'change BasicFileChange>,
+        path: &'path MPath,
+        cross_repo_push_source: CrossRepoPushSource,
+        push_authored_by: PushAuthoredBy,
+    ) -> Result<HookExecution> {
+        if push_authored_by.service() {
+            return Ok(HookExecution::Accepted);
+        }
+        if cross_repo_push_source == CrossRepoPushSource::PushRedirected {
+            // For push-redirected pushes we rely on the hook
+            // running in the original repo
+            return Ok(HookExecution::Accepted);
+        }
+        let change = match change {
+            Some(change) => change,
+            None => return Ok(HookExecution::Accepted),
+        };
+        let format = match self.format_for_path(path) {
+            Some(format) => format,
+            None => return Ok(HookExecution::Accepted),
+        };
+
+        let text = content_manager
+            .get_file_text(ctx, change.content_id())
+            .await?;
+        if let Some(error) = text.and_then(|text| format.check(&text)) {
+            return Ok(HookExecution::Rejected(HookRejectionInfo::new_long(
+                "Invalid config syntax",
+                format!(
+                    "ABORT: '{}' is not valid {}: {}",
+                    path,
+                    format.name(),
+                    error
+                ),
+            )));
+        }
+        Ok(HookExecution::Accepted)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        assert_eq!(Format::Json.check(br#"{"a": [1, 2]}"#), None);
+        assert!(Format::Json.check(br#"{"a": [1, 2}"#).is_some());
+
+        assert_eq!(Format::Yaml.check(b"a:\n  - 1\n---\nb: 2\n"), None);
+        assert_eq!(Format::Yaml.check(b""), None);
+        assert!(Format::Yaml.check(b"a: 1\n---\nb: [1\n").is_some());
+
+        assert_eq!(Format::Toml.check(b"[a]\nb = 1\n"), None);
+        assert!(Format::Toml.check(b"[a]\nb = \n").is_some());
+        assert!(Format::Toml.check(b"a = \"\xff\"").is_some());
+    }
+
+    #[test]
+    fn test_format_for_path() {
+        let hook = CheckConfigSyntax::builder()
+            .json_globs(["**/*.json"])
+            .yaml_globs(["config/*.yaml", "config/*.yml"])
+            .toml_globs(["Cargo.toml"])
+            .build()
+            .unwrap();
+        let format = |path: &str| hook.format_for_path(&MPath::new(path).unwrap());
+        assert_eq!(format("a/b/c.json"), Some(Format::Json));
+        assert_eq!(format("c.json"), Some(Format::Json));
+        assert_eq!(format("config/a.yml"), Some(Format::Yaml));
+        assert_eq!(format("config/sub/a.yml"), None);
+        assert_eq!(format("Cargo.toml"), Some(Format::Toml));
+        assert_eq!(format("lib/Cargo.toml"), None);
+
+        assert!(CheckConfigSyntax::builder().build().is_err());
+    }
+}
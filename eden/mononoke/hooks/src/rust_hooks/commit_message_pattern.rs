@@ -0,0 +1,216 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use bookmarks::BookmarkName;
+use context::CoreContext;
+use mononoke_types::BonsaiChangeset;
+use regex::Regex;
+
+use crate::ChangesetHook;
+use crate::CrossRepoPushSource;
+use crate::FileContentManager;
+use crate::HookConfig;
+use crate::HookExecution;
+use crate::HookRejectionInfo;
+use crate::PushAuthoredBy;
+
+const DEFAULT_REQUIRED_MESSAGE: &str = "Commit message must match '${regex}'.";
+const DEFAULT_FORBIDDEN_MESSAGE: &str =
+    "Commit message must not contain '${match}' (matches '${regex}').";
+
+#[derive(Default)]
+pub struct CommitMessagePatternBuilder {
+    required_regexes: Vec<String>,
+    forbidden_regexes: Vec<String>,
+    required_message: Option<String>,
+    forbidden_message: Option<String>,
+}
+
+impl CommitMessagePatternBuilder {
+    pub fn set_from_config(mut self, config: &HookConfig) -> Self {
+        if let Some(v) = config.string_lists.get("required_regexes") {
+            self = self.required_regexes(v)
+        }
+        if let Some(v) = config.string_lists.get("forbidden_regexes") {
+            self = self.forbidden_regexes(v)
+        }
+        if let Some(v) = config.strings.get("required_message") {
+            self = self.required_message(v)
+        }
+        if let Some(v) = config.strings.get("forbidden_message") {
+            self = self.forbidden_message(v)
+        }
+        self
+    }
+
+    /// Regexes that the commit message must match.
+    pub fn required_regexes(mut self, strs: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.required_regexes = strs.into_iter().map(|s| String::from(s.as_ref())).collect();
+        self
+    }
+
+    /// Regexes that the commit message must not match.
+    pub fn forbidden_regexes(mut self, strs: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.forbidden_regexes = strs.into_iter().map(|s| String::from(s.as_ref())).collect();
+        self
+    }
+
+    /// Message shown when a required regex does not match. `${regex}` is
+    /// replaced by the regex.
+    pub fn required_message(mut self, message: impl ToString) -> Self {
+        self.required_message = Some(message.to_string());
+        self
+    }
+
+    /// Message shown when a forbidden regex matches. `${regex}` is replaced
+    /// by the regex, and `${match}` is replaced by the matched text.
+    pub fn forbidden_message(mut self, message: impl ToString) -> Self {
+        self.forbidden_message = Some(message.to_string());
+        self
+    }
+
+    pub fn build(self) -> Result<CommitMessagePattern> {
+        if self.required_regexes.is_empty() && self.forbidden_regexes.is_empty() {
+            return Err(anyhow!(
+                "Failed to initialize commit_message_pattern hook. Neither 'required_regexes' nor 'forbidden_regexes' is set."
+            ));
+        }
+        Ok(CommitMessagePattern {
+            required_regexes: self
+                .required_regexes
+                .iter()
+                .map(|s| Regex::new(s))
+                .collect::<Result<Vec<_>, _>>()
+                .context("Failed to create regex for required_regexes")?,
+            forbidden_regexes: self
+                .forbidden_regexes
+                .iter()
+                .map(|s| Regex::new(s))
+                .collect::<Result<Vec<_>, _>>()
+                .context("Failed to create regex for forbidden_regexes")?,
+            required_message: self
+                .required_message
+                .unwrap_or_else(|| DEFAULT_REQUIRED_MESSAGE.to_string()),
+            forbidden_message: self
+                .forbidden_message
+                .unwrap_or_else(|| DEFAULT_FORBIDDEN_MESSAGE.to_string()),
+        })
+    }
+}
+
+/// Requires the commit message to match some regexes, and not to match
+/// others. For example, require a task ID, or forbid "DO NOT LAND".
+pub struct CommitMessagePattern {
+    required_regexes: Vec<Regex>,
+    forbidden_regexes: Vec<Regex>,
+    required_message: String,
+    forbidden_message: String,
+}
+
+impl CommitMessagePattern {
+    pub fn builder() -> CommitMessagePatternBuilder {
+        CommitMessagePatternBuilder::default()
+    }
+
+    /// Explain why `message` is rejected. Empty if it is accepted.
+    fn check_message(&self, message: &str) -> Vec<String> {
+        let mut problems = Vec::new();
+        for regex in &self.required_regexes {
+            if !regex.is_match(message) {
+                problems.push(self.required_message.replace("${regex}", regex.as_str()));
+            }
+        }
+        for regex in &self.forbidden_regexes {
+            if let Some(m) = regex.find(message) {
+                problems.push(
+                    self.forbidden_message
+                        .replace("${regex}", regex.as_str())
+                        .replace("${match}", m.as_str()),
+                );
+            }
+        }
+        problems
+    }
+}
+
+#[async_trait]
+impl ChangesetHook for CommitMessagePattern {
+    async fn run<'this: 'cs, 'ctx: 'this, 'cs, 'fetcher: 'cs>(
+        &'this self,
+        _ctx: &'ctx CoreContext,
+        _bookmark: &BookmarkName,
+        changeset: &'cs BonsaiChangeset,
+        _content_manager: &'fetcher dyn FileContentManager,
+        cross_repo_push_source: CrossRepoPushSource,
+        push_authored_by: PushAuthoredBy,
+    ) -> Result<HookExecution> {
+        if push_authored_by.service() {
+            return Ok(HookExecution::Accepted);
+        }
+        if cross_repo_push_source == CrossRepoPushSource::PushRedirected {
+            // For push-redirected commits, we rely on running source-repo hooks
+            return Ok(HookExecution::Accepted);
+        }
+
+        let problems = self.check_message(changeset.message());
+        if problems.is_empty() {
+            Ok(HookExecution::Accepted)
+        } else {
+            Ok(HookExecution::Rejected(HookRejectionInfo::new_long(
+                "Invalid commit message",
+                problems.join("\n"),
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_required_and_forbidden() {
+        let hook = CommitMessagePattern::builder()
+            .required_regexes(["(?m)^Task: T[0-9]+$"])
+            .forbidden_regexes(["(?i)do not land"])
+            .build()
+            .unwrap();
+        assert!(hook.check_message("fix\n\nTask: T123").is_empty());
+        assert_eq!(
+            hook.check_message("fix\n\nDo not land\nTask: T123"),
+            ["Commit message must not contain 'Do not land' (matches '(?i)do not land')."]
+        );
+        assert_eq!(
+            hook.check_message("fix"),
+            ["Commit message must match '(?m)^Task: T[0-9]+$'."]
+        );
+    }
+
+    #[test]
+    fn test_custom_messages() {
+        let hook = CommitMessagePattern::builder()
+            .required_regexes(["T[0-9]+"])
+            .required_message("Please add a task ID")
+            .forbidden_regexes(["WIP"])
+            .forbidden_message("${match} commits cannot land")
+            .build()
+            .unwrap();
+        assert_eq!(
+            hook.check_message("WIP"),
+            ["Please add a task ID", "WIP commits cannot land"]
+        );
+    }
+
+    #[test]
+    fn test_missing_config() {
+        assert!(CommitMessagePattern::builder().build().is_err());
+    }
+}
@@ -14,6 +14,7 @@ use context::CoreContext;
 use mononoke_types::BonsaiChangeset;
 use regex::Regex;
 
+use super::check_positive;
 use crate::ChangesetHook;
 use crate::CrossRepoPushSource;
 use crate::FileContentManager;
@@ -29,6 +30,9 @@ pub struct LimitCommitsizeBuilder {
     override_limits: Option<Vec<u64>>,
     ignore_path_regexes: Option<Vec<String>>,
     changed_files_limit: Option<u64>,
+    bookmark_override_regexes: Option<Vec<String>>,
+    bookmark_override_commit_size_limits: Option<Vec<i64>>,
+    bookmark_override_changed_files_limits: Option<Vec<i64>>,
 }
 
 impl LimitCommitsizeBuilder {
@@ -58,6 +62,30 @@ impl LimitCommitsizeBuilder {
         if let Some(v) = config.int_64_lists.get("override_limits") {
             self = self.override_limits(v.iter().map(|i| *i as u64));
         }
+        if let Some(v) = config.string_lists.get("bookmark_override_regexes") {
+            self = self.bookmark_override_regexes(v);
+        }
+        if let Some(v) = config.int_lists.get("bookmark_override_commitsizelimits") {
+            self = self.bookmark_override_commit_size_limits(v.iter().map(|i| i64::from(*i)));
+        }
+        if let Some(v) = config
+            .int_64_lists
+            .get("bookmark_override_commitsizelimits")
+        {
+            self = self.bookmark_override_commit_size_limits(v.iter().copied());
+        }
+        if let Some(v) = config
+            .int_lists
+            .get("bookmark_override_changed_files_limits")
+        {
+            self = self.bookmark_override_changed_files_limits(v.iter().map(|i| i64::from(*i)));
+        }
+        if let Some(v) = config
+            .int_64_lists
+            .get("bookmark_override_changed_files_limits")
+        {
+            self = self.bookmark_override_changed_files_limits(v.iter().copied());
+        }
         self
     }
 
@@ -91,6 +119,37 @@ impl LimitCommitsizeBuilder {
         self
     }
 
+    /// Regexes of bookmarks that use different limits. The first matching
+    /// regex decides the limits.
+    pub fn bookmark_override_regexes(
+        mut self,
+        strs: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Self {
+        self.bookmark_override_regexes =
+            Some(strs.into_iter().map(|s| String::from(s.as_ref())).collect());
+        self
+    }
+
+    /// Commit size limits for bookmarks matching `bookmark_override_regexes`.
+    /// Must be positive.
+    pub fn bookmark_override_commit_size_limits(
+        mut self,
+        limits: impl IntoIterator<Item = i64>,
+    ) -> Self {
+        self.bookmark_override_commit_size_limits = Some(limits.into_iter().collect());
+        self
+    }
+
+    /// Changed files limits for bookmarks matching `bookmark_override_regexes`.
+    /// Must be positive.
+    pub fn bookmark_override_changed_files_limits(
+        mut self,
+        limits: impl IntoIterator<Item = i64>,
+    ) -> Self {
+        self.bookmark_override_changed_files_limits = Some(limits.into_iter().collect());
+        self
+    }
+
     pub fn build(self) -> Result<LimitCommitsize> {
         let regexes = self
             .override_limit_path_regexes
@@ -115,6 +174,43 @@ impl LimitCommitsizeBuilder {
         let regexes_with_limits: Vec<(Regex, u64)> =
             regexes.into_iter().zip(limits.into_iter()).collect();
 
+        let bookmark_regexes = self
+            .bookmark_override_regexes
+            .unwrap_or_default()
+            .into_iter()
+            .map(|s| Regex::new(&s))
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to create regex for bookmark_override_regexes")?;
+        let bookmark_size_limits = bookmark_override_limits(
+            self.bookmark_override_commit_size_limits,
+            bookmark_regexes.len(),
+            "bookmark_override_commitsizelimits",
+        )?;
+        let bookmark_files_limits = bookmark_override_limits(
+            self.bookmark_override_changed_files_limits,
+            bookmark_regexes.len(),
+            "bookmark_override_changed_files_limits",
+        )?;
+        if bookmark_regexes.len() != bookmark_size_limits.len()
+            || bookmark_regexes.len() != bookmark_files_limits.len()
+        {
+            return Err(anyhow!(
+                "Failed to initialize limit_commitsize hook. Lists 'bookmark_override_regexes', 'bookmark_override_commitsizelimits' and 'bookmark_override_changed_files_limits' have different sizes."
+            ));
+        }
+        let bookmark_overrides = bookmark_regexes
+            .into_iter()
+            .zip(bookmark_size_limits)
+            .zip(bookmark_files_limits)
+            .map(
+                |((bookmark_regex, commit_size_limit), changed_files_limit)| BookmarkOverride {
+                    bookmark_regex,
+                    commit_size_limit,
+                    changed_files_limit,
+                },
+            )
+            .collect();
+
         Ok(LimitCommitsize {
             commit_size_limit: self
                 .commit_size_limit
@@ -128,21 +224,67 @@ impl LimitCommitsizeBuilder {
                 .collect::<Result<Vec<_>, _>>()
                 .context("Failed to create regex for ignore_path_regex")?,
             changed_files_limit: self.changed_files_limit,
+            bookmark_overrides,
         })
     }
 }
 
+/// Convert per-bookmark limits from the config. Missing limits mean the
+/// default limits are used for all `len` bookmark regexes.
+fn bookmark_override_limits(
+    limits: Option<Vec<i64>>,
+    len: usize,
+    name: &str,
+) -> Result<Vec<Option<u64>>> {
+    match limits {
+        Some(limits) => limits
+            .into_iter()
+            .map(|limit| Ok(Some(check_positive("limit_commitsize", name, limit)?)))
+            .collect(),
+        None => Ok(vec![None; len]),
+    }
+}
+
+/// Limits used instead of the default ones for matching bookmarks.
+/// `None` means the default limit is used.
+struct BookmarkOverride {
+    bookmark_regex: Regex,
+    commit_size_limit: Option<u64>,
+    changed_files_limit: Option<u64>,
+}
+
 pub struct LimitCommitsize {
     commit_size_limit: u64,
     ignore_path_regexes: Vec<Regex>,
     override_limit_path_regexes_with_limits: Vec<(Regex, u64)>,
     changed_files_limit: Option<u64>,
+    bookmark_overrides: Vec<BookmarkOverride>,
 }
 
 impl LimitCommitsize {
     pub fn builder() -> LimitCommitsizeBuilder {
         LimitCommitsizeBuilder::default()
     }
+
+    /// Commit size limit and changed files limit for `bookmark`.
+    fn limits_for_bookmark(&self, bookmark: &BookmarkName) -> (u64, Option<u64>) {
+        let mut commit_size_limit = self.commit_size_limit;
+        let mut changed_files_limit = self.changed_files_limit;
+        let bookmark = bookmark.as_str();
+        if let Some(o) = self
+            .bookmark_overrides
+            .iter()
+            .find(|o| o.bookmark_regex.is_match(bookmark))
+        {
+            if let Some(limit) = o.commit_size_limit {
+                commit_size_limit = limit;
+            }
+            if let Some(limit) = o.changed_files_limit {
+                changed_files_limit = Some(limit);
+            }
+        }
+        (commit_size_limit, changed_files_limit)
+    }
 }
 
 #[async_trait]
@@ -150,7 +292,7 @@ impl ChangesetHook for LimitCommitsize {
     async fn run<'this: 'cs, 'ctx: 'this, 'cs, 'fetcher: 'cs>(
         &'this self,
         _ctx: &'ctx CoreContext,
-        _bookmark: &BookmarkName,
+        bookmark: &BookmarkName,
         changeset: &'cs BonsaiChangeset,
         _content_manager: &'fetcher dyn FileContentManager,
         cross_repo_push_source: CrossRepoPushSource,
@@ -164,8 +306,10 @@ impl ChangesetHook for LimitCommitsize {
             return Ok(HookExecution::Accepted);
         }
 
+        let (commit_size_limit, changed_files_limit) = self.limits_for_bookmark(bookmark);
+
         // find max commit size based on the files in the changeset
-        let mut max_commit_size_limit = self.commit_size_limit;
+        let mut max_commit_size_limit = commit_size_limit;
         for (path, _) in changeset.file_changes() {
             let path = format!("{}", path);
             let path_size_limit = self
@@ -195,7 +339,7 @@ impl ChangesetHook for LimitCommitsize {
             totalsize += file_change.size().unwrap_or(0);
         }
 
-        if let Some(changed_files_limit) = self.changed_files_limit {
+        if let Some(changed_files_limit) = changed_files_limit {
             if num_changed_files > changed_files_limit {
                 return Ok(HookExecution::Rejected(HookRejectionInfo::new_long(
                     "Commit too large",
@@ -472,6 +616,102 @@ mod test {
         Ok(())
     }
 
+    #[fbinit::test]
+    async fn test_limitcommitsize_bookmark_override(fb: FacebookInit) -> Result<(), Error> {
+        let ctx = CoreContext::test_mock(fb);
+        let repo: BasicTestRepo = test_repo_factory::build_empty(fb)?;
+        borrowed!(ctx, repo);
+
+        let cs_id = CreateCommitContext::new_root(ctx, repo)
+            .add_file("dir/a", "a")
+            .add_file("dir/b", "b")
+            .add_file("dir/c", "c")
+            .commit()
+            .await?;
+
+        let bcs = cs_id.load(ctx, &repo.repo_blobstore).await?;
+
+        let content_manager = RepoFileContentManager::new(&repo);
+        let config = HookConfig {
+            ints_64: hashmap! {
+                "commitsizelimit".to_string() => 1,
+                "changed_files_limit".to_string() => 3,
+            },
+            string_lists: hashmap! {
+                "bookmark_override_regexes".to_string() => vec![
+                    "^release/.*$".to_string(),
+                    "^strict$".to_string(),
+                ],
+            },
+            int_64_lists: hashmap! {
+                "bookmark_override_commitsizelimits".to_string() => vec![3, 3],
+                "bookmark_override_changed_files_limits".to_string() => vec![3, 2],
+            },
+            ..Default::default()
+        };
+        let hook = LimitCommitsize::builder()
+            .set_from_config(&config)
+            .build()?;
+
+        let mut results = Vec::new();
+        for bookmark in ["book", "release/1", "strict"] {
+            let hook_execution = hook
+                .run(
+                    ctx,
+                    &BookmarkName::new(bookmark)?,
+                    &bcs,
+                    &content_manager,
+                    CrossRepoPushSource::NativeToThisRepo,
+                    PushAuthoredBy::User,
+                )
+                .await?;
+            results.push(match hook_execution {
+                HookExecution::Accepted => "accepted".to_string(),
+                HookExecution::Rejected(info) => info.long_description,
+            });
+        }
+        // The default commit size limit is 1 byte.
+        assert!(results[0].contains("commit 3 bytes"));
+        // The release bookmark allows 3 bytes.
+        assert_eq!(results[1], "accepted");
+        // The strict bookmark allows 3 bytes, but only 2 files.
+        assert!(results[2].contains("changed 3 files"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_limitcommitsize_bookmark_override_config() {
+        let regexes = || {
+            hashmap! {
+                "bookmark_override_regexes".to_string() => vec!["^strict$".to_string()],
+            }
+        };
+        let ints_64 = || hashmap! {"commitsizelimit".to_string() => 1};
+
+        // 32-bit lists are read too.
+        let hook = build_hook_with_limits(
+            ints_64(),
+            regexes(),
+            hashmap! {"bookmark_override_changed_files_limits".to_string() => vec![2]},
+        )
+        .unwrap();
+        assert_eq!(hook.bookmark_overrides[0].changed_files_limit, Some(2));
+        assert_eq!(hook.bookmark_overrides[0].commit_size_limit, None);
+
+        let err = build_hook_with_limits(
+            ints_64(),
+            regexes(),
+            hashmap! {"bookmark_override_commitsizelimits".to_string() => vec![0]},
+        )
+        .err()
+        .unwrap();
+        assert!(
+            format!("{:#}", err)
+                .contains("'bookmark_override_commitsizelimits' must be positive, got 0")
+        );
+    }
+
     fn build_hook(ints_64: HashMap<String, i64>) -> Result<LimitCommitsize> {
         build_hook_with_limits(ints_64, hashmap! {}, hashmap! {})
     }
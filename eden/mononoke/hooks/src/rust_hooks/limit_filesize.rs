@@ -14,6 +14,8 @@ use mononoke_types::BasicFileChange;
 use mononoke_types::MPath;
 use regex::Regex;
 
+use super::check_positive;
+use super::get_int;
 use crate::CrossRepoPushSource;
 use crate::FileContentManager;
 use crate::FileHook;
@@ -25,17 +27,26 @@ use crate::PushAuthoredBy;
 #[derive(Default)]
 pub struct LimitFilesizeBuilder {
     path_regexes: Option<Vec<String>>,
-    limits: Option<Vec<i32>>,
+    limits: Option<Vec<i64>>,
+    default_limit: Option<i64>,
 }
 
 impl LimitFilesizeBuilder {
     pub fn set_from_config(mut self, config: &HookConfig) -> Self {
+        // Please note that the _i64 configs override any i32s one with the same key.
         if let Some(v) = config.string_lists.get("filesize_limits_regexes") {
             self = self.filesize_limits_regexes(v)
         }
 
         if let Some(v) = config.int_lists.get("filesize_limits_values") {
-            self.limits = Some(v.clone())
+            self = self.filesize_limits_values(v.iter().map(|i| *i as i64))
+        }
+        if let Some(v) = config.int_64_lists.get("filesize_limits_values") {
+            self = self.filesize_limits_values(v.iter().copied())
+        }
+
+        if let Some(v) = get_int(config, "filesize_limit") {
+            self = self.filesize_limit(v)
         }
 
         self
@@ -49,33 +60,70 @@ impl LimitFilesizeBuilder {
         self
     }
 
+    /// Limits for files matching `filesize_limits_regexes`. A negative
+    /// limit means files matching the regex are not limited.
+    pub fn filesize_limits_values(mut self, limits: impl IntoIterator<Item = i64>) -> Self {
+        self.limits = Some(limits.into_iter().collect());
+        self
+    }
+
+    /// Limit for files that do not match any of `filesize_limits_regexes`.
+    /// Must be positive.
+    pub fn filesize_limit(mut self, limit: i64) -> Self {
+        self.default_limit = Some(limit);
+        self
+    }
+
     pub fn build(self) -> Result<LimitFilesize> {
-        if let (Some(regexes_str), Some(limits)) = (self.path_regexes, self.limits) {
-            if regexes_str.is_empty() || limits.is_empty() {
-                return Err(anyhow!(
-                    "Failed to initialize limit_filesize hook. Either 'filesize_limits_regexes' or 'filesize_limits_values' list is empty."
-                ));
-            }
-            let regexes = regexes_str
-                .into_iter()
-                .map(|s| Regex::new(&s))
-                .collect::<Result<Vec<_>, _>>()
-                .context("Failed to create regex for path_regexes")?;
+        let default_limit = self
+            .default_limit
+            .map(|limit| check_positive("limit_filesize", "filesize_limit", limit))
+            .transpose()?;
+        match (self.path_regexes, self.limits) {
+            (None, None) => match default_limit {
+                Some(default_limit) => Ok(LimitFilesize {
+                    path_regexes_with_limits: Vec::new(),
+                    default_limit: Some(default_limit),
+                }),
+                None => Err(anyhow!(
+                    "Failed to initialize limit_filesize hook. Either 'filesize_limits_regexes' and 'filesize_limits_values', or 'filesize_limit' must be set."
+                )),
+            },
+            (Some(regexes_str), Some(limits)) => {
+                if regexes_str.is_empty() || limits.is_empty() {
+                    return Err(anyhow!(
+                        "Failed to initialize limit_filesize hook. Either 'filesize_limits_regexes' or 'filesize_limits_values' list is empty."
+                    ));
+                }
+                let regexes = regexes_str
+                    .into_iter()
+                    .map(|s| Regex::new(&s))
+                    .collect::<Result<Vec<_>, _>>()
+                    .context("Failed to create regex for path_regexes")?;
 
-            let limits: Vec<Option<u64>> = limits.into_iter().map(|n| n.try_into().ok()).collect();
+                let limits: Vec<Option<u64>> =
+                    limits.into_iter().map(|n| n.try_into().ok()).collect();
 
-            return Ok(LimitFilesize {
-                path_regexes_with_limits: regexes.into_iter().zip(limits.into_iter()).collect(),
-            });
+                Ok(LimitFilesize {
+                    path_regexes_with_limits: regexes.into_iter().zip(limits.into_iter()).collect(),
+                    default_limit,
+                })
+            }
+            (Some(_), None) => Err(anyhow!(
+                "Failed to initialize limit_filesize hook. 'filesize_limits_regexes' is set, but 'filesize_limits_values' is missing."
+            )),
+            (None, Some(_)) => Err(anyhow!(
+                "Failed to initialize limit_filesize hook. 'filesize_limits_values' is set, but 'filesize_limits_regexes' is missing."
+            )),
         }
-        Err(anyhow!(
-            "Failed to initialize limit_filesize hook. Either 'filesize_limits_regexes' or 'filesize_limits_values' option is missing."
-        ))
     }
 }
 
 pub struct LimitFilesize {
+    /// The first matching regex decides the limit. `None` means no limit.
     path_regexes_with_limits: Vec<(Regex, Option<u64>)>,
+    /// Limit for files not matching any regex.
+    default_limit: Option<u64>,
 }
 
 impl LimitFilesize {
@@ -130,6 +178,113 @@ impl FileHook for LimitFilesize {
                 }
             }
         }
-        Ok(HookExecution::Accepted)
+        match self.default_limit {
+            Some(limit) if len > limit => Ok(HookExecution::Rejected(HookRejectionInfo::new_long(
+                "File too large",
+                format!(
+                    "File size limit is {} bytes. You tried to push file {} that is over the limit ({} bytes). See https://fburl.com/landing_big_diffs for instructions.",
+                    limit, path, len
+                ),
+            ))),
+            _ => Ok(HookExecution::Accepted),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Error;
+    use blobstore::Loadable;
+    use borrowed::borrowed;
+    use fbinit::FacebookInit;
+    use hooks_content_stores::RepoFileContentManager;
+    use maplit::hashmap;
+    use tests_utils::BasicTestRepo;
+    use tests_utils::CreateCommitContext;
+
+    use super::*;
+
+    #[fbinit::test]
+    async fn test_limit_filesize_default_limit(fb: FacebookInit) -> Result<(), Error> {
+        let ctx = CoreContext::test_mock(fb);
+        let repo: BasicTestRepo = test_repo_factory::build_empty(fb)?;
+        borrowed!(ctx, repo);
+
+        let cs_id = CreateCommitContext::new_root(ctx, repo)
+            .add_file("small", "a")
+            .add_file("large", "aaaa")
+            .add_file("dir/large", "aaaa")
+            .commit()
+            .await?;
+        let bcs = cs_id.load(ctx, &repo.repo_blobstore).await?;
+        let content_manager = RepoFileContentManager::new(&repo);
+
+        let config = HookConfig {
+            string_lists: hashmap! {
+                "filesize_limits_regexes".to_string() => vec!["^dir/".to_string()],
+            },
+            int_64_lists: hashmap! {
+                "filesize_limits_values".to_string() => vec![10],
+            },
+            ints_64: hashmap! {
+                "filesize_limit".to_string() => 2,
+            },
+            ..Default::default()
+        };
+        let hook = LimitFilesize::builder().set_from_config(&config).build()?;
+
+        let mut rejected = Vec::new();
+        for (path, change) in bcs.simplified_file_changes() {
+            let hook_execution = hook
+                .run(
+                    ctx,
+                    &content_manager,
+                    change,
+                    path,
+                    CrossRepoPushSource::NativeToThisRepo,
+                    PushAuthoredBy::User,
+                )
+                .await?;
+            if let HookExecution::Rejected(info) = hook_execution {
+                assert!(info.long_description.contains("over the limit (4 bytes)"));
+                rejected.push(path.to_string());
+            }
+        }
+        // "dir/large" matches the regex with a higher limit.
+        assert_eq!(rejected, vec!["large".to_string()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_limit_filesize_invalid_config() {
+        let config = HookConfig {
+            ints_64: hashmap! {
+                "filesize_limit".to_string() => -1,
+            },
+            ..Default::default()
+        };
+        let err = LimitFilesize::builder()
+            .set_from_config(&config)
+            .build()
+            .err()
+            .unwrap();
+        assert!(format!("{:#}", err).contains("'filesize_limit' must be positive, got -1"));
+
+        let config = HookConfig {
+            string_lists: hashmap! {
+                "filesize_limits_regexes".to_string() => vec!["^dir/".to_string()],
+            },
+            ints: hashmap! {
+                "filesize_limit".to_string() => 2,
+            },
+            ..Default::default()
+        };
+        let err = LimitFilesize::builder()
+            .set_from_config(&config)
+            .build()
+            .err()
+            .unwrap();
+        assert!(format!("{:#}", err).contains("'filesize_limits_values' is missing"));
     }
 }
@@ -14,6 +14,8 @@ use mercurial_types::simple_fsencode;
 use mononoke_types::BasicFileChange;
 use mononoke_types::MPath;
 
+use super::check_positive;
+use super::get_int;
 use crate::CrossRepoPushSource;
 use crate::FileContentManager;
 use crate::FileHook;
@@ -25,21 +27,48 @@ use crate::PushAuthoredBy;
 // The filesystem max is 255.
 const MAX_PATH_COMPONENT_LIMIT: usize = 255;
 
+// Windows paths are limited to 260 characters (MAX_PATH), including the
+// terminating NUL.
+const DEFAULT_LENGTH_LIMIT: usize = 260;
+
+// NTFS limits path components to 255 characters.
+const DEFAULT_COMPONENT_LENGTH_LIMIT: usize = 255;
+
 #[derive(Clone, Debug)]
 pub struct LimitPathLengthHook {
+    /// Paths must be shorter than this, in characters as counted by
+    /// Windows.
     length_limit: usize,
+    /// Path components must not be longer than this, in characters as
+    /// counted by Windows.
+    component_length_limit: usize,
 }
 
 impl LimitPathLengthHook {
     pub fn new(config: &HookConfig) -> Result<Self, Error> {
-        let length_limit = config
-            .strings
-            .get("length_limit")
-            .ok_or_else(|| Error::msg("Required config length_limit is missing"))?;
-
-        let length_limit = length_limit.parse().context("While parsing length_limit")?;
+        Ok(Self {
+            length_limit: read_limit(config, "length_limit", DEFAULT_LENGTH_LIMIT)?,
+            component_length_limit: read_limit(
+                config,
+                "component_length_limit",
+                DEFAULT_COMPONENT_LENGTH_LIMIT,
+            )?,
+        })
+    }
+}
 
-        Ok(Self { length_limit })
+/// Read the limit `name` from `strings`, `ints_64` or `ints`, in that order.
+fn read_limit(config: &HookConfig, name: &str, default: usize) -> Result<usize, Error> {
+    let limit = match config.strings.get(name) {
+        Some(v) => Some(
+            v.parse()
+                .with_context(|| format!("While parsing {}", name))?,
+        ),
+        None => get_int(config, name),
+    };
+    match limit {
+        Some(limit) => check_positive("limit_path_length", name, limit),
+        None => Ok(default),
     }
 }
 
@@ -62,13 +91,19 @@ impl FileHook for LimitPathLengthHook {
             return Ok(HookExecution::Accepted);
         }
 
+        // Check the configured limit first, so that its message is shown for
+        // components that are too long in both checks.
+        if let Some(rejection) = check_component_length(path, self.component_length_limit) {
+            return Ok(rejection);
+        }
+
         // Encode file in mercurial encoding to make sure mercurial can accept those files
         // as well
         if let Some(rejection) = check_path(path)? {
             return Ok(rejection);
         }
 
-        let len = path.len();
+        let len = wchar_len(path);
 
         let execution = if len >= self.length_limit {
             HookExecution::Rejected(HookRejectionInfo::new_long(
@@ -86,6 +121,27 @@ impl FileHook for LimitPathLengthHook {
     }
 }
 
+/// Length of `path` in characters as counted by Windows, including
+/// separators.
+fn wchar_len(path: &MPath) -> usize {
+    let components = path.into_iter().map(|component| component.wchar_len());
+    components.sum::<usize>() + path.num_components() - 1
+}
+
+fn check_component_length(path: &MPath, limit: usize) -> Option<HookExecution> {
+    let (component, len) = path
+        .into_iter()
+        .map(|component| (component, component.wchar_len()))
+        .find(|(_, len)| *len > limit)?;
+    Some(HookExecution::Rejected(HookRejectionInfo::new_long(
+        "Path component too long",
+        format!(
+            "Path component '{}' in '{}' is {} characters long, which exceeds the limit of {}",
+            component, path, len, limit,
+        ),
+    )))
+}
+
 fn check_path(path: &MPath) -> Result<Option<HookExecution>, Error> {
     let mut elements = path
         .as_ref()
@@ -130,6 +186,60 @@ mod test {
         assert!(check_path(&path).unwrap().is_some());
     }
 
+    #[test]
+    fn test_component_length() {
+        let path = MPath::new(format!("dir/{}/file", "\u{e9}".repeat(10))).unwrap();
+        assert!(check_component_length(&path, 10).is_none());
+        match check_component_length(&path, 9) {
+            Some(HookExecution::Rejected(info)) => assert!(info.long_description.starts_with(
+                "Path component '\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}' in"
+            )),
+            _ => panic!("expected rejection"),
+        }
+    }
+
+    #[test]
+    fn test_wchar_len() {
+        let path = MPath::new(format!("dir/{}", "\u{e9}".repeat(10))).unwrap();
+        assert_eq!(wchar_len(&path), 14);
+        assert_eq!(wchar_len(&MPath::new("a").unwrap()), 1);
+    }
+
+    #[test]
+    fn test_default_limits() {
+        let hook = LimitPathLengthHook::new(&HookConfig::default()).unwrap();
+        assert_eq!(hook.length_limit, DEFAULT_LENGTH_LIMIT);
+        assert_eq!(hook.component_length_limit, DEFAULT_COMPONENT_LENGTH_LIMIT);
+    }
+
+    #[test]
+    fn test_limits_from_config() {
+        let config = HookConfig {
+            strings: maplit::hashmap! {
+                "component_length_limit".to_string() => "100".to_string(),
+            },
+            ints: maplit::hashmap! {
+                "length_limit".to_string() => 200,
+            },
+            ..Default::default()
+        };
+        let hook = LimitPathLengthHook::new(&config).unwrap();
+        assert_eq!(hook.length_limit, 200);
+        assert_eq!(hook.component_length_limit, 100);
+
+        let config = HookConfig {
+            ints: maplit::hashmap! {
+                "component_length_limit".to_string() => 0,
+            },
+            ..Default::default()
+        };
+        let err = LimitPathLengthHook::new(&config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Failed to initialize limit_path_length hook. 'component_length_limit' must be positive, got 0."
+        );
+    }
+
     #[test]
     fn test_path_ok() {
         let path = MPath::new("flib/intern/__generated__/GraphQLFetchersMeerkatStep/ic/GQLG_File__EntIcxPositionSearchHitWorkdayPositionViewStateJunction__GraphQLFacebookInternalTypeSetFetcherWrapper.php").unwrap();
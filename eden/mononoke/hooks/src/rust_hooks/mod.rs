@@ -9,7 +9,10 @@
 
 mod always_fail_changeset;
 mod block_empty_commit;
+mod block_merge_commits;
+mod check_config_syntax;
 mod check_nocommit;
+mod commit_message_pattern;
 mod conflict_markers;
 pub(crate) mod deny_files;
 mod limit_commit_message_length;
@@ -19,10 +22,18 @@ mod limit_path_length;
 mod lua_pattern;
 pub(crate) mod no_bad_extensions;
 pub(crate) mod no_bad_filenames;
+mod no_binary_files;
+mod no_case_conflicts;
 mod no_insecure_filenames;
+mod no_invisible_filenames;
 pub(crate) mod no_questionable_filenames;
+mod no_secrets;
 pub(crate) mod no_windows_filenames;
+mod require_trailers;
+mod symlink_policy;
 
+use anyhow::anyhow;
+use anyhow::Context;
 use anyhow::Result;
 use fbinit::FacebookInit;
 use futures::future::Future;
@@ -53,12 +64,24 @@ pub fn hook_name_to_changeset_hook<'a>(
         Ok(match name {
             "always_fail_changeset" => Some(b(always_fail_changeset::AlwaysFailChangeset::new())),
             "block_empty_commit" => Some(b(block_empty_commit::BlockEmptyCommit::new())),
+            "block_merge_commits" => Some(b(block_merge_commits::BlockMergeCommits::builder()
+                .set_from_config(config)
+                .build()?)),
+            "commit_message_pattern" => {
+                Some(b(commit_message_pattern::CommitMessagePattern::builder()
+                    .set_from_config(config)
+                    .build()?))
+            }
             "limit_commit_message_length" => Some(b(
                 limit_commit_message_length::LimitCommitMessageLength::new(config)?,
             )),
             "limit_commitsize" => Some(b(limit_commitsize::LimitCommitsize::builder()
                 .set_from_config(config)
                 .build()?)),
+            "no_case_conflicts" => Some(b(no_case_conflicts::NoCaseConflicts::new())),
+            "require_trailers" => Some(b(require_trailers::RequireTrailers::builder()
+                .set_from_config(config)
+                .build()?)),
             _ => None,
         })
     }
@@ -70,6 +93,11 @@ pub fn hook_name_to_file_hook(
     config: &HookConfig,
 ) -> Result<Option<Box<dyn FileHook + 'static>>> {
     Ok(match name {
+        "check_config_syntax" => Some(Box::new(
+            check_config_syntax::CheckConfigSyntax::builder()
+                .set_from_config(config)
+                .build()?,
+        )),
         "check_nocommit" => Some(Box::new(check_nocommit::CheckNocommitHook::new(config)?)),
         "conflict_markers" => Some(Box::new(conflict_markers::ConflictMarkers::new())),
         "deny_files" => Some(Box::new(
@@ -95,19 +123,69 @@ pub fn hook_name_to_file_hook(
                 .set_from_config(config)
                 .build()?,
         )),
+        "no_binary_files" => Some(Box::new(
+            no_binary_files::NoBinaryFiles::builder()
+                .set_from_config(config)
+                .build()?,
+        )),
         "no_insecure_filenames" => {
             Some(Box::new(no_insecure_filenames::NoInsecureFilenames::new()?))
         }
+        "no_invisible_filenames" => Some(Box::new(
+            no_invisible_filenames::NoInvisibleFilenames::builder()
+                .set_from_config(config)
+                .build()?,
+        )),
         "no_questionable_filenames" => Some(Box::new(
             no_questionable_filenames::NoQuestionableFilenames::builder()
                 .set_from_config(config)
                 .build()?,
         )),
+        "no_secrets" => Some(Box::new(
+            no_secrets::NoSecrets::builder()
+                .set_from_config(config)
+                .build()?,
+        )),
         "no_windows_filenames" => Some(Box::new(
             no_windows_filenames::NoWindowsFilenames::builder()
                 .set_from_config(config)
                 .build()?,
         )),
+        "symlink_policy" => Some(Box::new(
+            symlink_policy::SymlinkPolicy::builder()
+                .set_from_config(config)
+                .build()?,
+        )),
         _ => None,
     })
 }
+
+/// Read the integer config `key`. Like other hooks, `ints_64` overrides
+/// `ints` with the same key.
+pub(crate) fn get_int(config: &HookConfig, key: &str) -> Option<i64> {
+    match config.ints_64.get(key) {
+        Some(v) => Some(*v),
+        None => config.ints.get(key).map(|v| (*v).into()),
+    }
+}
+
+/// Check that the config value `key` of `hook` is positive, and convert it.
+pub(crate) fn check_positive<T: TryFrom<i64>>(hook: &str, key: &str, value: i64) -> Result<T>
+where
+    T::Error: std::error::Error + Send + Sync + 'static,
+{
+    if value <= 0 {
+        return Err(anyhow!(
+            "Failed to initialize {} hook. '{}' must be positive, got {}.",
+            hook,
+            key,
+            value
+        ));
+    }
+    value.try_into().with_context(|| {
+        format!(
+            "Failed to initialize {} hook. '{}' is too large.",
+            hook, key
+        )
+    })
+}
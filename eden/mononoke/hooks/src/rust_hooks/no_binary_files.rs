@@ -0,0 +1,246 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::HashSet;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use context::CoreContext;
+use metaconfig_types::HookConfig;
+use mononoke_types::BasicFileChange;
+use mononoke_types::FileType;
+use mononoke_types::MPath;
+use regex::bytes::Regex;
+
+use super::check_positive;
+use super::get_int;
+use crate::CrossRepoPushSource;
+use crate::FileContentManager;
+use crate::FileHook;
+use crate::HookExecution;
+use crate::HookRejectionInfo;
+use crate::PushAuthoredBy;
+
+/// Same as git, which looks for NUL bytes in the first 8000 bytes.
+const DEFAULT_SNIFF_BYTES: usize = 8000;
+
+#[derive(Default)]
+pub struct NoBinaryFilesBuilder<'a> {
+    text_extensions: Vec<String>,
+    allowed_paths: Option<&'a str>,
+    sniff_bytes: Option<i64>,
+}
+
+impl<'a> NoBinaryFilesBuilder<'a> {
+    pub fn set_from_config(mut self, config: &'a HookConfig) -> Self {
+        if let Some(v) = config.string_lists.get("text_extensions") {
+            self = self.text_extensions(v)
+        }
+        if let Some(v) = config.strings.get("allowed_paths") {
+            self = self.allowed_paths(v)
+        }
+        if let Some(v) = get_int(config, "sniff_bytes") {
+            self = self.sniff_bytes(v)
+        }
+        self
+    }
+
+    /// Extensions, like "txt", of files that must not be binary anywhere.
+    pub fn text_extensions(mut self, strs: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.text_extensions = strs
+            .into_iter()
+            .map(|s| s.as_ref().to_ascii_lowercase())
+            .collect();
+        self
+    }
+
+    /// If set, binary files are only allowed on paths matching this regex.
+    pub fn allowed_paths(mut self, regex: &'a str) -> Self {
+        self.allowed_paths = Some(regex);
+        self
+    }
+
+    /// Number of bytes at the start of a file that are checked for NUL
+    /// bytes. Must be positive. Defaults to 8000.
+    pub fn sniff_bytes(mut self, sniff_bytes: i64) -> Self {
+        self.sniff_bytes = Some(sniff_bytes);
+        self
+    }
+
+    pub fn build(self) -> Result<NoBinaryFiles> {
+        if self.text_extensions.is_empty() && self.allowed_paths.is_none() {
+            return Err(anyhow!(
+                "Failed to initialize no_binary_files hook. Neither 'text_extensions' nor 'allowed_paths' is set."
+            ));
+        }
+        let sniff_bytes = match self.sniff_bytes {
+            Some(sniff_bytes) => check_positive("no_binary_files", "sniff_bytes", sniff_bytes)?,
+            None => DEFAULT_SNIFF_BYTES,
+        };
+        Ok(NoBinaryFiles {
+            text_extensions: self.text_extensions.into_iter().collect(),
+            allowed_paths: self
+                .allowed_paths
+                .map(Regex::new)
+                .transpose()
+                .context("Failed to create allowed_paths regex")?,
+            sniff_bytes,
+        })
+    }
+}
+
+/// Hook to keep binary files out of the repo.
+///
+/// A file is binary if there is a NUL byte in the first `sniff_bytes` bytes.
+/// Binary files are rejected if their extension is one of the
+/// `text_extensions`, or if `allowed_paths` is set and does not match their
+/// path. Symlinks are not checked.
+pub struct NoBinaryFiles {
+    text_extensions: HashSet<String>,
+    allowed_paths: Option<Regex>,
+    sniff_bytes: usize,
+}
+
+impl NoBinaryFiles {
+    pub fn builder<'a>() -> NoBinaryFilesBuilder<'a> {
+        NoBinaryFilesBuilder::default()
+    }
+
+    fn has_text_extension(&self, path: &MPath) -> bool {
+        let mut filename_iter = path.basename().as_ref().rsplit(|c| *c == b'.');
+        let suffix = filename_iter.next().expect("File without a name");
+        filename_iter.next().is_some()
+            && std::str::from_utf8(suffix).map_or(false, |s| {
+                self.text_extensions.contains(&s.to_ascii_lowercase())
+            })
+    }
+}
+
+#[async_trait]
+impl FileHook for NoBinaryFiles {
+    async fn run<'this: 'change, 'ctx: 'this, 'change, 'fetcher: 'change, 'path: 'change>(
+        &'this self,
+        ctx: &'ctx CoreContext,
+        content_manager: &'fetcher dyn FileContentManager,
+        change: Option<&'change BasicFileChange>,
+        path: &'path MPath,
+        cross_repo_push_source: CrossRepoPushSource,
+        push_authored_by: PushAuthoredBy,
+    ) -> Result<HookExecution> {
+        if push_authored_by.service() {
+            return Ok(HookExecution::Accepted);
+        }
+        if cross_repo_push_source == CrossRepoPushSource::PushRedirected {
+            // For push-redirected pushes we rely on the hook
+            // running in the original repo
+            return Ok(HookExecution::Accepted);
+        }
+        let change = match change {
+            Some(change) if change.file_type() != FileType::Symlink => change,
+            _ => return Ok(HookExecution::Accepted),
+        };
+
+        let has_text_extension = self.has_text_extension(path);
+        let is_allowed_path = match &self.allowed_paths {
+            Some(allowed_paths) => allowed_paths.is_match(&path.to_vec()),
+            None => true,
+        };
+        if !has_text_extension && is_allowed_path {
+            return Ok(HookExecution::Accepted);
+        }
+
+        let prefix = content_manager
+            .get_file_prefix(ctx, change.content_id(), self.sniff_bytes)
+            .await?;
+        if !prefix.contains(&0) {
+            return Ok(HookExecution::Accepted);
+        }
+
+        let reason = if has_text_extension {
+            "Its extension is for text files"
+        } else {
+            "Binary files are not allowed on this path"
+        };
+        Ok(HookExecution::Rejected(HookRejectionInfo::new_long(
+            "Binary file",
+            format!(
+                "ABORT: '{}' is a binary file. {}. Store large or opaque files outside of the repo.",
+                path, reason
+            ),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Error;
+    use blobstore::Loadable;
+    use borrowed::borrowed;
+    use fbinit::FacebookInit;
+    use hooks_content_stores::RepoFileContentManager;
+    use tests_utils::BasicTestRepo;
+    use tests_utils::CreateCommitContext;
+
+    use super::*;
+
+    #[fbinit::test]
+    async fn test_no_binary_files(fb: FacebookInit) -> Result<(), Error> {
+        let ctx = CoreContext::test_mock(fb);
+        let repo: BasicTestRepo = test_repo_factory::build_empty(fb)?;
+        borrowed!(ctx, repo);
+
+        let cs_id = CreateCommitContext::new_root(ctx, repo)
+            .add_file("notes.TXT", "te\0xt")
+            .add_file("readme.md", "text")
+            .add_file("image.png", "\0png")
+            .add_file("assets/image.png", "\0png")
+            .add_file("assets/notes.txt", "te\0xt")
+            .add_file("late.bin", "long text\0")
+            .add_file("empty.txt", "")
+            .commit()
+            .await?;
+        let bcs = cs_id.load(ctx, &repo.repo_blobstore).await?;
+        let content_manager = RepoFileContentManager::new(&repo);
+
+        let hook = NoBinaryFiles::builder()
+            .text_extensions(["txt", "md"])
+            .allowed_paths("^assets/")
+            .sniff_bytes(4)
+            .build()?;
+
+        let mut rejected = Vec::new();
+        for (path, change) in bcs.simplified_file_changes() {
+            let hook_execution = hook
+                .run(
+                    ctx,
+                    &content_manager,
+                    change,
+                    path,
+                    CrossRepoPushSource::NativeToThisRepo,
+                    PushAuthoredBy::User,
+                )
+                .await?;
+            if let HookExecution::Rejected(_) = hook_execution {
+                rejected.push(path.to_string());
+            }
+        }
+        rejected.sort();
+        assert_eq!(rejected, ["assets/notes.txt", "image.png", "notes.TXT"]);
+
+        assert!(NoBinaryFiles::builder().build().is_err());
+        for sniff_bytes in [0, -1] {
+            let result = NoBinaryFiles::builder()
+                .text_extensions(["txt"])
+                .sniff_bytes(sniff_bytes)
+                .build();
+            assert!(result.is_err());
+        }
+        Ok(())
+    }
+}
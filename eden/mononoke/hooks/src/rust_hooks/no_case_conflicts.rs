@@ -0,0 +1,367 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bookmarks::BookmarkName;
+use context::CoreContext;
+use mononoke_types::BonsaiChangeset;
+use mononoke_types::MPath;
+use mononoke_types::MPathElement;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::ChangesetHook;
+use crate::CrossRepoPushSource;
+use crate::FileContentManager;
+use crate::HookExecution;
+use crate::HookRejectionInfo;
+use crate::PushAuthoredBy;
+
+/// Names added by a changeset, grouped by parent directory and folded name.
+type NewNames = HashMap<(Option<MPath>, String), BTreeSet<MPathElement>>;
+
+/// Rejects changesets that add paths which only differ from other paths by
+/// case or Unicode normalization. Checkouts of such paths break on macOS and
+/// Windows. Paths are compared with other paths in the changeset, and with
+/// paths in the bookmark that is being moved. A bookmark that is being
+/// created has no existing paths. Existing paths that the changeset removes,
+/// including whole directories, do not conflict.
+///
+/// Path elements that are not valid UTF-8 are not checked.
+#[derive(Clone, Debug)]
+pub struct NoCaseConflicts;
+
+impl NoCaseConflicts {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// The name that `element` has on a filesystem that is case-insensitive, and
+/// does not distinguish between NFC and NFD forms. `None` if `element` is not
+/// valid UTF-8.
+fn fold_name(element: &MPathElement) -> Option<String> {
+    let name = std::str::from_utf8(element.as_ref()).ok()?;
+    Some(name.to_lowercase().nfc().collect())
+}
+
+/// Group all elements of `paths`, including directories, by parent
+/// directory and folded name.
+fn new_names<'a>(paths: impl IntoIterator<Item = &'a MPath>) -> NewNames {
+    let mut names = NewNames::new();
+    for path in paths {
+        let mut parent: Option<MPath> = None;
+        for element in path {
+            if let Some(folded) = fold_name(element) {
+                names
+                    .entry((parent.clone(), folded))
+                    .or_default()
+                    .insert(element.clone());
+            }
+            parent = Some(MPath::join_opt_element(parent.as_ref(), element));
+        }
+    }
+    names
+}
+
+/// Pairs of paths in `names` that conflict with each other.
+fn conflicts_within(names: &NewNames) -> Vec<(MPath, MPath)> {
+    let mut conflicts = Vec::new();
+    for ((parent, _folded), elements) in names {
+        let mut elements = elements.iter();
+        if let Some(first) = elements.next() {
+            for other in elements {
+                conflicts.push((
+                    MPath::join_opt_element(parent.as_ref(), first),
+                    MPath::join_opt_element(parent.as_ref(), other),
+                ));
+            }
+        }
+    }
+    conflicts
+}
+
+/// Pairs of existing and new paths that conflict with each other.
+fn conflicts_with_existing(
+    names: &NewNames,
+    existing: &HashMap<Option<MPath>, Vec<MPathElement>>,
+) -> Vec<(MPath, MPath)> {
+    let mut conflicts = Vec::new();
+    for ((parent, folded), elements) in names {
+        let existing_elements = match existing.get(parent) {
+            Some(existing_elements) => existing_elements,
+            None => continue,
+        };
+        for existing_element in existing_elements {
+            if elements.contains(existing_element)
+                || fold_name(existing_element).as_ref() != Some(folded)
+            {
+                continue;
+            }
+            let existing_path = MPath::join_opt_element(parent.as_ref(), existing_element);
+            for element in elements {
+                conflicts.push((
+                    existing_path.clone(),
+                    MPath::join_opt_element(parent.as_ref(), element),
+                ));
+            }
+        }
+    }
+    conflicts
+}
+
+/// Whether the existing `path` is removed by the changeset. A directory is
+/// removed if all files in it are in `removed`.
+async fn is_removed(
+    ctx: &CoreContext,
+    content_manager: &dyn FileContentManager,
+    bookmark: &BookmarkName,
+    path: &MPath,
+    removed: &HashSet<MPath>,
+) -> Result<bool> {
+    let mut pending = vec![path.clone()];
+    while !pending.is_empty() {
+        let mut directories = Vec::new();
+        for path in pending.drain(..) {
+            if removed.contains(&path) {
+                continue;
+            }
+            if !removed.iter().any(|removed| path.is_prefix_of(removed)) {
+                return Ok(false);
+            }
+            directories.push(Some(path));
+        }
+        if directories.is_empty() {
+            break;
+        }
+        let listed = content_manager
+            .list_directories(ctx, bookmark.clone(), directories.clone())
+            .await?;
+        for directory in directories {
+            match listed.get(&directory) {
+                Some(names) => pending.extend(
+                    names
+                        .iter()
+                        .map(|name| MPath::join_opt_element(directory.as_ref(), name)),
+                ),
+                // A file that is not removed.
+                None => return Ok(false),
+            }
+        }
+    }
+    Ok(true)
+}
+
+#[async_trait]
+impl ChangesetHook for NoCaseConflicts {
+    async fn run<'this: 'cs, 'ctx: 'this, 'cs, 'fetcher: 'cs>(
+        &'this self,
+        ctx: &'ctx CoreContext,
+        bookmark: &BookmarkName,
+        changeset: &'cs BonsaiChangeset,
+        content_manager: &'fetcher dyn FileContentManager,
+        cross_repo_push_source: CrossRepoPushSource,
+        push_authored_by: PushAuthoredBy,
+    ) -> Result<HookExecution> {
+        if push_authored_by.service() {
+            return Ok(HookExecution::Accepted);
+        }
+        if cross_repo_push_source == CrossRepoPushSource::PushRedirected {
+            // For push-redirected commits, we rely on running source-repo hooks
+            return Ok(HookExecution::Accepted);
+        }
+
+        let mut added = Vec::new();
+        let mut removed = HashSet::new();
+        for (path, change) in changeset.simplified_file_changes() {
+            match change {
+                Some(_) => added.push(path),
+                None => {
+                    removed.insert(path.clone());
+                }
+            }
+        }
+        let names = new_names(added);
+        if names.is_empty() {
+            return Ok(HookExecution::Accepted);
+        }
+
+        let mut conflicts = conflicts_within(&names);
+        let directories = names
+            .keys()
+            .map(|(parent, _folded)| parent.clone())
+            .collect::<HashSet<_>>();
+        let existing = content_manager
+            .list_directories(ctx, bookmark.clone(), directories.into_iter().collect())
+            .await?;
+        let mut removed_cache = HashMap::new();
+        for (existing_path, path) in conflicts_with_existing(&names, &existing) {
+            let is_removed = match removed_cache.get(&existing_path) {
+                Some(is_removed) => *is_removed,
+                None => {
+                    let is_removed =
+                        is_removed(ctx, content_manager, bookmark, &existing_path, &removed)
+                            .await?;
+                    removed_cache.insert(existing_path.clone(), is_removed);
+                    is_removed
+                }
+            };
+            if !is_removed {
+                conflicts.push((existing_path, path));
+            }
+        }
+
+        if conflicts.is_empty() {
+            return Ok(HookExecution::Accepted);
+        }
+        conflicts.sort();
+        let description = conflicts
+            .iter()
+            .map(|(path1, path2)| format!("'{}' conflicts with '{}'", path1, path2))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(HookExecution::Rejected(HookRejectionInfo::new_long(
+            "Case conflict",
+            format!(
+                "The following paths only differ by case or Unicode normalization, which breaks checkouts on macOS and Windows:\n{}",
+                description
+            ),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::anyhow;
+    use anyhow::Error;
+    use blobstore::Loadable;
+    use borrowed::borrowed;
+    use fbinit::FacebookInit;
+    use hooks_content_stores::RepoFileContentManager;
+    use tests_utils::bookmark;
+    use tests_utils::BasicTestRepo;
+    use tests_utils::CreateCommitContext;
+
+    use super::*;
+
+    #[test]
+    fn test_fold_name() -> Result<()> {
+        let nfc = MPathElement::new("Caf\u{e9}".into())?;
+        let nfd = MPathElement::new("cafe\u{301}".into())?;
+        assert_eq!(fold_name(&nfc), fold_name(&nfd));
+        assert_eq!(
+            fold_name(&MPathElement::new("README".into())?).as_deref(),
+            Some("readme")
+        );
+        assert_eq!(fold_name(&MPathElement::new(b"\xff".to_vec())?), None);
+        Ok(())
+    }
+
+    #[fbinit::test]
+    async fn test_no_case_conflicts(fb: FacebookInit) -> Result<(), Error> {
+        let ctx = CoreContext::test_mock(fb);
+        let repo: BasicTestRepo = test_repo_factory::build_empty(fb)?;
+        borrowed!(ctx, repo);
+
+        let root = CreateCommitContext::new_root(ctx, repo)
+            .add_file("README", "readme")
+            .add_file("dir/a", "a")
+            .commit()
+            .await?;
+        let main = bookmark(ctx, repo, "main").set_to(root).await?;
+        let content_manager = RepoFileContentManager::new(&repo);
+
+        let cases = [
+            (
+                vec![("readme", Some("x"))],
+                Some("'README' conflicts with 'readme'"),
+            ),
+            (
+                vec![("Dir/b", Some("x"))],
+                Some("'dir' conflicts with 'Dir'"),
+            ),
+            (
+                vec![("x/Foo", Some("x")), ("x/foo", Some("x"))],
+                Some("'x/Foo' conflicts with 'x/foo'"),
+            ),
+            (
+                vec![("caf\u{e9}", Some("x")), ("cafe\u{301}", Some("x"))],
+                Some("conflicts with"),
+            ),
+            (vec![("README", None), ("readme", Some("x"))], None),
+            (vec![("dir/a", None), ("Dir/a", Some("x"))], None),
+            (
+                vec![("dir/a", None), ("dir/c", Some("x")), ("Dir/b", Some("x"))],
+                Some("'Dir' conflicts with 'dir'"),
+            ),
+            (vec![("README.md", Some("x")), ("dir/b", Some("x"))], None),
+        ];
+        for (changes, expected) in cases {
+            let mut commit = CreateCommitContext::new(ctx, repo, vec![root]);
+            for (path, content) in &changes {
+                commit = match content {
+                    Some(content) => commit.add_file(*path, *content),
+                    None => commit.delete_file(*path),
+                };
+            }
+            let bcs = commit
+                .commit()
+                .await?
+                .load(ctx, &repo.repo_blobstore)
+                .await?;
+
+            let hook_execution = NoCaseConflicts::new()
+                .run(
+                    ctx,
+                    &main,
+                    &bcs,
+                    &content_manager,
+                    CrossRepoPushSource::NativeToThisRepo,
+                    PushAuthoredBy::User,
+                )
+                .await?;
+            match (hook_execution, expected) {
+                (HookExecution::Accepted, None) => {}
+                (HookExecution::Rejected(info), Some(expected)) => {
+                    assert!(
+                        info.long_description.contains(expected),
+                        "{}",
+                        info.long_description
+                    );
+                }
+                (execution, _) => {
+                    return Err(anyhow!("unexpected {:?} for {:?}", execution, changes));
+                }
+            }
+        }
+
+        // A bookmark that does not exist yet has no existing paths.
+        let bcs = CreateCommitContext::new(ctx, repo, vec![root])
+            .add_file("readme", "x")
+            .commit()
+            .await?
+            .load(ctx, &repo.repo_blobstore)
+            .await?;
+        let hook_execution = NoCaseConflicts::new()
+            .run(
+                ctx,
+                &BookmarkName::new("new")?,
+                &bcs,
+                &content_manager,
+                CrossRepoPushSource::NativeToThisRepo,
+                PushAuthoredBy::User,
+            )
+            .await?;
+        assert!(matches!(hook_execution, HookExecution::Accepted));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use context::CoreContext;
+use metaconfig_types::HookConfig;
+use mononoke_types::BasicFileChange;
+use mononoke_types::MPath;
+use regex::bytes::Regex;
+
+use crate::CrossRepoPushSource;
+use crate::FileContentManager;
+use crate::FileHook;
+use crate::HookExecution;
+use crate::HookRejectionInfo;
+use crate::PushAuthoredBy;
+
+#[derive(Default)]
+pub struct NoInvisibleFilenamesBuilder<'a> {
+    /// Paths on which the hook is not enforced.
+    allowed_paths: Option<&'a str>,
+    /// Characters that are allowed even if they are invisible.
+    allowed_characters: Option<&'a str>,
+}
+
+impl<'a> NoInvisibleFilenamesBuilder<'a> {
+    pub fn set_from_config(mut self, config: &'a HookConfig) -> Self {
+        if let Some(v) = config.strings.get("allowed_paths") {
+            self = self.allowed_paths(v)
+        }
+        if let Some(v) = config.strings.get("allowed_characters") {
+            self = self.allowed_characters(v)
+        }
+        self
+    }
+
+    pub fn allowed_paths(mut self, regex: &'a str) -> Self {
+        self.allowed_paths = Some(regex);
+        self
+    }
+
+    /// For example, "\u{200d}" allows zero-width joiners, which are used
+    /// by some emoji.
+    pub fn allowed_characters(mut self, characters: &'a str) -> Self {
+        self.allowed_characters = Some(characters);
+        self
+    }
+
+    pub fn build(self) -> Result<NoInvisibleFilenames> {
+        Ok(NoInvisibleFilenames {
+            allowed_paths: self
+                .allowed_paths
+                .map(Regex::new)
+                .transpose()
+                .context("Failed to create allowed_paths regex")?,
+            allowed_characters: self
+                .allowed_characters
+                .unwrap_or_default()
+                .chars()
+                .collect(),
+        })
+    }
+}
+
+/// Hook to disallow filenames that are not valid UTF-8, or that contain
+/// characters that are not visible when the filename is displayed.
+///
+/// Such characters break tools that handle filenames, and can be used to make
+/// a filename look like a different one. Rejected characters are:
+///
+///  - Control characters, like newlines and escape sequences.
+///  - Zero-width and other invisible characters, like U+200B ZERO WIDTH SPACE,
+///    U+00AD SOFT HYPHEN, or U+FEFF BYTE ORDER MARK.
+///  - Bidirectional formatting characters, like U+202E RIGHT-TO-LEFT OVERRIDE.
+pub struct NoInvisibleFilenames {
+    allowed_paths: Option<Regex>,
+    allowed_characters: Vec<char>,
+}
+
+impl NoInvisibleFilenames {
+    pub fn builder<'a>() -> NoInvisibleFilenamesBuilder<'a> {
+        NoInvisibleFilenamesBuilder::default()
+    }
+
+    /// Explain why `path` is rejected, if it is.
+    fn check_path(&self, path: &MPath) -> Option<String> {
+        let bytes = path.to_vec();
+        let path = match std::str::from_utf8(&bytes) {
+            Ok(path) => path,
+            Err(_) => {
+                return Some(format!(
+                    "ABORT: Illegal filename: {}. The path is not valid UTF-8.",
+                    String::from_utf8_lossy(&bytes)
+                ));
+            }
+        };
+        let (c, kind) = path
+            .chars()
+            .filter(|c| !self.allowed_characters.contains(c))
+            .find_map(|c| Some((c, invisible_kind(c)?)))?;
+        Some(format!(
+            "ABORT: Illegal filename: {}. The path contains {} U+{:04X}.",
+            path.escape_debug(),
+            kind,
+            c as u32
+        ))
+    }
+}
+
+/// Describe `c` if it is invisible or changes how the rest of the filename
+/// is displayed.
+fn invisible_kind(c: char) -> Option<&'static str> {
+    match c {
+        '\u{061C}'
+        | '\u{200E}'
+        | '\u{200F}'
+        | '\u{202A}'..='\u{202E}'
+        | '\u{2066}'..='\u{2069}' => Some("the bidirectional formatting character"),
+        '\u{00AD}'
+        | '\u{034F}'
+        | '\u{115F}'
+        | '\u{1160}'
+        | '\u{180E}'
+        | '\u{200B}'..='\u{200D}'
+        | '\u{2028}'
+        | '\u{2029}'
+        | '\u{2060}'..='\u{2064}'
+        | '\u{206A}'..='\u{206F}'
+        | '\u{3164}'
+        | '\u{FEFF}'
+        | '\u{FFA0}'
+        | '\u{FFF9}'..='\u{FFFB}'
+        | '\u{E0000}'..='\u{E007F}' => Some("the invisible character"),
+        c if c.is_control() => Some("the control character"),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl FileHook for NoInvisibleFilenames {
+    async fn run<'this: 'change, 'ctx: 'this, 'change, 'fetcher: 'change, 'path: 'change>(
+        &'this self,
+        _ctx: &'ctx CoreContext,
+        _content_manager: &'fetcher dyn FileContentManager,
+        change: Option<&'change BasicFileChange>,
+        path: &'path MPath,
+        cross_repo_push_source: CrossRepoPushSource,
+        push_authored_by: PushAuthoredBy,
+    ) -> Result<HookExecution> {
+        if push_authored_by.service() {
+            return Ok(HookExecution::Accepted);
+        }
+        if cross_repo_push_source == CrossRepoPushSource::PushRedirected {
+            // For push-redirected pushes we rely on the hook
+            // running in the original repo
+            return Ok(HookExecution::Accepted);
+        }
+        if change.is_none() {
+            return Ok(HookExecution::Accepted);
+        }
+
+        if let Some(allowed_paths) = &self.allowed_paths {
+            if allowed_paths.is_match(&path.to_vec()) {
+                return Ok(HookExecution::Accepted);
+            }
+        }
+
+        match self.check_path(path) {
+            Some(description) => Ok(HookExecution::Rejected(HookRejectionInfo::new_long(
+                "Illegal filename",
+                description,
+            ))),
+            None => Ok(HookExecution::Accepted),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check(hook: &NoInvisibleFilenames, path: &[u8]) -> Option<String> {
+        hook.check_path(&MPath::new(path).unwrap())
+    }
+
+    #[test]
+    fn test_check_path() {
+        let hook = NoInvisibleFilenames::builder().build().unwrap();
+        assert_eq!(check(&hook, b"dir/caf\xc3\xa9 file.txt"), None);
+        assert_eq!(check(&hook, "emoji/\u{1F600}".as_bytes()), None);
+        assert_eq!(
+            check(&hook, b"dir/\xff"),
+            Some("ABORT: Illegal filename: dir/\u{FFFD}. The path is not valid UTF-8.".to_string())
+        );
+        assert_eq!(
+            check(&hook, "evil\u{202E}txt.exe".as_bytes()),
+            Some(
+                "ABORT: Illegal filename: evil\\u{202e}txt.exe. The path contains the bidirectional formatting character U+202E.".to_string()
+            )
+        );
+        assert_eq!(
+            check(&hook, "a\u{200B}b".as_bytes()),
+            Some(
+                "ABORT: Illegal filename: a\\u{200b}b. The path contains the invisible character U+200B.".to_string()
+            )
+        );
+        assert_eq!(
+            check(&hook, b"a\x1b[31mb"),
+            Some(
+                "ABORT: Illegal filename: a\\u{1b}[31mb. The path contains the control character U+001B.".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_allowed_characters() {
+        let hook = NoInvisibleFilenames::builder()
+            .allowed_characters("\u{200D}")
+            .build()
+            .unwrap();
+        assert_eq!(check(&hook, "\u{1F469}\u{200D}\u{1F52C}".as_bytes()), None);
+        assert!(check(&hook, "a\u{200B}b".as_bytes()).is_some());
+    }
+}
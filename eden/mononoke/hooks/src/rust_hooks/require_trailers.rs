@@ -0,0 +1,268 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use anyhow::anyhow;
+use anyhow::Result;
+use async_trait::async_trait;
+use bookmarks::BookmarkName;
+use context::CoreContext;
+use mononoke_types::BonsaiChangeset;
+
+use crate::ChangesetHook;
+use crate::CrossRepoPushSource;
+use crate::FileContentManager;
+use crate::HookConfig;
+use crate::HookExecution;
+use crate::HookRejectionInfo;
+use crate::PushAuthoredBy;
+
+#[derive(Default)]
+pub struct RequireTrailersBuilder {
+    required_trailers: Vec<String>,
+    author_trailers: Vec<String>,
+}
+
+impl RequireTrailersBuilder {
+    pub fn set_from_config(mut self, config: &HookConfig) -> Self {
+        if let Some(v) = config.string_lists.get("required_trailers") {
+            self = self.required_trailers(v)
+        }
+        if let Some(v) = config.string_lists.get("author_trailers") {
+            self = self.author_trailers(v)
+        }
+        self
+    }
+
+    /// Trailers, like "Test-plan", that must be present and not empty.
+    pub fn required_trailers(mut self, keys: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.required_trailers = keys.into_iter().map(|s| String::from(s.as_ref())).collect();
+        self
+    }
+
+    /// Trailers, like "Signed-off-by", that must be present with the commit
+    /// author as the value.
+    pub fn author_trailers(mut self, keys: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.author_trailers = keys.into_iter().map(|s| String::from(s.as_ref())).collect();
+        self
+    }
+
+    pub fn build(self) -> Result<RequireTrailers> {
+        if self.required_trailers.is_empty() && self.author_trailers.is_empty() {
+            return Err(anyhow!(
+                "Failed to initialize require_trailers hook. Neither 'required_trailers' nor 'author_trailers' is set."
+            ));
+        }
+        if let Some(key) = self
+            .required_trailers
+            .iter()
+            .chain(&self.author_trailers)
+            .find(|key| !is_trailer_key(key))
+        {
+            return Err(anyhow!(
+                "Failed to initialize require_trailers hook. '{}' is not a valid trailer.",
+                key
+            ));
+        }
+        Ok(RequireTrailers {
+            required_trailers: self.required_trailers,
+            author_trailers: self.author_trailers,
+        })
+    }
+}
+
+/// Requires trailers, like "Signed-off-by: Author <email>", in commit
+/// messages.
+pub struct RequireTrailers {
+    required_trailers: Vec<String>,
+    author_trailers: Vec<String>,
+}
+
+impl RequireTrailers {
+    pub fn builder() -> RequireTrailersBuilder {
+        RequireTrailersBuilder::default()
+    }
+
+    /// Explain why a commit by `author` with `message` is rejected. Empty
+    /// if it is accepted.
+    fn check_message(&self, author: &str, message: &str) -> Vec<String> {
+        let trailers = parse_trailers(message);
+        let values = |key: &str| {
+            trailers
+                .iter()
+                .filter(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        let mut problems = Vec::new();
+        for key in &self.required_trailers {
+            let values = values(key);
+            if values.is_empty() {
+                problems.push(format!("Commit message must have a '{}:' trailer.", key));
+            } else if values.iter().all(|v| v.is_empty()) {
+                problems.push(format!("The '{}:' trailer must not be empty.", key));
+            }
+        }
+        for key in &self.author_trailers {
+            if !values(key).contains(&author.trim()) {
+                problems.push(format!(
+                    "Commit message must have a '{}: {}' trailer matching the author.",
+                    key,
+                    author.trim()
+                ));
+            }
+        }
+        problems
+    }
+}
+
+/// Trailer keys are made of ASCII letters, digits, and hyphens.
+fn is_trailer_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Parse the trailers of a commit message, like `git interpret-trailers`.
+///
+/// Trailers are "Key: value" lines in the last paragraph of the message.
+/// Lines starting with whitespace continue the value of the previous
+/// trailer. If the last paragraph has other lines, or is the only
+/// paragraph, the message has no trailers.
+fn parse_trailers(message: &str) -> Vec<(&str, String)> {
+    let lines = message.trim_end().lines().collect::<Vec<_>>();
+    let start = match lines.iter().rposition(|line| line.trim().is_empty()) {
+        Some(blank) => blank + 1,
+        None => return Vec::new(),
+    };
+
+    let mut trailers: Vec<(&str, String)> = Vec::new();
+    for line in &lines[start..] {
+        if line.starts_with(char::is_whitespace) {
+            match trailers.last_mut() {
+                Some((_, value)) => {
+                    if !value.is_empty() {
+                        value.push(' ');
+                    }
+                    value.push_str(line.trim());
+                }
+                None => return Vec::new(),
+            }
+            continue;
+        }
+        match line.split_once(':') {
+            Some((key, value)) if is_trailer_key(key) => {
+                trailers.push((key, value.trim().to_string()));
+            }
+            _ => return Vec::new(),
+        }
+    }
+    trailers
+}
+
+#[async_trait]
+impl ChangesetHook for RequireTrailers {
+    async fn run<'this: 'cs, 'ctx: 'this, 'cs, 'fetcher: 'cs>(
+        &'this self,
+        _ctx: &'ctx CoreContext,
+        _bookmark: &BookmarkName,
+        changeset: &'cs BonsaiChangeset,
+        _content_manager: &'fetcher dyn FileContentManager,
+        cross_repo_push_source: CrossRepoPushSource,
+        push_authored_by: PushAuthoredBy,
+    ) -> Result<HookExecution> {
+        if push_authored_by.service() {
+            return Ok(HookExecution::Accepted);
+        }
+        if cross_repo_push_source == CrossRepoPushSource::PushRedirected {
+            // For push-redirected commits, we rely on running source-repo hooks
+            return Ok(HookExecution::Accepted);
+        }
+
+        let problems = self.check_message(changeset.author(), changeset.message());
+        if problems.is_empty() {
+            Ok(HookExecution::Accepted)
+        } else {
+            Ok(HookExecution::Rejected(HookRejectionInfo::new_long(
+                "Missing commit message trailers",
+                problems.join("\n"),
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_trailers() {
+        assert_eq!(
+            parse_trailers(
+                "title\n\nbody\n\nTest-plan: ran\n  the tests\nSigned-off-by: A <a@b>\n"
+            ),
+            [
+                ("Test-plan", "ran the tests".to_string()),
+                ("Signed-off-by", "A <a@b>".to_string()),
+            ]
+        );
+        // The title is not a trailer.
+        assert!(parse_trailers("Fix: crash").is_empty());
+        // The last paragraph has a line that is not a trailer.
+        assert!(parse_trailers("title\n\nSummary: x\nnot a trailer").is_empty());
+        assert!(parse_trailers("title\n\nNot a key: x").is_empty());
+        assert_eq!(
+            parse_trailers("title\n\nReviewers:"),
+            [("Reviewers", String::new())]
+        );
+    }
+
+    #[test]
+    fn test_check_message() {
+        let hook = RequireTrailers::builder()
+            .required_trailers(["Test-plan"])
+            .author_trailers(["Signed-off-by"])
+            .build()
+            .unwrap();
+        let author = "Alice <alice@example.com>";
+        assert!(
+            hook.check_message(
+                author,
+                "title\n\ntest-plan: ran tests\nSigned-off-by: Alice <alice@example.com>"
+            )
+            .is_empty()
+        );
+        assert_eq!(
+            hook.check_message(
+                author,
+                "title\n\nTest-plan:\nSigned-off-by: Bob <bob@example.com>"
+            ),
+            [
+                "The 'Test-plan:' trailer must not be empty.",
+                "Commit message must have a 'Signed-off-by: Alice <alice@example.com>' trailer matching the author.",
+            ]
+        );
+        // Trailers in the middle of the message do not count.
+        assert_eq!(
+            hook.check_message(
+                author,
+                "title\n\nTest-plan: x\nSigned-off-by: Alice <alice@example.com>\n\nmore text"
+            )
+            .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(RequireTrailers::builder().build().is_err());
+        assert!(
+            RequireTrailers::builder()
+                .required_trailers(["Test plan"])
+                .build()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,336 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Error;
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use context::CoreContext;
+use metaconfig_types::HookConfig;
+use mononoke_types::BasicFileChange;
+use mononoke_types::FileType;
+use mononoke_types::MPath;
+
+use crate::CrossRepoPushSource;
+use crate::FileContentManager;
+use crate::FileHook;
+use crate::HookExecution;
+use crate::HookRejectionInfo;
+use crate::PushAuthoredBy;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Policy {
+    /// Symlinks may point anywhere.
+    Allow,
+    /// Symlinks are not allowed.
+    Deny,
+    /// Symlinks must point to a relative path inside the repo.
+    Relative,
+}
+
+impl FromStr for Policy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "allow" => Ok(Policy::Allow),
+            "deny" => Ok(Policy::Deny),
+            "relative" => Ok(Policy::Relative),
+            _ => Err(anyhow!(
+                "Unknown symlink policy '{}'. Expected 'allow', 'deny', or 'relative'.",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SymlinkPolicyBuilder {
+    path_prefixes: Vec<String>,
+    policies: Vec<String>,
+    default_policy: Option<String>,
+}
+
+impl SymlinkPolicyBuilder {
+    pub fn set_from_config(mut self, config: &HookConfig) -> Self {
+        if let Some(v) = config.string_lists.get("symlink_policy_path_prefixes") {
+            self = self.symlink_policy_path_prefixes(v)
+        }
+        if let Some(v) = config.string_lists.get("symlink_policies") {
+            self = self.symlink_policies(v)
+        }
+        if let Some(v) = config.strings.get("default_symlink_policy") {
+            self = self.default_symlink_policy(v)
+        }
+        self
+    }
+
+    /// Path prefixes with their own policy in `symlink_policies`. The
+    /// longest matching prefix decides the policy.
+    pub fn symlink_policy_path_prefixes(
+        mut self,
+        strs: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Self {
+        self.path_prefixes = strs.into_iter().map(|s| String::from(s.as_ref())).collect();
+        self
+    }
+
+    /// Policies, one of "allow", "deny", or "relative", for
+    /// `symlink_policy_path_prefixes`.
+    pub fn symlink_policies(mut self, strs: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.policies = strs.into_iter().map(|s| String::from(s.as_ref())).collect();
+        self
+    }
+
+    /// Policy for symlinks outside of `symlink_policy_path_prefixes`.
+    /// Defaults to "relative".
+    pub fn default_symlink_policy(mut self, policy: impl ToString) -> Self {
+        self.default_policy = Some(policy.to_string());
+        self
+    }
+
+    pub fn build(self) -> Result<SymlinkPolicy> {
+        if self.path_prefixes.len() != self.policies.len() {
+            return Err(anyhow!(
+                "Failed to initialize symlink_policy hook. 'symlink_policy_path_prefixes' and 'symlink_policies' have different lengths."
+            ));
+        }
+        let prefix_policies = self
+            .path_prefixes
+            .iter()
+            .zip(&self.policies)
+            .map(|(prefix, policy)| {
+                let prefix = MPath::new_opt(prefix)
+                    .with_context(|| format!("Invalid path prefix '{}'", prefix))?;
+                Ok((prefix, policy.parse::<Policy>()?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let default_policy = match self.default_policy {
+            Some(policy) => policy.parse()?,
+            None => Policy::Relative,
+        };
+        Ok(SymlinkPolicy {
+            prefix_policies,
+            default_policy,
+        })
+    }
+}
+
+/// Hook to restrict symlinks. Depending on the path, symlinks can be denied,
+/// allowed to point anywhere, or required to be relative and point inside
+/// the repo. Absolute symlinks, and symlinks that escape the repo, break
+/// builds in sandboxes.
+pub struct SymlinkPolicy {
+    prefix_policies: Vec<(Option<MPath>, Policy)>,
+    default_policy: Policy,
+}
+
+impl SymlinkPolicy {
+    pub fn builder() -> SymlinkPolicyBuilder {
+        SymlinkPolicyBuilder::default()
+    }
+
+    fn policy_for_path(&self, path: &MPath) -> Policy {
+        self.prefix_policies
+            .iter()
+            .filter(|(prefix, _)| MPath::is_prefix_of_opt(prefix.as_ref(), path))
+            .max_by_key(|(prefix, _)| prefix.as_ref().map_or(0, |p| p.num_components()))
+            .map_or(self.default_policy, |(_, policy)| *policy)
+    }
+}
+
+/// Explain why the symlink at `path` must not point to `target`, if it
+/// must not.
+fn check_relative_target(path: &MPath, target: &[u8]) -> Option<String> {
+    if target.is_empty() {
+        return Some(format!("ABORT: Symlink '{}' has an empty target.", path));
+    }
+    let target_str = String::from_utf8_lossy(target);
+    let is_windows_absolute = target.get(1) == Some(&b':') || target.starts_with(b"\\");
+    if target.starts_with(b"/") || is_windows_absolute {
+        return Some(format!(
+            "ABORT: Symlink '{}' points to the absolute path '{}'. Use a relative path instead.",
+            path, target_str
+        ));
+    }
+
+    // The symlink is resolved relative to its directory.
+    let mut depth = path.num_components() - 1;
+    for component in target.split(|c| *c == b'/' || *c == b'\\') {
+        match component {
+            b"" | b"." => {}
+            b".." => match depth.checked_sub(1) {
+                Some(parent_depth) => depth = parent_depth,
+                None => {
+                    return Some(format!(
+                        "ABORT: Symlink '{}' points to '{}', which is outside of the repository.",
+                        path, target_str
+                    ));
+                }
+            },
+            _ => depth += 1,
+        }
+    }
+    None
+}
+
+#[async_trait]
+impl FileHook for SymlinkPolicy {
+    async fn run<'this: 'change, 'ctx: 'this, 'change, 'fetcher: 'change, 'path: 'change>(
+        &'this self,
+        ctx: &'ctx CoreContext,
+        content_manager: &'fetcher dyn FileContentManager,
+        change: Option<&'change BasicFileChange>,
+        path: &'path MPath,
+        cross_repo_push_source: CrossRepoPushSource,
+        push_authored_by: PushAuthoredBy,
+    ) -> Result<HookExecution> {
+        if push_authored_by.service() {
+            return Ok(HookExecution::Accepted);
+        }
+        if cross_repo_push_source == CrossRepoPushSource::PushRedirected {
+            // For push-redirected pushes we rely on the hook
+            // running in the original repo
+            return Ok(HookExecution::Accepted);
+        }
+        let change = match change {
+            Some(change) if change.file_type() == FileType::Symlink => change,
+            _ => return Ok(HookExecution::Accepted),
+        };
+
+        let description = match self.policy_for_path(path) {
+            Policy::Allow => None,
+            Policy::Deny => Some(format!("ABORT: Symlinks are not allowed at '{}'.", path)),
+            Policy::Relative => {
+                let target = match change.size() {
+                    0 => Bytes::new(),
+                    size => {
+                        content_manager
+                            .get_file_prefix(ctx, change.content_id(), size.try_into()?)
+                            .await?
+                    }
+                };
+                check_relative_target(path, &target)
+            }
+        };
+        match description {
+            Some(description) => Ok(HookExecution::Rejected(HookRejectionInfo::new_long(
+                "Symlink not allowed",
+                description,
+            ))),
+            None => Ok(HookExecution::Accepted),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use blobstore::Loadable;
+    use borrowed::borrowed;
+    use fbinit::FacebookInit;
+    use hooks_content_stores::RepoFileContentManager;
+    use tests_utils::BasicTestRepo;
+    use tests_utils::CreateCommitContext;
+
+    use super::*;
+
+    fn check(path: &str, target: &str) -> bool {
+        check_relative_target(&MPath::new(path).unwrap(), target.as_bytes()).is_none()
+    }
+
+    #[test]
+    fn test_check_relative_target() {
+        assert!(check("link", "file"));
+        assert!(check("a/b/link", "../../c/./file"));
+        assert!(check("a/link", "b/../../c"));
+        assert!(!check("a/link", "../../c"));
+        assert!(!check("link", "/etc/passwd"));
+        assert!(!check("link", "C:\\Windows"));
+        assert!(!check("a/link", "..\\..\\c"));
+        assert!(!check("link", ""));
+        assert_eq!(
+            check_relative_target(&MPath::new("a/link").unwrap(), b"../.."),
+            Some(
+                "ABORT: Symlink 'a/link' points to '../..', which is outside of the repository."
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_policy_for_path() {
+        let hook = SymlinkPolicy::builder()
+            .symlink_policy_path_prefixes(["", "third-party", "third-party/tools"])
+            .symlink_policies(["deny", "allow", "relative"])
+            .build()
+            .unwrap();
+        let policy = |path: &str| hook.policy_for_path(&MPath::new(path).unwrap());
+        assert_eq!(policy("link"), Policy::Deny);
+        assert_eq!(policy("third-party/link"), Policy::Allow);
+        assert_eq!(policy("third-party2/link"), Policy::Deny);
+        assert_eq!(policy("third-party/tools/bin/link"), Policy::Relative);
+
+        let hook = SymlinkPolicy::builder().build().unwrap();
+        assert_eq!(
+            hook.policy_for_path(&MPath::new("link").unwrap()),
+            Policy::Relative
+        );
+
+        assert!(
+            SymlinkPolicy::builder()
+                .default_symlink_policy("forbid")
+                .build()
+                .is_err()
+        );
+        assert!(
+            SymlinkPolicy::builder()
+                .symlink_policy_path_prefixes(["a"])
+                .build()
+                .is_err()
+        );
+    }
+
+    #[fbinit::test]
+    async fn test_symlink_policy_empty_target(fb: FacebookInit) -> Result<(), Error> {
+        let ctx = CoreContext::test_mock(fb);
+        let repo: BasicTestRepo = test_repo_factory::build_empty(fb)?;
+        borrowed!(ctx, repo);
+
+        let cs_id = CreateCommitContext::new_root(ctx, repo)
+            .add_file_with_type("link", "", FileType::Symlink)
+            .commit()
+            .await?;
+        let bcs = cs_id.load(ctx, &repo.repo_blobstore).await?;
+        let content_manager = RepoFileContentManager::new(&repo);
+        let hook = SymlinkPolicy::builder().build()?;
+
+        for (path, change) in bcs.simplified_file_changes() {
+            let hook_execution = hook
+                .run(
+                    ctx,
+                    &content_manager,
+                    change,
+                    path,
+                    CrossRepoPushSource::NativeToThisRepo,
+                    PushAuthoredBy::User,
+                )
+                .await?;
+            match hook_execution {
+                HookExecution::Rejected(info) => {
+                    assert!(info.long_description.contains("has an empty target"));
+                }
+                HookExecution::Accepted => return Err(anyhow!("should be rejected")),
+            }
+        }
+
+        Ok(())
+    }
+}
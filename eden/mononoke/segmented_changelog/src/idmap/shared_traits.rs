@@ -19,6 +19,7 @@ use crate::dag::errors;
 use crate::dag::errors::programming;
 use crate::dag::errors::BackendError;
 use crate::dag::errors::DagError;
+use crate::dag::errors::NotFoundError;
 use crate::dag::id::Group;
 use crate::dag::id::Id;
 use crate::dag::idmap::IdMapWrite;
@@ -286,7 +287,7 @@ impl IdConvert for IdMapWrapper {
         // NOTE: The server implementation puts all Ids in the "master" group.
         self.vertex_id_with_max_group(&name, Group::MASTER)
             .await?
-            .ok_or(DagError::VertexNotFound(name))
+            .ok_or_else(|| name.not_found_error())
     }
     async fn vertex_id_with_max_group(
         &self,
@@ -377,7 +378,7 @@ impl IdConvert for IdMapWrapper {
                 found
                     .get(&cs_id_from_vertex_name(name))
                     .copied()
-                    .ok_or_else(|| DagError::VertexNotFound(name.clone()))
+                    .ok_or_else(|| name.not_found_error())
             })
             .collect())
     }
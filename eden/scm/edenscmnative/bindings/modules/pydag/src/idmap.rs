@@ -104,7 +104,7 @@ py_class!(pub class idmap |py| {
         let mut result = Vec::with_capacity(nodes.len());
         for (node, id) in nodes.into_iter().zip(ids) {
             let present = match id {
-                Err(dag::Error::VertexNotFound(_)) => false,
+                Err(dag::Error::VertexNotFound { .. }) => false,
                 Ok(_) => true,
                 Err(e) => return Err(e).map_pyerr(py),
             };
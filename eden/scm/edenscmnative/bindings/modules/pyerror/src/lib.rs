@@ -94,7 +94,7 @@ fn register_error_handlers() {
                     dag::errors::BackendError::Other(e) => return specific_error_handler(py, e),
                     _ => {}
                 },
-                dag::Error::VertexNotFound(_) | dag::Error::IdNotFound(_) => {
+                dag::Error::VertexNotFound { .. } | dag::Error::IdNotFound(_) => {
                     return Some(PyErr::new::<CommitLookupError, _>(
                         py,
                         cpython_ext::Str::from(e.to_string()),
@@ -152,7 +152,7 @@ fn register_error_handlers() {
             ))
         } else if matches!(
             e.downcast_ref::<dag::Error>(),
-            Some(dag::Error::VertexNotFound(_)) | Some(dag::Error::IdNotFound(_))
+            Some(dag::Error::VertexNotFound { .. }) | Some(dag::Error::IdNotFound(_))
         ) {
             Some(PyErr::new::<CommitLookupError, _>(
                 py,
@@ -7,12 +7,14 @@
 
 use std::borrow::Cow;
 use std::collections::BTreeMap;
+use std::fmt;
 use std::ops::Range;
 use std::path::PathBuf;
 use std::str;
 use std::sync::Arc;
 
 use minibytes::Text;
+use regex::Regex;
 
 use crate::convert::FromConfigValue;
 use crate::Result;
@@ -31,6 +33,14 @@ pub trait Config {
             .collect()
     }
 
+    /// Keys matching the given regular expression.
+    fn keys_matching(&self, section: &str, pattern: &Regex) -> Vec<Text> {
+        self.keys(section)
+            .into_iter()
+            .filter(|k| pattern.is_match(k))
+            .collect()
+    }
+
     /// Get config value for a given config.
     /// Return `None` if the config item does not exist or is unset.
     fn get(&self, section: &str, name: &str) -> Option<Text> {
@@ -52,6 +62,15 @@ pub trait Config {
     /// Get config sections.
     fn sections(&self) -> Cow<[Text]>;
 
+    /// Sections with the given prefix.
+    fn sections_matching(&self, prefix: &str) -> Vec<Text> {
+        self.sections()
+            .iter()
+            .filter(|s| s.starts_with(prefix))
+            .cloned()
+            .collect()
+    }
+
     /// Get the sources of a config.
     fn get_sources(&self, section: &str, name: &str) -> Cow<[ValueSource]>;
 
@@ -101,6 +120,22 @@ pub trait ConfigExt: Config {
         Ok(self.get_opt(section, name)?.unwrap_or_else(default_func))
     }
 
+    /// Describe how the value of a config is decided, including overrides.
+    ///
+    /// For example, "value 1 from /etc/sapling/system.rc:12 overridden by
+    /// value 2 from --config".
+    fn explain(&self, section: &str, name: &str) -> String {
+        let sources = self.get_sources(section, name);
+        if sources.is_empty() {
+            return format!("{}.{} is not set", section, name);
+        }
+        sources
+            .iter()
+            .map(|source| source.to_string())
+            .collect::<Vec<_>>()
+            .join(" overridden by ")
+    }
+
     /// Get a config item. Convert to type `T`.
     ///
     /// If the config item is not set, return `T::default()`.
@@ -235,6 +270,35 @@ impl ValueSource {
     pub fn file_content(&self) -> Option<Text> {
         self.location.as_ref().map(|src| src.content.clone())
     }
+
+    /// Return the 1-based line number of the config value in the file.
+    /// Or `None` if there is no such information.
+    pub fn line_number(&self) -> Option<usize> {
+        self.location.as_ref().map(|src| {
+            let end = src.location.start.min(src.content.len());
+            src.content.as_bytes()[..end]
+                .iter()
+                .filter(|&&b| b == b'\n')
+                .count()
+                + 1
+        })
+    }
+}
+
+impl fmt::Display for ValueSource {
+    /// Format as "value X from path:line", or "unset from source".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "value {} from ", value)?,
+            None => write!(f, "unset from ")?,
+        }
+        match (&self.location, self.line_number()) {
+            (Some(location), Some(line)) if !location.path.as_os_str().is_empty() => {
+                write!(f, "{}:{}", location.path.display(), line)
+            }
+            _ => write!(f, "{}", self.source),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -256,4 +320,23 @@ mod tests {
         // Make sure we can pass BTreeMap config to generic func.
         wants_impl(&map);
     }
+
+    #[test]
+    fn test_matching() {
+        let map: BTreeMap<&str, &str> = vec![
+            ("merge-tools.vimdiff.args", "a"),
+            ("merge-tools.vimdiff.priority", "1"),
+            ("merge-tools.meld.args", "b"),
+            ("merge.tool", "meld"),
+            ("paths.default", "x"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            map.keys_matching("merge-tools", &Regex::new(r"\.args$").unwrap()),
+            vec!["meld.args", "vimdiff.args"]
+        );
+        assert_eq!(map.sections_matching("merge"), vec!["merge-tools", "merge"]);
+        assert!(map.sections_matching("foo").is_empty());
+    }
 }
@@ -8,12 +8,19 @@
 use std::borrow::Cow;
 #[cfg(any(feature = "fb", test))]
 use std::collections::hash_map::RandomState;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::fmt;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
 use std::str;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::SystemTime;
 
 use configmodel::Config;
 pub use configmodel::ValueLocation;
@@ -22,16 +29,31 @@ use indexmap::IndexMap;
 use minibytes::Text;
 use pest_hgrc::parse;
 use pest_hgrc::Instruction;
+use sha2::Digest;
+use sha2::Sha256;
 use util::path::expand_path;
 
 use crate::error::Error;
 
 /// Collection of config sections loaded from various sources.
+///
+/// Cloning a `ConfigSet` is cheap. The storage is shared and only copied
+/// (per section) when mutated.
 #[derive(Clone, Default, Debug)]
 pub struct ConfigSet {
-    sections: IndexMap<Text, Section>,
+    sections: Arc<IndexMap<Text, Arc<Section>>>,
     // canonicalized files that were loaded, including files with errors
-    files: Vec<PathBuf>,
+    files: Arc<Vec<PathBuf>>,
+    // how the files were loaded, used by `reload_changed`
+    loaded: Arc<HashMap<PathBuf, LoadedFile>>,
+    // Number of values of each (section, name) not set by a loaded file,
+    // like values set by `set` or `parse`. Used by `reload_changed` to tell
+    // which of them were set before a file was loaded.
+    untracked: Arc<UntrackedCounts>,
+    stats: Arc<LoadStats>,
+    // (section, name) pairs read by `get`, if access tracking is enabled.
+    // Shared with clones.
+    accessed: Option<Arc<Mutex<BTreeSet<(Text, Text)>>>>,
 }
 
 /// Internal representation of a config section.
@@ -40,6 +62,77 @@ struct Section {
     items: IndexMap<Text, Vec<ValueSource>>,
 }
 
+/// Statistics about loading config files.
+#[derive(Clone, Default, Debug)]
+pub struct LoadStats {
+    /// Directories that were loaded, with `.rc` files in them in the order
+    /// they were loaded.
+    pub directories: Vec<(PathBuf, Vec<PathBuf>)>,
+}
+
+/// Sort key of a file in a config directory. Files with a numeric prefix,
+/// like `10-foo.rc`, come first, ordered by the number. Other files come
+/// next. Ties are broken by file name.
+fn directory_sort_key(path: &Path) -> (bool, u64, OsString) {
+    let name = path.file_name().unwrap_or_default();
+    let lossy_name = name.to_string_lossy();
+    let digits: &str = match lossy_name.find(|c: char| !c.is_ascii_digit()) {
+        Some(end) => &lossy_name[..end],
+        None => &lossy_name,
+    };
+    let priority: Option<u64> = match digits {
+        "" => None,
+        digits => Some(digits.parse().unwrap_or(u64::MAX)),
+    };
+    (
+        priority.is_none(),
+        priority.unwrap_or(0),
+        name.to_os_string(),
+    )
+}
+
+type UntrackedCounts = IndexMap<(Text, Text), usize>;
+
+/// Information about a loaded file to reload it.
+#[derive(Clone)]
+struct LoadedFile {
+    kind: FileKind,
+    opts: Options,
+    // (mtime, size) when the file was loaded, or `None` if `stat` failed.
+    stat: Option<(SystemTime, u64)>,
+    // `ConfigSet::untracked` when the file was loaded.
+    untracked: Arc<UntrackedCounts>,
+}
+
+#[derive(Clone, Debug)]
+enum FileKind {
+    Hgrc,
+    Toml,
+    // Imported JSON into the given section.
+    Json(Text),
+}
+
+impl fmt::Debug for LoadedFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LoadedFile")
+            .field("kind", &self.kind)
+            .field("source", &self.opts.source)
+            .field("stat", &self.stat)
+            .finish()
+    }
+}
+
+fn file_stat(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Test if a value at `location` was not loaded from a file, for example
+/// by `parse`.
+fn is_untracked_location(location: &ValueLocation) -> bool {
+    location.path.as_os_str().is_empty()
+}
+
 /// Options that affects config setting functions like `load_path`, `parse`,
 /// and `set`.
 #[derive(Clone, Default)]
@@ -62,6 +155,10 @@ impl Config for ConfigSet {
     /// Get config value for a given config.
     /// Return `None` if the config item does not exist or is unset.
     fn get_considering_unset(&self, section: &str, name: &str) -> Option<Option<Text>> {
+        if let Some(accessed) = &self.accessed {
+            let mut accessed = accessed.lock().unwrap();
+            accessed.insert((Text::copy_from_slice(section), Text::copy_from_slice(name)));
+        }
         let section = self.sections.get(section)?;
         let value_sources: &Vec<ValueSource> = section.items.get(name)?;
         let value = value_sources.last()?.value.clone();
@@ -105,9 +202,32 @@ impl ConfigSet {
         Default::default()
     }
 
-    /// Load config files at given path. The path is a file.
+    /// Start recording configs read by `get` and its variants. Configs that
+    /// are read but not set are also recorded.
     ///
-    /// If `path` is a directory, it is ignored.
+    /// Clones of this `ConfigSet` made afterwards share the same record.
+    pub fn track_accesses(&mut self) {
+        if self.accessed.is_none() {
+            self.accessed = Some(Default::default());
+        }
+    }
+
+    /// Return `(section, name)` pairs read so far, sorted. Return an empty
+    /// list if `track_accesses` was not called.
+    pub fn accessed(&self) -> Vec<(Text, Text)> {
+        match &self.accessed {
+            Some(accessed) => accessed.lock().unwrap().iter().cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Load config files at given path.
+    ///
+    /// If `path` is a directory, files with names ending with `.rc` in it will
+    /// be loaded, in a deterministic order: files with a numeric prefix (ex.
+    /// `10-foo.rc`) are loaded first, ordered by the number, then other files
+    /// ordered by name. Later files override earlier ones. The order is
+    /// recorded in `load_stats`.
     /// If `path` is a file, it will be loaded directly.
     ///
     /// A config file can use `%include` to load other paths (directories or files). They will
@@ -142,6 +262,168 @@ impl ConfigSet {
         errors
     }
 
+    /// Load a TOML config file at given path.
+    ///
+    /// Top-level tables are mapped to sections. Nested tables are flattened
+    /// into dotted config names, so `[merge-tools.vimdiff]` with `args = ".."`
+    /// sets `merge-tools.vimdiff.args`. Non-string values are converted to
+    /// their TOML representation, and arrays are joined by commas.
+    ///
+    /// Loaded values participate in overrides and source tracking the same
+    /// way as values loaded by `load_path`. Missing files are ignored.
+    ///
+    /// Return a list of errors.
+    pub fn load_toml<P: AsRef<Path>>(&mut self, path: P, opts: &Options) -> Vec<Error> {
+        let mut errors = Vec::new();
+        if let Ok(path) = path.as_ref().canonicalize() {
+            self.record_file(&path, FileKind::Toml, opts);
+            match fs::read_to_string(&path) {
+                Ok(text) => self.load_toml_content(&path, Text::from(text), opts, &mut errors),
+                Err(error) => errors.push(Error::Io(path, error)),
+            }
+        }
+        errors
+    }
+
+    fn load_toml_content(
+        &mut self,
+        path: &Path,
+        buf: Text,
+        opts: &Options,
+        errors: &mut Vec<Error>,
+    ) {
+        type Sections = IndexMap<String, IndexMap<String, toml::Spanned<toml::Value>>>;
+
+        tracing::debug!(
+            "load {} from toml path '{}' ({} bytes)",
+            path.display(),
+            opts.source.as_ref(),
+            buf.len()
+        );
+
+        let sections: Sections = match toml::from_str(&buf) {
+            Ok(sections) => sections,
+            Err(error) => {
+                return errors.push(Error::ParseFile(path.to_path_buf(), error.to_string()));
+            }
+        };
+
+        let shared_path = Arc::new(path.to_path_buf());
+        for (section, items) in sections {
+            let section = Text::from(section);
+            for (name, value) in items {
+                let location = ValueLocation {
+                    path: shared_path.clone(),
+                    content: buf.clone(),
+                    location: value.start()..value.end(),
+                };
+                let mut flattened = Vec::new();
+                flatten_toml_value(name, value.into_inner(), &mut flattened);
+                for (name, value) in flattened {
+                    self.set_internal(
+                        section.clone(),
+                        Text::from(name),
+                        Some(Text::from(value)),
+                        Some(location.clone()),
+                        opts,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Import a JSON file containing a flat object into `section`.
+    ///
+    /// Each key becomes a config name in `section`, with a `ValueLocation`
+    /// pointing to the value in the JSON file. Strings are used as-is,
+    /// `null` unsets the config, arrays are joined by commas, and other
+    /// values use their JSON representation. Nested objects are reported
+    /// as errors. Missing files are ignored.
+    ///
+    /// Return a list of errors.
+    pub fn load_json<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        section: impl AsRef<str>,
+        opts: &Options,
+    ) -> Vec<Error> {
+        let mut errors = Vec::new();
+        if let Ok(path) = path.as_ref().canonicalize() {
+            let section = Text::copy_from_slice(section.as_ref());
+            self.record_file(&path, FileKind::Json(section.clone()), opts);
+            match fs::read_to_string(&path) {
+                Ok(text) => {
+                    self.load_json_content(&path, Text::from(text), section, opts, &mut errors)
+                }
+                Err(error) => errors.push(Error::Io(path, error)),
+            }
+        }
+        errors
+    }
+
+    fn load_json_content(
+        &mut self,
+        path: &Path,
+        buf: Text,
+        section: Text,
+        opts: &Options,
+        errors: &mut Vec<Error>,
+    ) {
+        tracing::debug!(
+            "load {} from json path '{}' ({} bytes)",
+            path.display(),
+            opts.source.as_ref(),
+            buf.len()
+        );
+
+        let items: IndexMap<String, &serde_json::value::RawValue> = match serde_json::from_str(&buf)
+        {
+            Ok(items) => items,
+            Err(error) => {
+                return errors.push(Error::ParseFile(path.to_path_buf(), error.to_string()));
+            }
+        };
+
+        let shared_path = Arc::new(path.to_path_buf());
+        for (name, raw) in items {
+            let raw = raw.get();
+            // `raw` is a slice of `buf`. Calculate its offset.
+            let start = raw.as_ptr() as usize - buf.as_ptr() as usize;
+            let value = match serde_json::from_str::<serde_json::Value>(raw) {
+                Ok(serde_json::Value::Null) => None,
+                Ok(serde_json::Value::String(s)) => Some(Text::from(s)),
+                Ok(serde_json::Value::Array(values)) => {
+                    let values: Vec<String> = values
+                        .into_iter()
+                        .map(|v| match v {
+                            serde_json::Value::String(s) => s,
+                            v => v.to_string(),
+                        })
+                        .collect();
+                    Some(Text::from(values.join(",")))
+                }
+                Ok(serde_json::Value::Object(_)) => {
+                    let message = format!("{}: nested objects are not supported", name);
+                    errors.push(Error::ParseFile(path.to_path_buf(), message));
+                    continue;
+                }
+                _ => Some(Text::from(raw.to_string())),
+            };
+            let location = ValueLocation {
+                path: shared_path.clone(),
+                content: buf.clone(),
+                location: start..start + raw.len(),
+            };
+            self.set_internal(
+                section.clone(),
+                Text::from(name),
+                value,
+                Some(location),
+                opts,
+            );
+        }
+    }
+
     /// Load content of an unnamed config file. The `ValueLocation`s of loaded config items will
     /// have an empty `path`.
     ///
@@ -169,6 +451,55 @@ impl ConfigSet {
         self.set_internal(section, name, value, None, &opts)
     }
 
+    /// Set a config item temporarily. The override is removed from the
+    /// value history when the returned guard is dropped. Other changes made
+    /// while the guard was alive are kept.
+    ///
+    /// The guard dereferences to the `ConfigSet`, so it can be used to read
+    /// the config, or to create nested overrides.
+    pub fn scoped_set(
+        &mut self,
+        section: impl AsRef<str>,
+        name: impl AsRef<str>,
+        value: Option<impl AsRef<str>>,
+        opts: &Options,
+    ) -> OverrideGuard<'_> {
+        let section = Text::copy_from_slice(section.as_ref());
+        let name = Text::copy_from_slice(name.as_ref());
+        let value = value.map(|v| Text::copy_from_slice(v.as_ref()));
+        let filtered = opts.filter(section, name, value);
+
+        let (section, name) = match &filtered {
+            Some((section, name, _)) => (section.clone(), name.clone()),
+            None => (Text::new(), Text::new()),
+        };
+        let existing = self.sections.get(&section);
+        let section_existed = existing.is_some();
+        let index = existing
+            .and_then(|s| s.items.get(&name))
+            .map_or(0, |values| values.len());
+        let untracked_count = self
+            .untracked
+            .get(&(section.clone(), name.clone()))
+            .cloned()
+            .unwrap_or(0);
+
+        let mut guard = OverrideGuard {
+            config: self,
+            section,
+            name,
+            section_existed,
+            index,
+            untracked_count,
+            active: filtered.is_some(),
+        };
+        if let Some((section, name, value)) = filtered {
+            let opts = Options::new().source(opts.source.clone());
+            guard.config.set_internal(section, name, value, None, &opts);
+        }
+        guard
+    }
+
     fn set_internal(
         &mut self,
         section: Text,
@@ -177,16 +508,16 @@ impl ConfigSet {
         location: Option<ValueLocation>,
         opts: &Options,
     ) {
-        let filtered = opts
-            .filters
-            .iter()
-            .fold(Some((section, name, value)), move |acc, func| {
-                acc.and_then(|(section, name, value)| func(section, name, value))
-            });
-        if let Some((section, name, value)) = filtered {
-            self.sections
+        if let Some((section, name, value)) = opts.filter(section, name, value) {
+            if location.as_ref().map_or(true, is_untracked_location) {
+                *Arc::make_mut(&mut self.untracked)
+                    .entry((section.clone(), name.clone()))
+                    .or_default() += 1;
+            }
+            let section = Arc::make_mut(&mut self.sections)
                 .entry(section)
-                .or_insert_with(Default::default)
+                .or_insert_with(Default::default);
+            Arc::make_mut(section)
                 .items
                 .entry(name)
                 .or_insert_with(|| Vec::with_capacity(1))
@@ -198,6 +529,23 @@ impl ConfigSet {
         }
     }
 
+    /// Unset every config in a section that currently has a value.
+    /// Each affected config gets its own `ValueSource` with `value = None`.
+    fn unset_section(&mut self, section: Text, location: ValueLocation, opts: &Options) {
+        let names: Vec<Text> = match self.sections.get(&section) {
+            None => return,
+            Some(existing) => existing
+                .items
+                .iter()
+                .filter(|(_, values)| matches!(values.last(), Some(v) if v.value.is_some()))
+                .map(|(name, _)| name.clone())
+                .collect(),
+        };
+        for name in names {
+            self.set_internal(section.clone(), name, None, Some(location.clone()), opts);
+        }
+    }
+
     /// override config values from a list of --config overrides
     pub(crate) fn set_overrides(&mut self, overrides: &[String]) -> crate::Result<()> {
         for config_override in overrides {
@@ -218,6 +566,181 @@ impl ConfigSet {
         Ok(())
     }
 
+    /// Record a loaded file so it can be reloaded by `reload_changed`.
+    fn record_file(&mut self, path: &Path, kind: FileKind, opts: &Options) {
+        let loaded = LoadedFile {
+            kind,
+            opts: opts.clone(),
+            stat: file_stat(path),
+            untracked: self.untracked.clone(),
+        };
+        Arc::make_mut(&mut self.files).push(path.to_path_buf());
+        Arc::make_mut(&mut self.loaded).insert(path.to_path_buf(), loaded);
+    }
+
+    /// Reload files that were changed on disk since they were loaded.
+    ///
+    /// Files are considered changed if their mtime or size changed. Only
+    /// changed files are parsed again. Their new config items replace the
+    /// old ones at the same position in the override history, so overrides
+    /// from other files, or from `set`, keep their precedence. Config items
+    /// that are new to a changed file are inserted after items that were set
+    /// before the file was loaded, and before everything else.
+    ///
+    /// Items from a file that fails to parse are kept as they were, and the
+    /// file is reloaded again next time. Items from a deleted file are
+    /// removed.
+    ///
+    /// `%include`s of files that are already loaded are not followed, since
+    /// those files are reloaded separately if changed.
+    ///
+    /// Return a list of errors.
+    pub fn reload_changed(&mut self) -> Vec<Error> {
+        let mut errors = Vec::new();
+        let changed: Vec<(PathBuf, LoadedFile, Option<(SystemTime, u64)>)> = self
+            .files
+            .iter()
+            .filter_map(|path| {
+                let loaded = self.loaded.get(path)?;
+                let stat = file_stat(path);
+                if stat != loaded.stat {
+                    Some((path.clone(), loaded.clone(), stat))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        for (path, loaded, stat) in changed {
+            tracing::debug!("reload changed file {}", path.display());
+            let mut reloaded = ConfigSet::new();
+            let mut file_errors = Vec::new();
+            match &loaded.kind {
+                FileKind::Hgrc => {
+                    let mut visited: HashSet<PathBuf> = self.files.iter().cloned().collect();
+                    visited.remove(&path);
+                    reloaded.load_file(&path, &loaded.opts, &mut visited, &mut file_errors);
+                }
+                FileKind::Toml => file_errors.extend(reloaded.load_toml(&path, &loaded.opts)),
+                FileKind::Json(section) => {
+                    file_errors.extend(reloaded.load_json(&path, section, &loaded.opts))
+                }
+            }
+            if !file_errors.is_empty() {
+                // Keep the old items instead of dropping everything from the file.
+                errors.extend(file_errors);
+                continue;
+            }
+            self.splice_file(&path, &loaded, reloaded);
+            // A deleted file is not recorded by the reload. Forget its old stat
+            // so it is not reloaded again until it is recreated.
+            if let Some(file) = Arc::make_mut(&mut self.loaded).get_mut(&path) {
+                file.stat = stat;
+            }
+        }
+        errors
+    }
+
+    /// Replace config items from `path` with items from `reloaded`.
+    /// `loaded` is how `path` was loaded originally.
+    fn splice_file(&mut self, path: &Path, loaded: &LoadedFile, reloaded: ConfigSet) {
+        let order: HashMap<&Path, usize> = self
+            .files
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.as_path(), i))
+            .collect();
+        let position = order.get(path).cloned().unwrap_or(usize::MAX);
+        let is_from_path = |v: &ValueSource| match &v.location {
+            Some(location) => location.path.as_path() == path,
+            None => false,
+        };
+        let is_untracked =
+            |v: &ValueSource| v.location.as_ref().map_or(true, is_untracked_location);
+        let is_loaded_before = |v: &ValueSource| match &v.location {
+            Some(location) => order
+                .get(location.path.as_path())
+                .map_or(false, |&i| i < position),
+            None => false,
+        };
+        // Number of untracked values of each config that were set before
+        // `path` was loaded.
+        let untracked_before = &loaded.untracked;
+
+        let sections = Arc::make_mut(&mut self.sections);
+
+        // Remove old items. Remember where they were.
+        let mut insert_at: HashMap<(Text, Text), usize> = HashMap::new();
+        for (section_name, section) in sections.iter_mut() {
+            if !section.items.values().flatten().any(is_from_path) {
+                continue;
+            }
+            for (name, values) in Arc::make_mut(section).items.iter_mut() {
+                if let Some(index) = values.iter().position(is_from_path) {
+                    insert_at.insert((section_name.clone(), name.clone()), index);
+                    values.retain(|v| !is_from_path(v));
+                }
+            }
+        }
+
+        // Insert new items.
+        for (section_name, reloaded_section) in reloaded.sections.iter() {
+            let section = sections
+                .entry(section_name.clone())
+                .or_insert_with(Default::default);
+            let section = Arc::make_mut(section);
+            for (name, new_values) in reloaded_section.items.iter() {
+                let values = section.items.entry(name.clone()).or_insert_with(Vec::new);
+                let index = match insert_at.get(&(section_name.clone(), name.clone())) {
+                    Some(&index) => index,
+                    None => {
+                        let key = (section_name.clone(), name.clone());
+                        let before = untracked_before.get(&key).cloned().unwrap_or(0);
+                        let mut untracked_seen = 0;
+                        values
+                            .iter()
+                            .position(|v| {
+                                if is_untracked(v) {
+                                    untracked_seen += 1;
+                                    untracked_seen > before
+                                } else {
+                                    !is_loaded_before(v)
+                                }
+                            })
+                            .unwrap_or(values.len())
+                    }
+                };
+                let tail = values.split_off(index);
+                values.extend(new_values.iter().cloned());
+                values.extend(tail);
+            }
+        }
+
+        // Remove configs that are no longer set by anything.
+        for section in sections.values_mut() {
+            if section.items.values().any(|values| values.is_empty()) {
+                Arc::make_mut(section)
+                    .items
+                    .retain(|_, values| !values.is_empty());
+            }
+        }
+        sections.retain(|_, section| !section.items.is_empty());
+
+        // Track newly included files.
+        for path in reloaded.files.iter() {
+            if !self.loaded.contains_key(path) {
+                Arc::make_mut(&mut self.files).push(path.clone());
+            }
+        }
+        let untracked = loaded.untracked.clone();
+        let loaded = Arc::make_mut(&mut self.loaded);
+        for (path, file) in reloaded.loaded.iter() {
+            let mut file = file.clone();
+            file.untracked = untracked.clone();
+            loaded.insert(path.clone(), file);
+        }
+    }
+
     fn load_file(
         &mut self,
         path: &Path,
@@ -234,7 +757,11 @@ impl ConfigSet {
                 return;
             }
 
-            self.files.push(path.to_path_buf());
+            if path.is_dir() {
+                return self.load_dir(path, opts, visited, errors);
+            }
+
+            self.record_file(path, FileKind::Hgrc, opts);
 
             match fs::read_to_string(path) {
                 Ok(mut text) => {
@@ -253,11 +780,14 @@ impl ConfigSet {
             // `canonicalize`. `C:\foo\.\x` would be canonicalized without errors.
             #[cfg(windows)]
             {
-                if let Some(path_str) = path.to_str() {
-                    if path_str.starts_with(r"\\?\") {
-                        let path = Path::new(&path_str[4..]);
-                        self.load_file(&path, opts, visited, errors);
-                    }
+                use std::os::windows::ffi::OsStrExt;
+                use std::os::windows::ffi::OsStringExt;
+
+                let wide: Vec<u16> = path.as_os_str().encode_wide().collect();
+                let prefix: Vec<u16> = r"\\?\".encode_utf16().collect();
+                if let Some(rest) = wide.strip_prefix(prefix.as_slice()) {
+                    let path = PathBuf::from(std::ffi::OsString::from_wide(rest));
+                    self.load_file(&path, opts, visited, errors);
                 }
             }
         }
@@ -267,6 +797,37 @@ impl ConfigSet {
         // reported in `errors`.
     }
 
+    fn load_dir(
+        &mut self,
+        path: &Path,
+        opts: &Options,
+        visited: &mut HashSet<PathBuf>,
+        errors: &mut Vec<Error>,
+    ) {
+        let entries = match fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(error) => return errors.push(Error::Io(path.to_path_buf(), error)),
+        };
+        let mut rc_paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|p| p.extension() == Some(OsStr::new("rc")) && p.is_file())
+            .collect();
+        rc_paths.sort_by_cached_key(|p| directory_sort_key(p));
+
+        Arc::make_mut(&mut self.stats)
+            .directories
+            .push((path.to_path_buf(), rc_paths.clone()));
+        for rc_path in rc_paths {
+            self.load_file(&rc_path, opts, visited, errors);
+        }
+    }
+
+    /// Statistics about loaded config files.
+    pub fn load_stats(&self) -> &LoadStats {
+        &self.stats
+    }
+
     fn load_file_content(
         &mut self,
         path: &Path,
@@ -315,14 +876,26 @@ impl ConfigSet {
                     name,
                     span,
                 } => {
-                    let section = buf.slice_to_bytes(section);
-                    let name = buf.slice_to_bytes(name);
                     let location = ValueLocation {
                         path: shared_path.clone(),
                         content: buf.clone(),
                         location: span,
                     };
-                    self.set_internal(section.clone(), name, None, location.into(), opts);
+                    let wildcard_section = match name {
+                        "*" => Some(section),
+                        _ => name.strip_suffix(".*"),
+                    };
+                    match wildcard_section {
+                        Some(section) => {
+                            let section = buf.slice_to_bytes(section);
+                            self.unset_section(section, location, opts);
+                        }
+                        None => {
+                            let section = buf.slice_to_bytes(section);
+                            let name = buf.slice_to_bytes(name);
+                            self.set_internal(section, name, None, location.into(), opts);
+                        }
+                    }
                 }
                 Instruction::Include {
                     path: include_path,
@@ -362,12 +935,16 @@ impl ConfigSet {
                     if let Some(value) = value.value() {
                         result.push_str(key);
                         result.push_str("=");
-                        // When a newline delimited list is loaded, the whitespace around each
-                        // entry is trimmed. In order for the serialized config to be parsable, we
-                        // need some indentation after each newline. Since this whitespace will be
-                        // stripped on load, it shouldn't hurt anything.
-                        let value = value.replace("\n", "\n ");
-                        result.push_str(&value);
+                        if needs_quote(value) {
+                            result.push_str(&quote(value));
+                        } else {
+                            // When a newline delimited list is loaded, the whitespace around each
+                            // entry is trimmed. In order for the serialized config to be parsable,
+                            // we need some indentation after each newline. Since this whitespace
+                            // will be stripped on load, it shouldn't hurt anything.
+                            let value = value.replace("\n", "\n ");
+                            result.push_str(&value);
+                        }
                         result.push_str("\n");
                     }
                 }
@@ -379,6 +956,64 @@ impl ConfigSet {
         result
     }
 
+    /// Export the effective config as JSON for machine consumption.
+    ///
+    /// The output is in the form of `{section: {name: {value, source, path, range}}}`.
+    /// `value` is `null` if the config is unset. `path` and `range` are `null`
+    /// if the value was not loaded from a file.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut result = serde_json::Map::new();
+        for (section_name, section) in self.sections.iter() {
+            let mut items = serde_json::Map::new();
+            for (name, values) in section.items.iter() {
+                if let Some(value) = values.last() {
+                    let (path, range) = match value.location() {
+                        Some((path, range)) => (
+                            serde_json::json!(path.to_string_lossy()),
+                            serde_json::json!([range.start, range.end]),
+                        ),
+                        None => (serde_json::Value::Null, serde_json::Value::Null),
+                    };
+                    let item = serde_json::json!({
+                        "value": value.value().as_ref().map(|v| v.as_ref()),
+                        "source": value.source().as_ref(),
+                        "path": path,
+                        "range": range,
+                    });
+                    items.insert(name.to_string(), item);
+                }
+            }
+            result.insert(section_name.to_string(), serde_json::Value::Object(items));
+        }
+        serde_json::Value::Object(result)
+    }
+
+    /// Calculate a stable hash of the effective config.
+    ///
+    /// Only `(section, name, value)` of set configs are considered. Sources,
+    /// locations, override history and insertion order do not affect the
+    /// result. This is useful to invalidate caches that depend on config.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut items: Vec<(&Text, &Text, &Text)> = Vec::new();
+        for (section_name, section) in self.sections.iter() {
+            for (name, values) in section.items.iter() {
+                if let Some(Some(value)) = values.last().map(|v| v.value()) {
+                    items.push((section_name, name, value));
+                }
+            }
+        }
+        items.sort_unstable();
+
+        let mut hasher = Sha256::new();
+        for (section, name, value) in items {
+            for text in [section, name, value] {
+                hasher.update((text.len() as u64).to_le_bytes());
+                hasher.update(text.as_bytes());
+            }
+        }
+        hasher.finalize().into()
+    }
+
     #[cfg(any(feature = "fb", test))]
     /// Ensures that every value set by `superset_location` matches the final value set by one of
     /// `subset_locations`. This is used during config migrations to ensure the final config
@@ -398,17 +1033,17 @@ impl ConfigSet {
         let subset_locations: HashSet<String, RandomState> =
             HashSet::from_iter(subset_locations.into_iter());
 
-        for (sname, section) in self.sections.iter_mut() {
-            for (kname, values) in section.items.iter_mut() {
+        // Indexes of values to remove. Sections are only copied if they have
+        // values to remove.
+        let mut removals: Vec<(Text, Text, Vec<usize>)> = Vec::new();
+
+        for (sname, section) in self.sections.iter() {
+            for (kname, values) in section.items.iter() {
                 let mut super_value = None;
                 let mut sub_value = None;
-                let values_copy = values.clone();
-
-                let mut removals = 0;
-                for (index, value) in values_copy.iter().enumerate() {
-                    // Convert the index into the original index.
-                    let index = index - removals;
+                let mut removed = Vec::new();
 
+                for (index, value) in values.iter().enumerate() {
                     // Get the filename of the value's rc location
                     let location: Option<String> = value
                         .location()
@@ -449,8 +1084,7 @@ impl ConfigSet {
                                     .unwrap_or_default(),
                                 &location,
                             );
-                            values.remove(index);
-                            removals += 1;
+                            removed.push(index);
                             continue;
                         }
                     }
@@ -463,6 +1097,9 @@ impl ConfigSet {
                         }
                     }
                 }
+                if !removed.is_empty() {
+                    removals.push((sname.clone(), kname.clone(), removed));
+                }
 
                 // If the superset value doesn't match the most recent subset value, remove the
                 // superset value.
@@ -490,16 +1127,177 @@ impl ConfigSet {
             }
         }
 
+        if !removals.is_empty() {
+            let sections = Arc::make_mut(&mut self.sections);
+            for (sname, kname, removed) in removals {
+                if let Some(section) = sections.get_mut(&sname) {
+                    if let Some(values) = Arc::make_mut(section).items.get_mut(&kname) {
+                        for index in removed.into_iter().rev() {
+                            values.remove(index);
+                        }
+                    }
+                }
+            }
+        }
+
         result
     }
 }
 
+/// Convert a TOML value to `(name, value)` config items. Tables are flattened
+/// using dotted names.
+fn flatten_toml_value(name: String, value: toml::Value, out: &mut Vec<(String, String)>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                flatten_toml_value(format!("{}.{}", name, key), value, out);
+            }
+        }
+        value => out.push((name, toml_value_to_string(value))),
+    }
+}
+
+fn toml_value_to_string(value: toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s,
+        toml::Value::Array(values) => values
+            .into_iter()
+            .map(toml_value_to_string)
+            .collect::<Vec<_>>()
+            .join(","),
+        value => value.to_string(),
+    }
+}
+
+/// Test if a value cannot be written as-is without losing information when
+/// parsed back. For example, it has trailing whitespace or control characters.
+fn needs_quote(value: &str) -> bool {
+    let is_space = |c: char| c == ' ' || c == '\t';
+    value.ends_with('\n')
+        || value.contains(|c: char| c.is_ascii_control() && c != '\n')
+        || value
+            .split('\n')
+            .any(|line| line.starts_with(is_space) || line.ends_with(is_space))
+        || pest_hgrc::unquote(value).is_some()
+}
+
+/// Quote a value so it can be decoded by `pest_hgrc::unquote`.
+fn quote(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 3);
+    result.push_str("$'");
+    for ch in value.chars() {
+        match ch {
+            '\\' => result.push_str("\\\\"),
+            '\'' => result.push_str("\\'"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '\0' => result.push_str("\\0"),
+            c if c.is_ascii_control() => result.push_str(&format!("\\x{:02x}", c as u8)),
+            c => result.push(c),
+        }
+    }
+    result.push('\'');
+    result
+}
+
+/// Reverts a `ConfigSet::scoped_set` override on drop.
+pub struct OverrideGuard<'a> {
+    config: &'a mut ConfigSet,
+    section: Text,
+    name: Text,
+    section_existed: bool,
+    // Index of the override in the value history.
+    index: usize,
+    // Number of untracked values of the config before the override.
+    untracked_count: usize,
+    // `false` if the override was dropped by filters.
+    active: bool,
+}
+
+impl std::ops::Deref for OverrideGuard<'_> {
+    type Target = ConfigSet;
+
+    fn deref(&self) -> &ConfigSet {
+        self.config
+    }
+}
+
+impl std::ops::DerefMut for OverrideGuard<'_> {
+    fn deref_mut(&mut self) -> &mut ConfigSet {
+        self.config
+    }
+}
+
+impl Drop for OverrideGuard<'_> {
+    fn drop(&mut self) {
+        if !self.active {
+            return;
+        }
+        let sections = Arc::make_mut(&mut self.config.sections);
+        if let Some(section) = sections.get_mut(&self.section) {
+            let section = Arc::make_mut(section);
+            if let Some(values) = section.items.get_mut(&self.name) {
+                if self.index < values.len() {
+                    values.remove(self.index);
+                }
+                if values.is_empty() {
+                    section.items.shift_remove(&self.name);
+                }
+            }
+            if !self.section_existed && section.items.is_empty() {
+                sections.shift_remove(&self.section);
+            }
+        }
+        // Forget the override in untracked counts, including counts of files
+        // loaded after the override.
+        let key = (self.section.clone(), self.name.clone());
+        let count = self.untracked_count;
+        let forget = |untracked: &mut Arc<UntrackedCounts>| {
+            if untracked.get(&key).map_or(false, |&c| c > count) {
+                let untracked = Arc::make_mut(untracked);
+                if let Some(c) = untracked.get_mut(&key) {
+                    *c -= 1;
+                    if *c == 0 {
+                        untracked.shift_remove(&key);
+                    }
+                }
+            }
+        };
+        forget(&mut self.config.untracked);
+        if self
+            .config
+            .loaded
+            .values()
+            .any(|f| f.untracked.get(&key).map_or(false, |&c| c > count))
+        {
+            for file in Arc::make_mut(&mut self.config.loaded).values_mut() {
+                forget(&mut file.untracked);
+            }
+        }
+    }
+}
+
 impl Options {
     /// Create a default `Options`.
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Apply filters to a config item.
+    fn filter(
+        &self,
+        section: Text,
+        name: Text,
+        value: Option<Text>,
+    ) -> Option<(Text, Text, Option<Text>)> {
+        self.filters
+            .iter()
+            .fold(Some((section, name, value)), move |acc, func| {
+                acc.and_then(|(section, name, value)| func(section, name, value))
+            })
+    }
+
     /// Append a filter. A filter can decide to ignore a config item, or change its section,
     /// config name, or even value. The filter function takes a tuple of `(section, name, value)`
     /// and outputs `None` to prevent inserting that value, or `Some((section, name, value))` to
@@ -623,6 +1421,78 @@ pub(crate) mod tests {
         assert_eq!(cfg.clone().get("x", "a"), Some("1".into()));
     }
 
+    #[test]
+    fn test_clone_copy_on_write() {
+        let mut cfg = ConfigSet::new();
+        cfg.set("x", "a", Some("1"), &"set1".into());
+        cfg.set("y", "b", Some("2"), &"set1".into());
+
+        let mut cloned = cfg.clone();
+        assert!(Arc::ptr_eq(&cfg.sections, &cloned.sections));
+
+        cloned.set("x", "a", Some("3"), &"set2".into());
+        assert_eq!(cfg.get("x", "a"), Some("1".into()));
+        assert_eq!(cfg.get_sources("x", "a").len(), 1);
+        assert_eq!(cloned.get("x", "a"), Some("3".into()));
+        assert_eq!(cloned.get_sources("x", "a").len(), 2);
+
+        // Untouched sections are still shared.
+        assert!(Arc::ptr_eq(&cfg.sections["y"], &cloned.sections["y"]));
+        assert!(!Arc::ptr_eq(&cfg.sections["x"], &cloned.sections["x"]));
+    }
+
+    #[test]
+    fn test_scoped_set() {
+        let mut cfg = ConfigSet::new();
+        cfg.set("x", "a", Some("1"), &"set1".into());
+        {
+            let mut guard = cfg.scoped_set("x", "a", Some("2"), &"scoped".into());
+            assert_eq!(guard.get("x", "a"), Some("2".into()));
+            {
+                let guard = guard.scoped_set("y", "b", None::<&str>, &"scoped".into());
+                assert_eq!(guard.get_considering_unset("y", "b"), Some(None));
+                assert_eq!(guard.sections(), vec![Text::from("x"), Text::from("y")]);
+            }
+            assert_eq!(guard.sections(), vec![Text::from("x")]);
+            guard.set("x", "a", Some("3"), &"set2".into());
+            guard.set("x", "c", Some("4"), &"set2".into());
+        }
+        // Only the override is reverted. Later writes are kept.
+        assert_eq!(cfg.get("x", "a"), Some("3".into()));
+        let sources = cfg.get_sources("x", "a");
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].value(), &Some("1".into()));
+        assert_eq!(cfg.get("x", "c"), Some("4".into()));
+
+        {
+            let _guard = cfg.scoped_set("x", "d", Some("5"), &"scoped".into());
+        }
+        assert_eq!(cfg.get_sources("x", "d").len(), 0);
+        assert_eq!(cfg.keys("x"), vec![Text::from("a"), Text::from("c")]);
+    }
+
+    #[test]
+    fn test_track_accesses() {
+        let mut cfg = ConfigSet::new();
+        cfg.set("x", "a", Some("1"), &"set1".into());
+        cfg.get("x", "a");
+        assert!(cfg.accessed().is_empty());
+
+        cfg.track_accesses();
+        cfg.get("x", "a");
+        cfg.get_or("y", "b", || 2).unwrap();
+        cfg.clone().get_nonempty("x", "a");
+        cfg.clone().get("x", "c");
+        assert_eq!(
+            cfg.accessed(),
+            vec![
+                (Text::from("x"), Text::from("a")),
+                (Text::from("x"), Text::from("c")),
+                (Text::from("y"), Text::from("b")),
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_basic() {
         let mut cfg = ConfigSet::new();
@@ -870,6 +1740,40 @@ pub(crate) mod tests {
         assert_eq!(sources[1].location().unwrap(), (PathBuf::new(), 26..35));
     }
 
+    #[test]
+    fn test_parse_unset_wildcard() {
+        let mut cfg = ConfigSet::new();
+        cfg.parse(
+            "[hooks]\n\
+             a = 1\n\
+             b = 2\n\
+             %unset b\n\
+             [x]\n\
+             c = 3\n\
+             %unset hooks.*\n\
+             d = 4\n\
+             [hooks]\n\
+             e = 5\n\
+             [x]\n\
+             %unset *\n\
+             f = 6\n",
+            &"test_parse_unset_wildcard".into(),
+        );
+
+        assert_eq!(cfg.get("hooks", "a"), None);
+        assert_eq!(cfg.get("hooks", "b"), None);
+        assert_eq!(cfg.get("hooks", "e"), Some(Text::from("5")));
+        assert_eq!(cfg.get("x", "c"), None);
+        assert_eq!(cfg.get("x", "d"), None);
+        assert_eq!(cfg.get("x", "f"), Some(Text::from("6")));
+
+        // "b" was already unset and does not get another ValueSource.
+        assert_eq!(cfg.get_sources("hooks", "a").len(), 2);
+        assert_eq!(cfg.get_sources("hooks", "b").len(), 2);
+        let sources = cfg.get_sources("hooks", "a");
+        assert_eq!(sources[1].location().unwrap(), (PathBuf::new(), 40..53));
+    }
+
     #[test]
     fn test_filters() {
         fn exclude_list_section_x(
@@ -988,6 +1892,37 @@ pub(crate) mod tests {
         assert_eq!(cfg.get("y", "b"), Some(Text::from("1")));
     }
 
+    #[test]
+    fn test_load_dir_order() {
+        let dir = TempDir::new("test_load_dir_order").unwrap();
+        write_file(dir.path().join("conf.d/z.rc"), "[x]\na=z\nz=z");
+        write_file(dir.path().join("conf.d/a.rc"), "[x]\na=a\nb=a");
+        write_file(dir.path().join("conf.d/10-b.rc"), "[x]\na=10\nb=10\nc=10");
+        write_file(dir.path().join("conf.d/9-c.rc"), "[x]\na=9\nb=9\nc=9\nd=9");
+        // Not loaded: not ending with ".rc", or not a file.
+        write_file(dir.path().join("conf.d/1-e.txt"), "[x]\na=txt");
+        write_file(dir.path().join("conf.d/sub.rc/1.rc"), "[x]\na=sub");
+        write_file(dir.path().join("rootrc"), "%include conf.d\n");
+
+        let mut cfg = ConfigSet::new();
+        let errors = cfg.load_path(dir.path().join("rootrc"), &"test".into());
+        assert!(errors.is_empty(), "load_path had errors {:?}", errors);
+
+        assert_eq!(cfg.get("x", "a"), Some(Text::from("z")));
+        assert_eq!(cfg.get("x", "b"), Some(Text::from("a")));
+        assert_eq!(cfg.get("x", "c"), Some(Text::from("10")));
+        assert_eq!(cfg.get("x", "d"), Some(Text::from("9")));
+
+        let directories = &cfg.load_stats().directories;
+        assert_eq!(directories.len(), 1);
+        let names: Vec<_> = directories[0]
+            .1
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["9-c.rc", "10-b.rc", "a.rc", "z.rc"]);
+    }
+
     #[test]
     fn test_parse_include_builtin() {
         let dir = TempDir::new("test_parse_include").unwrap();
@@ -1026,6 +1961,180 @@ pub(crate) mod tests {
         assert_eq!(cfg.get("y", "b"), Some(Text::from("2")));
     }
 
+    #[test]
+    fn test_load_toml() {
+        let dir = TempDir::new("test_load_toml").unwrap();
+        write_file(
+            dir.path().join("a.toml"),
+            "[x]\n\
+             a = \"1\"\n\
+             b = true\n\
+             c = [\"p\", \"q\", 3]\n\
+             [merge-tools.vimdiff]\n\
+             args = \"$local $other\"\n\
+             priority = 5\n",
+        );
+
+        let mut cfg = ConfigSet::new();
+        cfg.set("x", "a", Some("0"), &"--config".into());
+        let errors = cfg.load_toml(dir.path().join("a.toml"), &"toml".into());
+        assert!(errors.is_empty(), "load_toml had errors {:?}", errors);
+
+        assert_eq!(cfg.get("x", "a"), Some(Text::from("1")));
+        assert_eq!(cfg.get("x", "b"), Some(Text::from("true")));
+        assert_eq!(cfg.get("x", "c"), Some(Text::from("p,q,3")));
+        assert_eq!(
+            cfg.keys("merge-tools"),
+            vec![Text::from("vimdiff.args"), Text::from("vimdiff.priority")]
+        );
+        assert_eq!(
+            cfg.get("merge-tools", "vimdiff.args"),
+            Some(Text::from("$local $other"))
+        );
+
+        let sources = cfg.get_sources("x", "a");
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[1].source(), &"toml");
+        let (path, range) = sources[1].location().unwrap();
+        assert!(path.ends_with("a.toml"));
+        assert_eq!(&sources[1].file_content().unwrap()[range], "\"1\"");
+        assert_eq!(cfg.files().len(), 1);
+
+        write_file(dir.path().join("bad.toml"), "a = 1\n");
+        assert_eq!(
+            cfg.load_toml(dir.path().join("bad.toml"), &"toml".into())
+                .len(),
+            1
+        );
+        assert!(cfg
+            .load_toml(dir.path().join("missing.toml"), &"toml".into())
+            .is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parse_include_expand_non_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = TempDir::new("test_parse_include_expand_non_utf8").unwrap();
+        let non_utf8 = OsStr::from_bytes(b"\xe9t\xe9");
+        let mut env = crate::lock_env();
+        env.set_os("NON_UTF8_DIR", non_utf8);
+
+        write_file(
+            dir.path().join("rootrc"),
+            "%include ./${NON_UTF8_DIR}/1.rc\n",
+        );
+        write_file(dir.path().join(non_utf8).join("1.rc"), "[x]\na=1\n");
+
+        let mut cfg = ConfigSet::new();
+        let errors = cfg.load_path(dir.path().join("rootrc"), &"include_expand".into());
+        assert!(errors.is_empty());
+
+        assert_eq!(cfg.get("x", "a"), Some(Text::from("1")));
+    }
+
+    #[test]
+    fn test_load_json() {
+        let dir = TempDir::new("test_load_json").unwrap();
+        write_file(
+            dir.path().join("a.json"),
+            r#"{"a": "1", "b": true, "c": ["p", 3], "d": null, "e": {"f": 1}}"#,
+        );
+
+        let mut cfg = ConfigSet::new();
+        cfg.set("x", "d", Some("0"), &"--config".into());
+        let errors = cfg.load_json(dir.path().join("a.json"), "x", &"json".into());
+        assert_eq!(errors.len(), 1);
+
+        assert_eq!(cfg.keys("x"), vec!["d", "a", "b", "c"]);
+        assert_eq!(cfg.get("x", "a"), Some(Text::from("1")));
+        assert_eq!(cfg.get("x", "b"), Some(Text::from("true")));
+        assert_eq!(cfg.get("x", "c"), Some(Text::from("p,3")));
+        assert_eq!(cfg.get("x", "d"), None);
+
+        let sources = cfg.get_sources("x", "a");
+        assert_eq!(sources[0].source(), &"json");
+        let (path, range) = sources[0].location().unwrap();
+        assert!(path.ends_with("a.json"));
+        assert_eq!(range, 6..9);
+        assert_eq!(&sources[0].file_content().unwrap()[range], "\"1\"");
+
+        assert!(cfg
+            .load_json(dir.path().join("missing.json"), "x", &"json".into())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_reload_changed() {
+        let dir = TempDir::new("test_reload_changed").unwrap();
+        write_file(dir.path().join("a.rc"), "[x]\na=1\ny=1\nz=1\n");
+        write_file(dir.path().join("b.rc"), "[x]\nb=1\nc=1\n");
+
+        let mut cfg = ConfigSet::new();
+        cfg.set("x", "c", Some("0"), &"set".into());
+        cfg.load_path(dir.path().join("a.rc"), &"a".into());
+        cfg.load_path(dir.path().join("b.rc"), &"b".into());
+        cfg.set("x", "y", Some("override"), &"--config".into());
+        cfg.set("x", "e", Some("override"), &"--config".into());
+        assert!(cfg.reload_changed().is_empty());
+        assert_eq!(cfg.get("x", "a"), Some(Text::from("1")));
+
+        // "c" and "e" are new to a.rc. They are overridden by b.rc and
+        // --config, but override the earlier `set`.
+        write_file(dir.path().join("a.rc"), "[x]\na=22\ny=2\nc=2\nd=2\ne=2\n");
+        let errors = cfg.reload_changed();
+        assert!(errors.is_empty(), "reload_changed had errors {:?}", errors);
+
+        assert_eq!(cfg.get("x", "a"), Some(Text::from("22")));
+        assert_eq!(cfg.get("x", "y"), Some(Text::from("override")));
+        assert_eq!(cfg.get("x", "z"), None);
+        assert_eq!(cfg.get("x", "b"), Some(Text::from("1")));
+        assert_eq!(cfg.get("x", "c"), Some(Text::from("1")));
+        assert_eq!(cfg.get("x", "d"), Some(Text::from("2")));
+        assert_eq!(cfg.get("x", "e"), Some(Text::from("override")));
+        assert_eq!(cfg.keys("x"), vec!["c", "a", "y", "b", "e", "d"]);
+
+        let sources = |cfg: &ConfigSet, name: &str| -> Vec<String> {
+            cfg.get_sources("x", name)
+                .iter()
+                .map(|s| s.source().to_string())
+                .collect()
+        };
+        assert_eq!(sources(&cfg, "c"), ["set", "a", "b"]);
+        assert_eq!(sources(&cfg, "y"), ["a", "--config"]);
+        assert_eq!(sources(&cfg, "e"), ["a", "--config"]);
+        assert_eq!(cfg.files().len(), 2);
+
+        // Items are kept if the file fails to parse.
+        write_file(dir.path().join("a.rc"), "[x]\na=3\n[broken\n");
+        assert!(!cfg.reload_changed().is_empty());
+        assert_eq!(cfg.get("x", "a"), Some(Text::from("22")));
+        assert_eq!(sources(&cfg, "c"), ["set", "a", "b"]);
+
+        // Items from a deleted file are removed, and it is not reloaded again.
+        let path = dir.path().join("a.rc").canonicalize().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(cfg.reload_changed().is_empty());
+        assert_eq!(cfg.get("x", "a"), None);
+        assert_eq!(sources(&cfg, "c"), ["set", "b"]);
+        assert_eq!(cfg.loaded[&path].stat, None);
+        assert!(cfg.reload_changed().is_empty());
+
+        // It is reloaded when recreated.
+        write_file(dir.path().join("a.rc"), "[x]\na=4\n");
+        assert!(cfg.reload_changed().is_empty());
+        assert_eq!(cfg.get("x", "a"), Some(Text::from("4")));
+
+        // Untracked values are counted per config.
+        for _ in 0..10 {
+            cfg.set("x", "e", Some("override"), &"--config".into());
+        }
+        assert_eq!(cfg.untracked.len(), 3);
+        assert_eq!(cfg.untracked[&(Text::from("x"), Text::from("e"))], 11);
+    }
+
     #[test]
     fn test_serialize() {
         let mut cfg = ConfigSet::new();
@@ -1071,6 +2180,115 @@ space_list=value1.a value1.b
         assert_eq!(cfg.sections(), cfg2.sections());
     }
 
+    #[test]
+    fn test_quoted_values() {
+        let mut cfg = ConfigSet::new();
+        let errors = cfg.parse(
+            "[a]\n\
+             trailing = $'value  '\n\
+             escaped = $'\\ttab\\x01\\'\\\\'\n\
+             quoted = $'\"x\"'\n\
+             args = \"$local\" \"$other\"\n\
+             editor = \"C:\\tools\\new.exe\"\n\
+             literal = $'x'y'\n",
+            &"".into(),
+        );
+        assert!(errors.is_empty(), "cfg.parse had errors {:?}", errors);
+        assert_eq!(cfg.get("a", "trailing"), Some("value  ".into()));
+        assert_eq!(cfg.get("a", "escaped"), Some("\ttab\x01'\\".into()));
+        assert_eq!(cfg.get("a", "quoted"), Some("\"x\"".into()));
+        assert_eq!(cfg.get("a", "args"), Some("\"$local\" \"$other\"".into()));
+        // Double quoted values are not decoded.
+        assert_eq!(
+            cfg.get("a", "editor"),
+            Some("\"C:\\tools\\new.exe\"".into())
+        );
+        assert_eq!(cfg.get("a", "literal"), Some("$'x'y'".into()));
+
+        // Verify it round trips. A value that looks like $'...' is quoted.
+        cfg.set("a", "unquoted", Some("$'x'"), &"".into());
+        let mut cfg2 = ConfigSet::new();
+        let errors = cfg2.parse(cfg.to_string(), &"".into());
+        assert!(errors.is_empty(), "cfg2.parse had errors {:?}", errors);
+        for name in [
+            "trailing", "escaped", "quoted", "args", "editor", "literal", "unquoted",
+        ] {
+            assert_eq!(cfg.get("a", name), cfg2.get("a", name));
+        }
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut cfg = ConfigSet::new();
+        cfg.parse("[x]\na = 1\nb = 2\n%unset b\n", &"file".into());
+        cfg.set("y", "c", Some("3"), &"--config".into());
+        assert_eq!(
+            cfg.to_json(),
+            serde_json::json!({
+                "x": {
+                    "a": {"value": "1", "source": "file", "path": "", "range": [8, 9]},
+                    "b": {"value": null, "source": "file", "path": "", "range": [17, 24]},
+                },
+                "y": {
+                    "c": {"value": "3", "source": "--config", "path": null, "range": null},
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let mut cfg1 = ConfigSet::new();
+        cfg1.set("x", "a", Some("1"), &"set1".into());
+        cfg1.set("y", "b", Some("2"), &"set1".into());
+        cfg1.set("y", "c", Some("3"), &"set1".into());
+        cfg1.set("y", "c", None::<Text>, &"set1".into());
+
+        // Different order, sources, and override history.
+        let mut cfg2 = ConfigSet::new();
+        cfg2.set("y", "b", Some("0"), &"set2".into());
+        cfg2.set("y", "b", Some("2"), &"set2".into());
+        cfg2.parse("[x]\na = 1\n", &"set3".into());
+        assert_eq!(cfg1.fingerprint(), cfg2.fingerprint());
+
+        cfg2.set("x", "a", Some("2"), &"set2".into());
+        assert_ne!(cfg1.fingerprint(), cfg2.fingerprint());
+
+        // Values are not ambiguous when concatenated.
+        let mut cfg3 = ConfigSet::new();
+        cfg3.set("x", "ab", Some(""), &"".into());
+        let mut cfg4 = ConfigSet::new();
+        cfg4.set("x", "a", Some("b"), &"".into());
+        assert_ne!(cfg3.fingerprint(), cfg4.fingerprint());
+    }
+
+    #[test]
+    fn test_explain() {
+        let dir = TempDir::new("test_explain").unwrap();
+        let path = dir.path().join("system.rc");
+        write_file(path.clone(), "[x]\nb = 0\n\na = 1\n%unset a\n");
+
+        let mut cfg = ConfigSet::new();
+        cfg.load_path(&path, &"system".into());
+        cfg.set("x", "a", Some("2"), &"--config".into());
+        let path = path.canonicalize().unwrap();
+        assert_eq!(
+            cfg.explain("x", "a"),
+            format!(
+                "value 1 from {0}:4 overridden by unset from {0}:5 overridden by value 2 from --config",
+                path.display()
+            )
+        );
+        assert_eq!(
+            cfg.explain("x", "b"),
+            format!("value 0 from {}:2", path.display())
+        );
+        assert_eq!(cfg.explain("x", "c"), "x.c is not set");
+
+        cfg.parse("[y]\nd = 3\n", &"parsed".into());
+        assert_eq!(cfg.explain("y", "d"), "value 3 from parsed");
+    }
+
     #[test]
     fn test_superset_verifier() {
         let mut cfg = ConfigSet::new();
@@ -1240,6 +2458,7 @@ space_list=value1.a value1.b
         allow_configs.insert(("section2", "key2"));
 
         set(&mut cfg, "section2", "key2", "value2", "subset2");
+        let cloned = cfg.clone();
         cfg.ensure_location_supersets(
             "super".to_string(),
             vec![],
@@ -1254,6 +2473,9 @@ space_list=value1.a value1.b
             cfg.get("section2", "key2"),
             Some(Text::from_static("value2"))
         );
+
+        // Nothing was removed. Sections are still shared.
+        assert!(Arc::ptr_eq(&cfg.sections, &cloned.sections));
     }
 
     #[test]
@@ -44,7 +44,9 @@
 //!
 //! The include path is relative to the directory of the current config
 //! file being parsed. If it's a directory, files with names ending
-//! with `.rc` in it will be read.
+//! with `.rc` in it will be read. Files with a numeric prefix like
+//! `10-foo.rc` are read first, ordered by the number, followed by other
+//! files ordered by name.
 //!
 //! ### Unset a config
 //!
@@ -55,6 +57,9 @@
 //! %unset name1
 //! ```
 //!
+//! Use `%unset *` to unset all configs set so far in the current section,
+//! or `%unset section.*` to do that for another section.
+//!
 //! ### Multi-line values
 //!
 //! Indent non-first lines with a space:
@@ -65,10 +70,24 @@
 //!  line2
 //!  line3
 //! ```
+//!
+//! ### Quoted values
+//!
+//! Write a single-line value as `$'...'` to preserve leading or trailing
+//! whitespace, or to use escape sequences like `\n`, `\t`, `\\`, `\'` and
+//! `\xNN` (ASCII only). Values quoted in other ways are used as-is:
+//!
+//! ```plain,ignore
+//! [section]
+//! name1 = $'value with trailing space '
+//! name2 = $'tab\tseparated'
+//! name3 = "kept with quotes"
+//! ```
 
 pub(crate) mod builtin;
 pub mod config;
 pub mod hg;
+pub mod shared;
 
 pub use configmodel;
 pub use configmodel::convert;
@@ -0,0 +1,146 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Thread-safe config that can be replaced atomically.
+
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+use configmodel::Config;
+use configmodel::ValueSource;
+use minibytes::Text;
+
+use crate::config::ConfigSet;
+
+/// A `ConfigSet` shared by multiple threads, designed for long-lived servers
+/// that reload config periodically.
+///
+/// Readers take a cheap snapshot (an `Arc` clone under a short read lock).
+/// Writers replace the whole `ConfigSet` with `swap`. A reader never sees a
+/// partially updated config.
+pub struct SharedConfig {
+    inner: RwLock<Generation>,
+}
+
+struct Generation {
+    config: Arc<ConfigSet>,
+    number: u64,
+}
+
+impl SharedConfig {
+    /// Create a `SharedConfig` with the given initial config.
+    pub fn new(config: ConfigSet) -> Self {
+        let generation = Generation {
+            config: Arc::new(config),
+            number: 0,
+        };
+        Self {
+            inner: RwLock::new(generation),
+        }
+    }
+
+    /// Get the current config. The returned config is not affected by later
+    /// `swap`s, so multiple reads from it are consistent.
+    pub fn snapshot(&self) -> Arc<ConfigSet> {
+        self.inner.read().unwrap().config.clone()
+    }
+
+    /// Get the current generation number. It starts from 0 and increases
+    /// by 1 on each `swap`.
+    pub fn generation(&self) -> u64 {
+        self.inner.read().unwrap().number
+    }
+
+    /// Replace the config with a new one. Return the old config.
+    pub fn swap(&self, config: ConfigSet) -> Arc<ConfigSet> {
+        let config = Arc::new(config);
+        let mut inner = self.inner.write().unwrap();
+        inner.number += 1;
+        std::mem::replace(&mut inner.config, config)
+    }
+}
+
+impl Config for SharedConfig {
+    fn keys(&self, section: &str) -> Vec<Text> {
+        self.snapshot().keys(section)
+    }
+
+    fn get_considering_unset(&self, section: &str, name: &str) -> Option<Option<Text>> {
+        self.snapshot().get_considering_unset(section, name)
+    }
+
+    fn sections(&self) -> Cow<[Text]> {
+        Cow::Owned(self.snapshot().sections().into_owned())
+    }
+
+    fn get_sources(&self, section: &str, name: &str) -> Cow<[ValueSource]> {
+        Cow::Owned(self.snapshot().get_sources(section, name).into_owned())
+    }
+
+    fn files(&self) -> Cow<[PathBuf]> {
+        Cow::Owned(self.snapshot().files().to_vec())
+    }
+
+    fn layer_name(&self) -> Text {
+        Text::from_static("SharedConfig")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    fn config_with(value: &str) -> ConfigSet {
+        let mut config = ConfigSet::new();
+        config.set("x", "a", Some(value), &"test".into());
+        config.set("x", "b", Some(value), &"test".into());
+        config
+    }
+
+    #[test]
+    fn test_swap() {
+        let shared = SharedConfig::new(config_with("1"));
+        assert_eq!(shared.generation(), 0);
+        assert_eq!(shared.get("x", "a"), Some(Text::from("1")));
+
+        let snapshot = shared.snapshot();
+        let old = shared.swap(config_with("2"));
+        assert_eq!(shared.generation(), 1);
+        assert_eq!(old.get("x", "a"), Some(Text::from("1")));
+        assert_eq!(snapshot.get("x", "a"), Some(Text::from("1")));
+        assert_eq!(shared.get("x", "a"), Some(Text::from("2")));
+        assert_eq!(shared.get_sources("x", "a").len(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_reads() {
+        let shared = Arc::new(SharedConfig::new(config_with("0")));
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        // Values from the same snapshot are always consistent.
+                        let snapshot = shared.snapshot();
+                        assert_eq!(snapshot.get("x", "a"), snapshot.get("x", "b"));
+                    }
+                })
+            })
+            .collect();
+        for i in 1..100 {
+            shared.swap(config_with(&i.to_string()));
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(shared.generation(), 99);
+    }
+}
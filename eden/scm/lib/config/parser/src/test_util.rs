@@ -37,6 +37,16 @@ impl<'a> EnvLock<'a> {
 
         var.set(val);
     }
+
+    #[cfg(unix)]
+    pub(crate) fn set_os(&mut self, name: impl ToString, val: &OsStr) {
+        let var = self
+            .vars
+            .entry(name.to_string())
+            .or_insert_with(|| ScopedEnvVar::new(name));
+
+        var.set(Some(val));
+    }
 }
 
 pub(crate) struct ScopedEnvVar {
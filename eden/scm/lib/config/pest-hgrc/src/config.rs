@@ -66,7 +66,13 @@ impl<'a> Context<'a> {
         }
 
         let value = match lines.len() {
-            1 => Cow::Borrowed(strip_whitespace(lines[0], 0, lines[0].len())),
+            1 => {
+                let line = strip_whitespace(lines[0], 0, lines[0].len());
+                match unquote(line) {
+                    Some(unquoted) => Cow::Owned(unquoted),
+                    None => Cow::Borrowed(line),
+                }
+            }
             _ => {
                 // Strip empty lines at the end.
                 let mut n = lines.len();
@@ -201,6 +207,60 @@ fn strip_whitespace(buf: &str, start: usize, end: usize) -> &str {
         .trim_end_matches(|c| " \t\r\n".contains(c))
 }
 
+/// Decode a `$'...'` quoted value with escape sequences.
+///
+/// Return `None` if `s` is not a single `$'...'` quoted string, in which case
+/// it should be used as-is. Plain double or single quoted values are not
+/// decoded, so existing values like `"C:\tools\new.exe"` keep their meaning.
+/// Supported escapes are `\\`, `\'`, `\"`, `\n`, `\r`, `\t`, `\0` and `\xNN`
+/// (ASCII only, since values are UTF-8). Unknown escapes are kept literally.
+pub fn unquote(s: &str) -> Option<String> {
+    let inner = s.strip_prefix("$'")?.strip_suffix('\'')?;
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            // An unescaped quote in the middle. Not a single quoted string.
+            '\'' => return None,
+            '\\' => {
+                let rest = chars.as_str();
+                let decoded = match chars.next() {
+                    // The closing quote is escaped. Not a single quoted string.
+                    None => return None,
+                    Some('\\') => '\\',
+                    Some('\'') => '\'',
+                    Some('"') => '"',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('0') => '\0',
+                    Some('x') => match rest
+                        .get(1..3)
+                        .filter(|h| h.bytes().all(|b| b.is_ascii_hexdigit()))
+                        .map(|h| u8::from_str_radix(h, 16))
+                    {
+                        Some(Ok(byte)) if byte.is_ascii() => {
+                            chars = rest[3..].chars();
+                            byte as char
+                        }
+                        _ => {
+                            result.push_str("\\x");
+                            continue;
+                        }
+                    },
+                    Some(other) => {
+                        result.push('\\');
+                        other
+                    }
+                };
+                result.push(decoded);
+            }
+            _ => result.push(ch),
+        }
+    }
+    Some(result)
+}
+
 /// Extract text from a larger buffer, with spaces stripped.
 fn extract<'a>(buf: &'a str, span: Span<'a>) -> &'a str {
     strip_whitespace(buf, span.start(), span.end())
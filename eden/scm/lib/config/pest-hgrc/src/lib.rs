@@ -12,6 +12,9 @@
 //! - UnsetConfig(section, name)
 //! - Include(path)
 //!
+//! Single-line values written as `$'...'` are decoded using backslash
+//! escapes (see [`unquote`]). Other values are used as-is.
+//!
 //! Pure. Do not depend on a filesystem.
 
 pub(crate) mod config;
@@ -20,4 +23,5 @@ mod parser;
 mod tests;
 
 pub use config::parse;
+pub use config::unquote;
 pub use config::Instruction;
@@ -6,6 +6,7 @@
  */
 
 use crate::parse;
+use crate::unquote;
 
 #[test]
 fn test_parse_basic() {
@@ -72,3 +73,63 @@ fn test_parse_error() {
   = expected include or unset"#
     );
 }
+
+#[test]
+fn test_parse_quoted() {
+    let config = r#"[a]
+x = $'  spaces  '
+y = $'tab\there\nnewline\x41\\\'"'
+z = $'a' $'b'
+"#;
+    assert_eq!(
+        set_values(config),
+        ["  spaces  ", "tab\there\nnewline\x41\\'\"", "$'a' $'b'"]
+    );
+}
+
+#[test]
+fn test_parse_quoted_backward_compatible() {
+    // Values that are not $'...' quoted are not decoded.
+    let config = r#"[a]
+editor = "C:\tools\new.exe"
+x = "  spaces  "
+y = 'a\tb'
+z = "$local" "$other"
+"#;
+    assert_eq!(
+        set_values(config),
+        [
+            "\"C:\\tools\\new.exe\"",
+            "\"  spaces  \"",
+            "'a\\tb'",
+            "\"$local\" \"$other\"",
+        ]
+    );
+}
+
+fn set_values(config: &str) -> Vec<String> {
+    parse(config)
+        .unwrap()
+        .into_iter()
+        .map(|inst| match inst {
+            crate::Instruction::SetConfig { value, .. } => value.into_owned(),
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_unquote() {
+    assert_eq!(unquote("abc"), None);
+    assert_eq!(unquote("\"a\""), None);
+    assert_eq!(unquote("$'"), None);
+    assert_eq!(unquote("$''"), Some(String::new()));
+    assert_eq!(unquote(r"$'a\'b'"), Some("a'b".to_string()));
+    assert_eq!(unquote(r#"$'a\"b'"#), Some("a\"b".to_string()));
+    assert_eq!(unquote(r"$'a'b'"), None);
+    assert_eq!(unquote(r"$'a\'"), None);
+    assert_eq!(
+        unquote(r"$'\x7f\xff\xg1'"),
+        Some("\x7f\\xff\\xg1".to_string())
+    );
+}
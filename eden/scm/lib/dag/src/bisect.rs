@@ -0,0 +1,70 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! # bisect
+//!
+//! Find the first "bad" vertex by testing vertexes between known "good" and
+//! "bad" vertexes.
+
+use futures::StreamExt;
+
+use crate::DagAlgorithm;
+use crate::NameSet;
+use crate::Result;
+use crate::VertexName;
+
+/// State of a bisection, calculated by [`bisect`].
+#[derive(Debug, Clone)]
+pub struct BisectStep {
+    /// Vertexes that might be the first bad vertex.
+    /// This includes skipped vertexes and the known bad vertexes.
+    pub candidates: NameSet,
+
+    /// The next vertex to test. `None` if there are no untested, unskipped
+    /// vertexes left. In that case, the first bad vertex is one of
+    /// `candidates`.
+    pub next: Option<VertexName>,
+}
+
+/// Calculate the bisection state from `good`, `bad` and `skip` vertexes.
+///
+/// The candidates are `ancestors(bad) - ancestors(good)`. The next vertex
+/// to test is the one that splits the candidates most evenly, regardless of
+/// whether it turns out to be good or bad. Vertexes in `skip` are never
+/// picked as the next vertex.
+///
+/// Candidates are counted using set operations, which are span-based if the
+/// sets are backed by a segmented `IdDag`. So each step is roughly
+/// `O(candidates * segments)` instead of `O(candidates ^ 2)`.
+pub async fn bisect(
+    dag: &dyn DagAlgorithm,
+    good: NameSet,
+    bad: NameSet,
+    skip: NameSet,
+) -> Result<BisectStep> {
+    let candidates = dag.only(bad.clone(), good).await?;
+    let total = candidates.count().await?;
+    let testable = candidates.clone() - bad - skip;
+
+    let mut next: Option<VertexName> = None;
+    let mut best_score = 0;
+    let mut iter = testable.iter().await?;
+    while let Some(vertex) = iter.next().await {
+        let vertex = vertex?;
+        // If `vertex` is bad, `ancestors(vertex) & candidates` remain.
+        // Otherwise, the rest remain.
+        let ancestors = dag.ancestors(vertex.clone().into()).await?;
+        let bad_count = ancestors.intersection(&candidates).count().await?;
+        let score = bad_count.min(total - bad_count);
+        if next.is_none() || score > best_score {
+            next = Some(vertex);
+            best_score = score;
+        }
+    }
+
+    Ok(BisectStep { candidates, next })
+}
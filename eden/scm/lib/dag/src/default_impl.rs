@@ -7,6 +7,7 @@
 
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::sync::Arc;
 
 use futures::StreamExt;
@@ -244,6 +245,34 @@ pub(crate) async fn first_ancestor_nth(
     Ok(Some(vertex))
 }
 
+pub(crate) async fn ancestors_within(
+    this: &(impl DagAlgorithm + ?Sized),
+    set: NameSet,
+    depth: u64,
+) -> Result<NameSet> {
+    // Largest remaining depth a vertex was visited with.
+    let mut visited: HashMap<VertexName, u64> = HashMap::new();
+    let mut to_visit: Vec<(VertexName, u64)> = Vec::new();
+    let mut iter = set.iter().await?;
+    while let Some(next) = iter.next().await {
+        to_visit.push((next?, depth));
+    }
+    while let Some((v, remaining)) = to_visit.pop() {
+        if visited.get(&v).map_or(false, |&r| r >= remaining) {
+            continue;
+        }
+        visited.insert(v.clone(), remaining);
+        if remaining > 0 {
+            for parent in this.parent_names(v).await? {
+                to_visit.push((parent, remaining - 1));
+            }
+        }
+    }
+    let hints = Hints::new_inherit_idmap_dag(set.hints());
+    let set = NameSet::from_iter(visited.into_keys().map(Ok), hints);
+    this.sort(&set).await
+}
+
 pub(crate) async fn first_ancestors(
     this: &(impl DagAlgorithm + ?Sized),
     set: NameSet,
@@ -391,6 +420,65 @@ pub(crate) async fn is_ancestor(
     Ok(false)
 }
 
+pub(crate) async fn depth(this: &(impl DagAlgorithm + ?Sized), name: VertexName) -> Result<u64> {
+    let mut depths: HashMap<VertexName, u64> = HashMap::new();
+    let mut to_visit = vec![name.clone()];
+    while let Some(v) = to_visit.last().cloned() {
+        if depths.contains_key(&v) {
+            to_visit.pop();
+            continue;
+        }
+        let parents = this.parent_names(v.clone()).await?;
+        let missing: Vec<VertexName> = parents
+            .iter()
+            .filter(|p| !depths.contains_key(p))
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            let depth = parents.iter().map(|p| depths[p] + 1).max().unwrap_or(0);
+            depths.insert(v, depth);
+            to_visit.pop();
+        } else {
+            to_visit.extend(missing);
+        }
+    }
+    Ok(depths[&name])
+}
+
+pub(crate) async fn shortest_path(
+    this: &(impl DagAlgorithm + ?Sized),
+    ancestor: VertexName,
+    descendant: VertexName,
+) -> Result<Option<Vec<VertexName>>> {
+    let range = this
+        .range(ancestor.clone().into(), descendant.clone().into())
+        .await?;
+    if !range.contains(&descendant).await? {
+        return Ok(None);
+    }
+    // BFS from `descendant`. `next[x]` is the child of `x` on the path.
+    let mut next: HashMap<VertexName, VertexName> = HashMap::new();
+    let mut to_visit = VecDeque::from(vec![descendant.clone()]);
+    while let Some(v) = to_visit.pop_front() {
+        if v == ancestor {
+            break;
+        }
+        for p in this.parent_names(v.clone()).await? {
+            if p != descendant && !next.contains_key(&p) && range.contains(&p).await? {
+                next.insert(p.clone(), v.clone());
+                to_visit.push_back(p);
+            }
+        }
+    }
+    let mut path = vec![ancestor.clone()];
+    let mut current = ancestor;
+    while current != descendant {
+        current = next[&current].clone();
+        path.push(current.clone());
+    }
+    Ok(Some(path))
+}
+
 #[tracing::instrument(skip(this), level=tracing::Level::DEBUG)]
 pub(crate) async fn hint_subdag_for_insertion(
     this: &(impl Parents + ?Sized),
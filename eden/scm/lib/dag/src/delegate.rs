@@ -152,6 +152,13 @@ macro_rules! delegate {
             {
                 self.$($t)*.ancestors(set)
             }
+            fn ancestors_within<'a: 's, 's>(&'a self, set: $crate::Set, depth: u64)
+                -> std::pin::Pin<Box<dyn std::future::Future<Output=
+                        $crate::Result<$crate::Set>
+                    > + Send + 's>> where Self: 's
+            {
+                self.$($t)*.ancestors_within(set, depth)
+            }
             fn first_ancestors<'a: 's, 's>(&'a self, set: $crate::Set)
                 -> std::pin::Pin<Box<dyn std::future::Future<Output=
                         $crate::Result<$crate::Set>
@@ -229,6 +236,20 @@ macro_rules! delegate {
             {
                 self.$($t)*.is_ancestor(ancestor, descendant)
             }
+            fn depth<'a: 's, 's>(&'a self, name: $crate::Vertex)
+                -> std::pin::Pin<Box<dyn std::future::Future<Output=
+                        $crate::Result<u64>
+                    > + Send + 's>> where Self: 's
+            {
+                self.$($t)*.depth(name)
+            }
+            fn shortest_path<'a: 's, 's>(&'a self, ancestor: $crate::Vertex, descendant: $crate::Vertex)
+                -> std::pin::Pin<Box<dyn std::future::Future<Output=
+                        $crate::Result<Option<Vec<$crate::Vertex>>>
+                    > + Send + 's>> where Self: 's
+            {
+                self.$($t)*.shortest_path(ancestor, descendant)
+            }
             fn heads_ancestors<'a: 's, 's>(&'a self, set: $crate::Set)
                 -> std::pin::Pin<Box<dyn std::future::Future<Output=
                         $crate::Result<$crate::Set>
@@ -0,0 +1,181 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! # discovery
+//!
+//! Find out which vertexes are shared with a remote graph, by asking the
+//! remote about a small sample of vertexes at a time.
+//!
+//! If the remote has a vertex, it also has all ancestors of the vertex. If
+//! the remote does not have a vertex, it does not have any descendant of
+//! the vertex either. So each answer decides a potentially large part of
+//! the local graph.
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::SeqCst;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+
+use crate::utils;
+use crate::DagAlgorithm;
+use crate::NameSet;
+use crate::Result;
+use crate::VertexName;
+
+/// Answers membership questions about a remote graph.
+#[async_trait::async_trait]
+pub trait RemoteMembership: Send + Sync {
+    /// Test if the remote graph has each of the `names`.
+    async fn contains(&self, names: &[VertexName]) -> Result<Vec<bool>>;
+}
+
+/// Outcome of [`discover`].
+pub struct Discovery {
+    /// Local vertexes that the remote also has.
+    pub common: NameSet,
+
+    /// Local vertexes that the remote does not have.
+    pub missing: NameSet,
+
+    /// Remote heads that are unknown locally.
+    pub unknown_remote_heads: NameSet,
+
+    /// Number of `RemoteMembership::contains` calls.
+    pub round_trips: usize,
+}
+
+/// Calculate vertexes in `dag` that are shared with, or missing from, a
+/// remote graph with the given `remote_heads`.
+///
+/// The undecided part of the graph is sampled by
+/// [`filter_known`](crate::utils::filter_known). `remote` is asked about at
+/// most `sample_size` vertexes per round trip. Larger samples are split
+/// into multiple round trips.
+pub async fn discover(
+    dag: &dyn DagAlgorithm,
+    remote_heads: NameSet,
+    remote: &dyn RemoteMembership,
+    sample_size: usize,
+) -> Result<Discovery> {
+    let sample_size = sample_size.max(1);
+    let all = dag.all().await?;
+    let known_heads = remote_heads.intersection(&all);
+    let unknown_remote_heads = remote_heads.difference(&all);
+
+    let known = dag.ancestors(known_heads).await?;
+    let undecided = all.difference(&known);
+    let round_trips = AtomicUsize::new(0);
+
+    let filter_known = |sample: &[VertexName]| -> BoxFuture<Result<Vec<VertexName>>> {
+        let sample = sample.to_vec();
+        let round_trips = &round_trips;
+        async move {
+            let mut known = Vec::new();
+            for chunk in sample.chunks(sample_size) {
+                let answers = remote.contains(chunk).await?;
+                round_trips.fetch_add(1, SeqCst);
+                for (name, has) in chunk.iter().zip(answers) {
+                    if has {
+                        known.push(name.clone());
+                    }
+                }
+            }
+            tracing::debug!(
+                target: "dag::discovery",
+                "sample: {} common, {} missing",
+                known.len(),
+                sample.len() - known.len()
+            );
+            Ok(known)
+        }
+        .boxed()
+    };
+    let common = utils::filter_known(undecided, &filter_known).await?;
+    let common = known.union(&common).flatten().await?;
+    let missing = all.difference(&common).flatten().await?;
+
+    Ok(Discovery {
+        common,
+        missing,
+        unknown_remote_heads,
+        round_trips: round_trips.load(SeqCst),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use nonblocking::non_blocking_result as r;
+
+    use super::*;
+    use crate::nameset::SyncNameSetQuery;
+    use crate::ops::IdConvert;
+    use crate::tests::TestDag;
+    use crate::NameDag;
+
+    #[async_trait::async_trait]
+    impl RemoteMembership for NameDag {
+        async fn contains(&self, names: &[VertexName]) -> Result<Vec<bool>> {
+            let mut result = Vec::with_capacity(names.len());
+            for name in names {
+                result.push(self.contains_vertex_name(name).await?);
+            }
+            Ok(result)
+        }
+    }
+
+    fn expand(set: NameSet) -> String {
+        let mut names: Vec<String> = set
+            .iter()
+            .unwrap()
+            .map(|v| String::from_utf8_lossy(v.unwrap().as_ref()).to_string())
+            .collect();
+        names.sort();
+        names.join(" ")
+    }
+
+    #[test]
+    fn test_discover() {
+        let local = TestDag::draw("A-B-C-D-E-F-G-H C-X-Y-Z E-P-Q");
+        let remote = TestDag::draw("A-B-C-D-E-F-R C-X-Y W");
+        let remote_heads = r(remote.dag.heads(r(remote.dag.all()).unwrap())).unwrap();
+        for sample_size in [1, 2, 100] {
+            let d = r(discover(
+                &local.dag,
+                remote_heads.clone(),
+                &remote.dag,
+                sample_size,
+            ))
+            .unwrap();
+            assert_eq!(expand(d.common), "A B C D E F X Y");
+            assert_eq!(expand(d.missing), "G H P Q Z");
+            assert_eq!(expand(d.unknown_remote_heads), "R W");
+        }
+
+        // Remote heads are enough to decide everything.
+        let remote_heads = NameSet::from_static_names(vec!["H".into(), "Z".into(), "Q".into()]);
+        let d = r(discover(&local.dag, remote_heads, &remote.dag, 1)).unwrap();
+        assert_eq!(d.round_trips, 0);
+        assert_eq!(expand(d.missing), "");
+    }
+
+    #[test]
+    fn test_discover_linear() {
+        let chain = |n: usize| -> String {
+            let names: Vec<String> = (0..n).map(|i| format!("N{}", i)).collect();
+            names.join("-")
+        };
+        let local = TestDag::draw(&chain(100));
+        let remote = TestDag::draw(&format!("{}-R", chain(60)));
+        let remote_heads = r(remote.dag.heads(r(remote.dag.all()).unwrap())).unwrap();
+        let d = r(discover(&local.dag, remote_heads, &remote.dag, 3)).unwrap();
+        assert_eq!(d.common.count().unwrap(), 60);
+        assert_eq!(d.missing.count().unwrap(), 40);
+        // Bisecting the linear history takes a logarithmic number of rounds.
+        assert!(d.round_trips <= 12, "round trips: {}", d.round_trips);
+    }
+}
@@ -17,8 +17,15 @@ use crate::VertexName;
 #[derive(Debug, Error)]
 pub enum DagError {
     /// A vertex name cannot be found.
-    #[error("{0:?} cannot be found")]
-    VertexNotFound(VertexName),
+    ///
+    /// `suggestions` are existing vertexes with similar names. They are
+    /// only filled by
+    /// [`PrefixLookup::vertex_not_found_error`](crate::ops::PrefixLookup::vertex_not_found_error).
+    #[error("{name:?} cannot be found{}", fmt_suggestions(.suggestions))]
+    VertexNotFound {
+        name: VertexName,
+        suggestions: Vec<VertexName>,
+    },
 
     /// An Id cannot be found.
     #[error("{0:?} cannot be found")]
@@ -44,6 +51,16 @@ pub enum DagError {
     /// No space for new Ids.
     #[error("out of space for group {0:?}")]
     IdOverflow(Group),
+
+    /// A query expression is malformed, or calls a function incorrectly.
+    /// See [`query`](crate::query).
+    #[error("invalid query: {0}")]
+    InvalidQuery(String),
+
+    /// The local master group is not a prefix of the master group of
+    /// another graph, and cannot be fast-forwarded to it.
+    #[error("master diverged: {0}")]
+    MasterDiverged(String),
 }
 
 #[derive(Debug, Error)]
@@ -83,6 +100,14 @@ impl From<io::Error> for DagError {
     }
 }
 
+fn fmt_suggestions(suggestions: &[VertexName]) -> String {
+    if suggestions.is_empty() {
+        String::new()
+    } else {
+        format!(" (did you mean {:?}?)", suggestions)
+    }
+}
+
 /// Quick way to return a `BackendError::Generic` error.
 pub fn bug<T>(message: impl ToString) -> crate::Result<T> {
     Err(DagError::Bug(message.to_string()))
@@ -111,6 +136,9 @@ impl NotFoundError for Id {
 impl NotFoundError for VertexName {
     fn not_found_error(&self) -> DagError {
         ::fail::fail_point!("dag-not-found-vertex");
-        DagError::VertexNotFound(self.clone())
+        DagError::VertexNotFound {
+            name: self.clone(),
+            suggestions: Vec::new(),
+        }
     }
 }
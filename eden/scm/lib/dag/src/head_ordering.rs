@@ -0,0 +1,131 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! # head_ordering
+//!
+//! Policies deciding the order of heads to assign ids in
+//! [`DagPersistent::add_heads_and_flush`](crate::ops::DagPersistent::add_heads_and_flush).
+//!
+//! Heads are assigned one by one. A head and its ancestors that do not have
+//! ids yet get a contiguous id range. So the order of heads affects how
+//! fragmented the ancestor sets are.
+
+use std::collections::HashSet;
+
+use crate::ops::IdConvert;
+use crate::ops::Parents;
+use crate::Result;
+use crate::VertexListWithOptions;
+use crate::VertexName;
+
+/// Decides the order of heads to assign ids.
+#[async_trait::async_trait]
+pub trait HeadOrdering: Send + Sync {
+    /// Reorder `heads` before inserting them.
+    ///
+    /// `map` is the `IdMap` before the insertion. `parents` is the same as
+    /// the one passed to `add_heads_and_flush`. The returned list should
+    /// contain the same vertexes and options as `heads`.
+    async fn order_heads(
+        &self,
+        map: &dyn IdConvert,
+        parents: &dyn Parents,
+        heads: &VertexListWithOptions,
+    ) -> Result<VertexListWithOptions>;
+}
+
+/// Keep the order specified by the caller. This is the default.
+#[async_trait::async_trait]
+impl HeadOrdering for () {
+    async fn order_heads(
+        &self,
+        _map: &dyn IdConvert,
+        _parents: &dyn Parents,
+        heads: &VertexListWithOptions,
+    ) -> Result<VertexListWithOptions> {
+        Ok(heads.clone())
+    }
+}
+
+/// Assign heads with more new ancestors first.
+///
+/// The longest branch gets a contiguous id range. Shorter branches, which
+/// are often merged into the longest branch, are assigned later so they do
+/// not split the id range of the longest branch.
+///
+/// Only heads with the same `highest_group` next to each other are
+/// reordered, so heads in the `MASTER` group are still assigned before
+/// heads in the `NON_MASTER` group if the caller specified so.
+///
+/// Counting new ancestors visits them via `parents` once per head. This
+/// can be slow if there are many heads sharing many new ancestors.
+pub struct LongestBranchFirst;
+
+#[async_trait::async_trait]
+impl HeadOrdering for LongestBranchFirst {
+    async fn order_heads(
+        &self,
+        map: &dyn IdConvert,
+        parents: &dyn Parents,
+        heads: &VertexListWithOptions,
+    ) -> Result<VertexListWithOptions> {
+        let mut list = heads.vertex_options();
+        if list.len() <= 1 {
+            return Ok(heads.clone());
+        }
+        let mut counts = Vec::with_capacity(list.len());
+        for (head, _) in &list {
+            counts.push(count_new_ancestors(map, parents, head).await?);
+        }
+
+        let mut start = 0;
+        while start < list.len() {
+            let group = list[start].1.highest_group;
+            let end = start
+                + list[start..]
+                    .iter()
+                    .take_while(|(_, opts)| opts.highest_group == group)
+                    .count();
+            // Stable sort. Heads with the same count keep their order.
+            let mut run: Vec<_> = counts[start..end]
+                .iter()
+                .copied()
+                .zip(list[start..end].iter().cloned())
+                .collect();
+            run.sort_by_key(|(count, _)| std::cmp::Reverse(*count));
+            for (i, (_, item)) in run.into_iter().enumerate() {
+                list[start + i] = item;
+            }
+            start = end;
+        }
+
+        tracing::debug!(target: "dag::headordering", "ordered heads: {:?}", &list);
+        Ok(list.into())
+    }
+}
+
+/// Count `head` and its ancestors that are not in `map`.
+async fn count_new_ancestors(
+    map: &dyn IdConvert,
+    parents: &dyn Parents,
+    head: &VertexName,
+) -> Result<usize> {
+    let mut visited = HashSet::new();
+    let mut to_visit = vec![head.clone()];
+    let mut count = 0;
+    while let Some(name) = to_visit.pop() {
+        if !visited.insert(name.clone()) {
+            continue;
+        }
+        if map.contains_vertex_name_locally(&[name.clone()]).await?[0] {
+            continue;
+        }
+        count += 1;
+        to_visit.extend(parents.parent_names(name).await?);
+    }
+    Ok(count)
+}
@@ -17,6 +17,8 @@ use std::ops::Deref;
 use std::path::Path;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
 
 use indexmap::set::IndexSet;
 use serde::Deserialize;
@@ -70,8 +72,13 @@ pub struct IdDag<Store> {
     pub(crate) store: Store,
     #[serde(skip, default = "default_seg_size")]
     new_seg_size: usize,
+    #[serde(skip)]
+    defer_high_level_segments: bool,
     #[serde(skip, default = "VerLink::new")]
     version: VerLink,
+    /// Depths of the lows of flat segments, calculated for a version.
+    #[serde(skip)]
+    depth_cache: Arc<Mutex<Option<(VerLink, HashMap<Id, u64>)>>>,
 }
 
 /// See benches/segment_sizes.rs (D16660078) for this choice.
@@ -89,6 +96,15 @@ impl IdDag<IndexedLogStore> {
         let store = IndexedLogStore::open(path)?;
         Self::open_from_store(store)
     }
+
+    /// Open [`IdDag`] at the given directory in read-only mode.
+    ///
+    /// Only the shared reader lock is taken and no index files are written.
+    /// The directory must exist. See [`IndexedLogStore::open_read_only`].
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<Self> {
+        let store = IndexedLogStore::open_read_only(path)?;
+        Self::open_from_store(store)
+    }
 }
 
 impl<S> IdDag<S> {
@@ -106,6 +122,20 @@ impl<S> IdDag<S> {
     pub(crate) fn get_new_segment_size(&self) -> usize {
         self.new_seg_size
     }
+
+    /// Skip building high-level segments when inserting flat segments.
+    ///
+    /// This makes inserting faster. Queries are still correct, but might be
+    /// slower until the missing high-level segments are built by
+    /// `build_missing_high_level_segments`.
+    pub fn set_defer_high_level_segments(&mut self, defer: bool) {
+        self.defer_high_level_segments = defer;
+    }
+
+    /// Whether building high-level segments is skipped.
+    pub(crate) fn get_defer_high_level_segments(&self) -> bool {
+        self.defer_high_level_segments
+    }
 }
 
 #[cfg(any(test, feature = "indexedlog-backend"))]
@@ -116,7 +146,9 @@ impl TryClone for IdDag<IndexedLogStore> {
         Ok(Self {
             store,
             new_seg_size: self.new_seg_size,
+            defer_high_level_segments: self.defer_high_level_segments,
             version: self.version.clone(),
+            depth_cache: self.depth_cache.clone(),
         })
     }
 }
@@ -129,7 +161,9 @@ impl IdDag<InProcessStore> {
         Self {
             store,
             new_seg_size: default_seg_size(),
+            defer_high_level_segments: false,
             version: VerLink::new(),
+            depth_cache: Default::default(),
         }
     }
 }
@@ -139,7 +173,9 @@ impl<Store: IdDagStore> IdDag<Store> {
         let dag = Self {
             store,
             new_seg_size: default_seg_size(),
+            defer_high_level_segments: false,
             version: VerLink::new(),
+            depth_cache: Default::default(),
         };
         Ok(dag)
     }
@@ -168,9 +204,41 @@ impl<Store: IdDagStore> IdDag<Store> {
         Ok(self.all()?.contains(id))
     }
 
+    /// Count segments at each level. The `n`-th item is the number of
+    /// segments at level `n`.
+    pub fn segment_counts(&self) -> Result<Vec<usize>> {
+        let max_level = self.store.max_level()?;
+        let mut counts = Vec::with_capacity(max_level as usize + 1);
+        for level in 0..=max_level {
+            let mut count = 0;
+            for seg in self.store.iter_segments_descending(Id::MAX, level)? {
+                seg?;
+                count += 1;
+            }
+            counts.push(count);
+        }
+        Ok(counts)
+    }
+
     pub(crate) fn version(&self) -> &VerLink {
         &self.version
     }
+
+    /// Calculate the depth of `id`. Same as [`IdDagAlgorithm::depth`], but
+    /// depths of flat segments are cached, and reused by later calls until
+    /// a non-append-only change like `strip`.
+    pub fn depth(&self, id: Id) -> Result<u64> {
+        let mut cache = self.depth_cache.lock().unwrap();
+        let low_depths = match &mut *cache {
+            // Depths do not change with append-only changes.
+            Some((version, low_depths)) if *version <= self.version => {
+                *version = self.version.clone();
+                low_depths
+            }
+            cache => &mut cache.insert((self.version.clone(), HashMap::new())).1,
+        };
+        calculate_depth(&self.store, id, low_depths)
+    }
 }
 
 // Build segments.
@@ -222,10 +290,22 @@ impl<Store: IdDagStore> IdDag<Store> {
         outcome: &PreparedFlatSegments,
     ) -> Result<usize> {
         let (count, set) = self.build_flat_segments_from_prepared_flat_segments(outcome)?;
+        if self.defer_high_level_segments {
+            return Ok(count);
+        }
         let count = count + self.build_all_high_level_segments(Level::MAX, set)?;
         Ok(count)
     }
 
+    /// Build high-level segments that are missing because of
+    /// `set_defer_high_level_segments`.
+    ///
+    /// Return number of segments inserted.
+    pub fn build_missing_high_level_segments(&mut self) -> Result<usize> {
+        let flat_id_set = self.all_ids_in_segment_level(0)?;
+        self.build_all_high_level_segments(Level::MAX, flat_id_set)
+    }
+
     /// Build flat segments using the outcome from `add_head`.
     /// This is not public because it does not keep high-level segments in sync.
     ///
@@ -655,6 +735,37 @@ pub trait IdDagAlgorithm: IdDagStore {
         Ok(result)
     }
 
+    /// Calculate ancestors that are at most `depth` edges away from `set`.
+    ///
+    /// Linear parts of flat segments are handled in one step, instead of
+    /// visiting ids one by one.
+    fn ancestors_within(&self, set: IdSet, depth: u64) -> Result<IdSet> {
+        let mut result = IdSet::empty();
+        // Largest remaining depth an id was visited with.
+        let mut visited: HashMap<Id, u64> = HashMap::new();
+        let mut to_visit: BinaryHeap<(Id, u64)> = set.iter_desc().map(|id| (id, depth)).collect();
+        while let Some((id, remaining)) = to_visit.pop() {
+            if visited.get(&id).map_or(false, |&r| r >= remaining) {
+                continue;
+            }
+            visited.insert(id, remaining);
+            let seg = self
+                .find_flat_segment_including_id(id)?
+                .ok_or_else(|| id.not_found_error())?;
+            let low = seg.span()?.low;
+            let distance_to_low = id.0 - low.0;
+            if distance_to_low >= remaining {
+                result.push(Id(id.0 - remaining)..=id);
+            } else {
+                result.push(low..=id);
+                for parent in seg.parents()? {
+                    to_visit.push((parent, remaining - distance_to_low - 1));
+                }
+            }
+        }
+        Ok(result)
+    }
+
     /// Like `ancestors` but follows only the first parents.
     fn first_ancestors(&self, set: IdSet) -> Result<IdSet> {
         fn trace(msg: &dyn Fn() -> String) {
@@ -827,6 +938,104 @@ pub trait IdDagAlgorithm: IdDagStore {
         }
     }
 
+    /// Calculate the depth of `id`. That is, the number of edges in the
+    /// longest path from a root to `id`. Roots have depth 0.
+    ///
+    /// This is O(flat segments in `ancestors(id)`).
+    fn depth(&self, id: Id) -> Result<u64> {
+        calculate_depth(self, id, &mut HashMap::new())
+    }
+
+    /// Count `ancestors(id)`, including `id`.
+    ///
+    /// This avoids calculating the ancestors if `id` is in a flat segment
+    /// with the `ONLY_HEAD` flag.
+    fn count_ancestors(&self, id: Id) -> Result<u64> {
+        let flat_seg = self
+            .find_flat_segment_including_id(id)?
+            .ok_or_else(|| id.not_found_error())?;
+        if flat_seg.only_head()? {
+            // ancestors(id) is Id::MIN..=id.
+            return Ok(id.0 - Id::MIN.0 + 1);
+        }
+        Ok(self.ancestors(id.into())?.count())
+    }
+
+    /// Find a shortest path from `ancestor` to `descendant` following
+    /// parent-child edges. The path starts with `ancestor` and ends with
+    /// `descendant`. Return `None` if `ancestor` is not an ancestor of
+    /// `descendant`.
+    ///
+    /// The search is limited to `ancestor::descendant` and walks linear
+    /// parts of flat segments in one step. So it is roughly
+    /// O(flat segments in `ancestor::descendant`).
+    fn shortest_path(&self, ancestor: Id, descendant: Id) -> Result<Option<Vec<Id>>> {
+        if ancestor > descendant {
+            return Ok(None);
+        }
+        let range = self.range(ancestor.into(), descendant.into())?;
+        if !range.contains(descendant) {
+            return Ok(None);
+        }
+
+        // Dijkstra from `descendant`. `next[x]` is `(y, low)`: the path
+        // continues from `x` to `y` via the linear `low..=y` part of a
+        // flat segment.
+        let mut distances: HashMap<Id, u64> = HashMap::new();
+        let mut next: HashMap<Id, (Id, Id)> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        distances.insert(descendant, 0);
+        heap.push(std::cmp::Reverse((0u64, descendant)));
+        while let Some(std::cmp::Reverse((distance, id))) = heap.pop() {
+            if id == ancestor {
+                break;
+            }
+            if distances.get(&id) != Some(&distance) {
+                continue;
+            }
+            let seg = self
+                .find_flat_segment_including_id(id)?
+                .ok_or_else(|| id.not_found_error())?;
+            let low = seg.span()?.low;
+            let mut relax = |target: Id, target_distance: u64| {
+                if distances
+                    .get(&target)
+                    .map_or(true, |&d| d > target_distance)
+                {
+                    distances.insert(target, target_distance);
+                    next.insert(target, (id, low));
+                    heap.push(std::cmp::Reverse((target_distance, target)));
+                }
+            };
+            if ancestor >= low {
+                relax(ancestor, distance + id.0 - ancestor.0);
+            } else {
+                for parent in seg.parents()? {
+                    if range.contains(parent) {
+                        relax(parent, distance + id.0 - low.0 + 1);
+                    }
+                }
+            }
+        }
+
+        let mut path = vec![ancestor];
+        let mut current = ancestor;
+        while current != descendant {
+            let (child, low) = match next.get(&current) {
+                Some(&v) => v,
+                None => return bug(format!("shortest_path: {:?} is not visited", current)),
+            };
+            let start = if current >= low { current + 1 } else { low };
+            let mut id = start;
+            while id <= child {
+                path.push(id);
+                id = id + 1;
+            }
+            current = child;
+        }
+        Ok(Some(path))
+    }
+
     /// Calculate the n-th first ancestor. If `n` is 0, return `id` unchanged.
     /// If `n` is 1, return the first parent of `id`.
     fn first_ancestor_nth(&self, id: Id, n: u64) -> Result<Id> {
@@ -1309,9 +1518,53 @@ pub trait IdDagAlgorithm: IdDagStore {
     }
 
     /// Test if `ancestor_id` is an ancestor of `descendant_id`.
+    ///
+    /// Unlike `ancestors`, this does not calculate the full ancestor set.
+    /// Segments entirely below `ancestor_id` are not visited.
     fn is_ancestor(&self, ancestor_id: Id, descendant_id: Id) -> Result<bool> {
-        let set = self.ancestors(descendant_id.into())?;
-        Ok(set.contains(ancestor_id))
+        // Parents always have smaller ids than their children.
+        if ancestor_id > descendant_id {
+            return Ok(false);
+        }
+        let mut visited = IdSet::empty();
+        let mut to_visit: BinaryHeap<Id> = BinaryHeap::new();
+        to_visit.push(descendant_id);
+        let max_level = self.max_level()?;
+        'outer: while let Some(id) = to_visit.pop() {
+            if id < ancestor_id || visited.contains(id) {
+                continue;
+            }
+            for level in (1..=max_level).rev() {
+                let seg = self.find_segment_by_head_and_level(id, level)?;
+                if let Some(seg) = seg {
+                    let span = seg.span()?;
+                    if span.low <= ancestor_id {
+                        return Ok(true);
+                    }
+                    visited.push_span(span);
+                    for parent in seg.parents()? {
+                        to_visit.push(parent);
+                    }
+                    continue 'outer;
+                }
+            }
+            match self.find_flat_segment_including_id(id)? {
+                Some(seg) => {
+                    let low = seg.span()?.low;
+                    if low <= ancestor_id {
+                        return Ok(true);
+                    }
+                    visited.push_span((low..=id).into());
+                    for parent in seg.parents()? {
+                        to_visit.push(parent);
+                    }
+                }
+                None => {
+                    return bug("flat segments are expected to cover everything but they are not");
+                }
+            }
+        }
+        Ok(false)
     }
 
     /// Calculate "heads" of the ancestors of the given [`IdSet`]. That is,
@@ -1376,6 +1629,24 @@ pub trait IdDagAlgorithm: IdDagStore {
         Ok(result)
     }
 
+    /// Calculate `ancestors(reachable) - ancestors(unreachable)`.
+    fn only(&self, reachable: IdSet, unreachable: IdSet) -> Result<IdSet> {
+        let reachable = self.ancestors(reachable)?;
+        if unreachable.is_empty() {
+            return Ok(reachable);
+        }
+        let unreachable = self.ancestors(unreachable)?;
+        Ok(reachable.difference(&unreachable))
+    }
+
+    /// Calculate `ancestors(reachable) - ancestors(unreachable)`, and
+    /// `ancestors(unreachable)`.
+    fn only_both(&self, reachable: IdSet, unreachable: IdSet) -> Result<(IdSet, IdSet)> {
+        let reachable = self.ancestors(reachable)?;
+        let unreachable = self.ancestors(unreachable)?;
+        Ok((reachable.difference(&unreachable), unreachable))
+    }
+
     /// Calculate the descendants of the given set.
     ///
     /// Logically equivalent to `range(set, all())`.
@@ -1666,6 +1937,46 @@ pub trait IdDagAlgorithm: IdDagStore {
 
 impl<S: IdDagStore> IdDagAlgorithm for S {}
 
+/// Calculate the depth of `id`. `low_depths` has depths of the lows of flat
+/// segments. It is used to skip known segments, and updated with new ones.
+fn calculate_depth(
+    dag: &(impl IdDagAlgorithm + ?Sized),
+    id: Id,
+    low_depths: &mut HashMap<Id, u64>,
+) -> Result<u64> {
+    let find_low = |id: Id| -> Result<(Id, Vec<Id>)> {
+        let seg = dag
+            .find_flat_segment_including_id(id)?
+            .ok_or_else(|| id.not_found_error())?;
+        Ok((seg.span()?.low, seg.parents()?))
+    };
+    let mut to_visit = vec![id];
+    while let Some(&current) = to_visit.last() {
+        let (low, parents) = find_low(current)?;
+        if low_depths.contains_key(&low) {
+            to_visit.pop();
+            continue;
+        }
+        let mut depth = 0;
+        let mut missing = Vec::new();
+        for parent in parents {
+            let (parent_low, _) = find_low(parent)?;
+            match low_depths.get(&parent_low) {
+                Some(&d) => depth = depth.max(d + parent.0 - parent_low.0 + 1),
+                None => missing.push(parent),
+            }
+        }
+        if missing.is_empty() {
+            low_depths.insert(low, depth);
+            to_visit.pop();
+        } else {
+            to_visit.extend(missing);
+        }
+    }
+    let (low, _) = find_low(id)?;
+    Ok(low_depths[&low] + id.0 - low.0)
+}
+
 impl<Store: IdDagStore> Deref for IdDag<Store> {
     type Target = dyn IdDagAlgorithm;
 
@@ -1859,6 +2170,59 @@ impl<Store: IdDagStore> IdDag<Store> {
 
         Ok(set)
     }
+
+    /// Count segments per level, and vertexes covered by segments.
+    pub fn segment_stats(&self) -> Result<SegmentStats> {
+        Ok(SegmentStats {
+            segments_per_level: self.segment_counts()?,
+            vertex_count: self.all()?.count(),
+        })
+    }
+
+    /// Rewrite all segments. Adjacent linear flat segments are merged, and
+    /// high-level segments are rebuilt from the merged flat segments.
+    ///
+    /// This does not change the graph or the `Id`s. Returns `false` without
+    /// writing anything if there are no flat segments to merge.
+    pub(crate) fn rebuild_segments(&mut self) -> Result<bool> {
+        let mut merged = PreparedFlatSegments::default();
+        let mut last: Option<FlatSegment> = None;
+        let mut flat_count = 0;
+        for group in Group::ALL {
+            for seg in self.flat_segments(group)?.segments {
+                flat_count += 1;
+                match last.as_mut() {
+                    Some(last_seg)
+                        if last_seg.high + 1 == seg.low
+                            && seg.parents == [last_seg.high]
+                            && last_seg.high.group() == seg.low.group() =>
+                    {
+                        last_seg.high = seg.high;
+                    }
+                    _ => merged.segments.extend(last.replace(seg)),
+                }
+            }
+        }
+        merged.segments.extend(last);
+        if merged.segments.len() == flat_count {
+            return Ok(false);
+        }
+
+        let all = self.all()?;
+        self.strip(all)?;
+        self.build_segments_from_prepared_flat_segments(&merged)?;
+        Ok(true)
+    }
+}
+
+/// Segment counts reported by [`IdDag::segment_stats`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SegmentStats {
+    /// Number of segments, indexed by level.
+    pub segments_per_level: Vec<usize>,
+
+    /// Number of vertexes covered by segments.
+    pub vertex_count: u64,
 }
 
 impl<Store: Persist> Persist for IdDag<Store> {
@@ -2254,6 +2618,89 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_depth_cache() {
+        let flat_segments = |segments: &[(u64, u64, &[u64])]| {
+            let mut prepared = PreparedFlatSegments::default();
+            for &(low, high, parents) in segments {
+                prepared.segments.insert(FlatSegment {
+                    low: Id(low),
+                    high: Id(high),
+                    parents: parents.iter().map(|&p| Id(p)).collect(),
+                });
+            }
+            prepared
+        };
+        let mut iddag = IdDag::new_in_process();
+        iddag
+            .build_segments_from_prepared_flat_segments(&flat_segments(&[
+                (0, 100, &[]),
+                (101, 200, &[50]),
+            ]))
+            .unwrap();
+        assert_eq!(iddag.depth(Id(200)).unwrap(), 150);
+        let cached = |iddag: &IdDag<InProcessStore>| {
+            let cache = iddag.depth_cache.lock().unwrap();
+            cache.as_ref().map_or(0, |(_, low_depths)| low_depths.len())
+        };
+        assert_eq!(cached(&iddag), 2);
+
+        // Append-only changes keep the cache.
+        iddag
+            .build_segments_from_prepared_flat_segments(&flat_segments(&[(201, 300, &[150])]))
+            .unwrap();
+        assert_eq!(iddag.depth(Id(300)).unwrap(), 200);
+        assert_eq!(cached(&iddag), 3);
+
+        // Strip invalidates the cache.
+        iddag.strip(Id(101).into()).unwrap();
+        iddag
+            .build_segments_from_prepared_flat_segments(&flat_segments(&[(101, 200, &[90])]))
+            .unwrap();
+        assert_eq!(iddag.depth(Id(200)).unwrap(), 190);
+        assert_eq!(cached(&iddag), 2);
+    }
+
+    #[test]
+    fn test_rebuild_segments() {
+        // Flat segments in the non-master group are not merged on insertion.
+        let n = Group::NON_MASTER.min_id();
+        let mut prepared = PreparedFlatSegments::default();
+        for (low, high, parents) in [
+            (n, n + 9, vec![]),
+            (n + 10, n + 19, vec![n + 9]),
+            (n + 20, n + 29, vec![n + 19]),
+            (n + 30, n + 39, vec![n + 5]),
+        ] {
+            prepared.segments.insert(FlatSegment { low, high, parents });
+        }
+        let mut iddag = IdDag::new_in_process();
+        iddag
+            .build_segments_from_prepared_flat_segments(&prepared)
+            .unwrap();
+        let before = iddag.segment_stats().unwrap();
+        assert_eq!(before.segments_per_level[0], 4);
+        assert_eq!(before.vertex_count, 40);
+        let ancestors = iddag.ancestors((n + 39).into()).unwrap();
+
+        assert!(iddag.rebuild_segments().unwrap());
+        let after = iddag.segment_stats().unwrap();
+        assert_eq!(after.segments_per_level[0], 2);
+        assert_eq!(after.vertex_count, 40);
+        assert_eq!(iddag.parent_ids(n + 20).unwrap(), [n + 19]);
+        assert_eq!(iddag.parent_ids(n + 30).unwrap(), [n + 5]);
+        assert_eq!(
+            iddag.ancestors((n + 39).into()).unwrap().as_spans(),
+            ancestors.as_spans()
+        );
+
+        // Nothing to merge. Segments are not rewritten.
+        let version = iddag.version().clone();
+        assert!(!iddag.rebuild_segments().unwrap());
+        assert_eq!(iddag.version(), &version);
+        assert_eq!(iddag.segment_stats().unwrap(), after);
+    }
+
     #[test]
     fn test_id_set_to_id_segments() {
         let mut iddag = IdDag::new_in_process();
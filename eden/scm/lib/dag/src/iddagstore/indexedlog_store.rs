@@ -687,6 +687,21 @@ impl IndexedLogStore {
         Ok(iddag)
     }
 
+    /// Open the store holding only the shared reader lock, without writing
+    /// index files. Writes fail at sync time.
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let log = Self::log_open_options()
+            .read_only(true)
+            .open(path.clone())?;
+        let iddag = Self {
+            log,
+            path,
+            cached_max_level: AtomicU8::new(MAX_LEVEL_UNKNOWN),
+        };
+        Ok(iddag)
+    }
+
     pub fn open_from_clean_log(log: log::Log) -> Result<Self> {
         let path = log.path().as_opt_path().unwrap().to_path_buf();
         if log.iter_dirty().next().is_some() {
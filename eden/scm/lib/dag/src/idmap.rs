@@ -21,14 +21,21 @@ use crate::segment::PreparedFlatSegments;
 use crate::types_ext::PreparedFlatSegmentsExt;
 use crate::Error;
 use crate::IdSet;
+use crate::IdSpan;
 use crate::Result;
 
+#[cfg(any(test, feature = "indexedlog-backend"))]
+mod cache;
 #[cfg(any(test, feature = "indexedlog-backend"))]
 mod indexedlog_idmap;
 mod mem_idmap;
 
 #[cfg(any(test, feature = "indexedlog-backend"))]
 pub use indexedlog_idmap::IdMap;
+#[cfg(any(test, feature = "indexedlog-backend"))]
+pub use indexedlog_idmap::IdMapProblem;
+#[cfg(any(test, feature = "indexedlog-backend"))]
+pub use indexedlog_idmap::IdMapVerifyReport;
 pub(crate) use mem_idmap::CoreMemIdMap;
 pub use mem_idmap::MemIdMap;
 
@@ -409,15 +416,52 @@ pub trait IdMapWrite {
     async fn remove_range(&mut self, low: Id, high: Id) -> Result<Vec<VertexName>>;
 }
 
+/// Iterate through `(Id, VertexName)` entries of an IdMap.
+pub trait IdMapEntries {
+    /// Iterate through entries with ids in `span`, in ascending id order.
+    fn entries_in_range(
+        &self,
+        span: IdSpan,
+    ) -> Result<Box<dyn Iterator<Item = Result<(Id, VertexName)>> + '_>>;
+
+    /// Iterate through all entries, in ascending id order.
+    fn entries(&self) -> Result<Box<dyn Iterator<Item = Result<(Id, VertexName)>> + '_>> {
+        self.entries_in_range(IdSpan::from(Id::MIN..=Id::MAX))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use nonblocking::non_blocking_result as r;
     use tempfile::tempdir;
 
     use super::*;
+    use crate::nameset::tests::nb;
     use crate::ops::Persist;
     use crate::ops::PrefixLookup;
 
+    #[cfg(all(test, feature = "indexedlog-backend"))]
+    #[test]
+    fn test_read_only() {
+        let dir = tempdir().unwrap();
+        let mut map = IdMap::open(dir.path()).unwrap();
+        let lock = map.lock().unwrap();
+        map.reload(&lock).unwrap();
+        map.insert(Id(1), b"abc").unwrap();
+        map.persist(&lock).unwrap();
+        drop(lock);
+
+        let mut map = IdMap::open_read_only(dir.path()).unwrap();
+        assert_eq!(map.find_id_by_name(b"abc").unwrap(), Some(Id(1)));
+        assert_eq!(map.find_name_by_id(Id(1)).unwrap().unwrap(), b"abc");
+        let lock = map.lock().unwrap();
+        map.reload(&lock).unwrap();
+        map.insert(Id(2), b"def").unwrap();
+        map.persist(&lock).unwrap_err();
+
+        assert!(IdMap::open_read_only(dir.path().join("missing")).is_err());
+    }
+
     #[cfg(all(test, feature = "indexedlog-backend"))]
     #[test]
     fn test_basic_operations() {
@@ -456,6 +500,30 @@ mod tests {
         );
         assert!(r(map.vertexes_by_hex_prefix(b"6b", 1)).unwrap().is_empty());
 
+        // Test suggestions.
+        assert_eq!(
+            r(map.similar_vertexes(&VertexName::from(&b"jkx"[..]), 3)).unwrap(),
+            [
+                VertexName::from(&b"jkl"[..]),
+                VertexName::from(&b"jkl2"[..])
+            ]
+        );
+        assert_eq!(
+            r(map.similar_vertexes(&VertexName::from(&b"gh"[..]), 3)).unwrap(),
+            [VertexName::from(&b"ghi"[..])]
+        );
+        assert!(r(map.similar_vertexes(&VertexName::from(&b"z"[..]), 3))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            nb(map.vertex_not_found_error(&VertexName::from(&b"gh"[..]), 3)).to_string(),
+            "gh cannot be found (did you mean [ghi]?)"
+        );
+        assert!(matches!(
+            nb(map.vertex_not_found_error(&VertexName::from(&b"z"[..]), 3)),
+            crate::Error::VertexNotFound { .. }
+        ));
+
         for _ in 0..=1 {
             assert_eq!(map.find_name_by_id(Id(1)).unwrap().unwrap(), b"abc");
             assert_eq!(map.find_name_by_id(Id(2)).unwrap().unwrap(), b"def");
@@ -473,6 +541,12 @@ mod tests {
             assert!(map.find_id_by_name(b"jkl3").unwrap().is_none());
         }
 
+        let report = map.verify().unwrap();
+        assert!(report.problems.is_empty());
+        assert_eq!(report.gaps.len(), 1);
+        assert_eq!(report.gaps[0].0, Group::MASTER);
+        assert_eq!(format!("{:?}", &report.gaps[0].1), "0 3..=9");
+
         // Test Debug
         assert_eq!(
             format!("{:?}", &map),
@@ -501,7 +575,7 @@ mod tests {
         }
     }
 
-    fn check_remove_range(mut map: impl IdConvert + IdMapWrite) {
+    fn check_remove_range(mut map: impl IdConvert + IdMapWrite + IdMapEntries) {
         let items: &[(Id, &[u8])] = &[
             (Id(0), b"z"),
             (Id(1), b"a"),
@@ -577,6 +651,28 @@ mod tests {
         let removed = r(map.remove_range(nid(20), nid(10000))).unwrap();
         assert_eq!(f(removed), "[n20]");
         assert_eq!(deleted(&map), "[1, 2, 3, 9, 11, N2, N3, N4, N20]");
+
+        let entries = |span: IdSpan| -> String {
+            let entries: Vec<(Id, VertexName)> = map
+                .entries_in_range(span)
+                .unwrap()
+                .collect::<Result<_>>()
+                .unwrap();
+            format!("{:?}", entries)
+        };
+        assert_eq!(
+            entries(IdSpan::from(Id(3)..=Id(13))),
+            "[(4, cc), (5, ccc), (13, ff)]"
+        );
+        assert_eq!(
+            entries(IdSpan::from(nid(1)..=nid(5))),
+            "[(N1, n1), (N5, n5)]"
+        );
+        let all: Vec<(Id, VertexName)> = map.entries().unwrap().collect::<Result<_>>().unwrap();
+        assert_eq!(
+            format!("{:?}", all),
+            "[(0, z), (4, cc), (5, ccc), (13, ff), (N0, n), (N1, n1), (N5, n5), (N12, n12)]"
+        );
     }
 
     fn nid(i: u64) -> Id {
@@ -0,0 +1,142 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::mem;
+
+use minibytes::Bytes;
+
+use crate::Id;
+use crate::VertexName;
+
+/// Bounded in-memory cache for `Id` <-> `VertexName` lookups.
+///
+/// Approximates LRU using two generations. Entries are inserted into, or
+/// promoted to the new generation. When the new generation is full, it
+/// becomes the old generation, and the previous old generation is dropped.
+/// So recently used entries survive, and at most `capacity` entries are
+/// kept.
+///
+/// Only positive lookup results are cached. Callers need to `clear` the
+/// cache if entries are removed.
+pub(crate) struct IdNameCache {
+    capacity: usize,
+    new: Generation,
+    old: Generation,
+}
+
+#[derive(Default)]
+struct Generation {
+    id2name: HashMap<Id, VertexName>,
+    name2id: HashMap<Bytes, Id>,
+}
+
+impl Generation {
+    fn len(&self) -> usize {
+        self.id2name.len()
+    }
+
+    fn remove_id(&mut self, id: Id) -> Option<VertexName> {
+        let name = self.id2name.remove(&id)?;
+        self.name2id.remove(name.as_ref());
+        Some(name)
+    }
+
+    fn remove_name(&mut self, name: &[u8]) -> Option<(Id, VertexName)> {
+        let id = self.name2id.remove(name)?;
+        let name = self.id2name.remove(&id)?;
+        Some((id, name))
+    }
+}
+
+impl IdNameCache {
+    /// Create a cache that keeps at most `capacity` entries. A `capacity`
+    /// less than 2 disables the cache.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            new: Default::default(),
+            old: Default::default(),
+        }
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub(crate) fn lookup_name(&mut self, id: Id) -> Option<VertexName> {
+        if let Some(name) = self.new.id2name.get(&id) {
+            return Some(name.clone());
+        }
+        let name = self.old.remove_id(id)?;
+        self.insert(id, name.clone());
+        Some(name)
+    }
+
+    pub(crate) fn lookup_id(&mut self, name: &[u8]) -> Option<Id> {
+        if let Some(&id) = self.new.name2id.get(name) {
+            return Some(id);
+        }
+        let (id, name) = self.old.remove_name(name)?;
+        self.insert(id, name);
+        Some(id)
+    }
+
+    pub(crate) fn insert(&mut self, id: Id, name: VertexName) {
+        // Each generation holds up to half of the entries.
+        let generation_capacity = self.capacity / 2;
+        if generation_capacity == 0 {
+            return;
+        }
+        if self.new.len() >= generation_capacity {
+            self.old = mem::take(&mut self.new);
+        }
+        self.new.name2id.insert(name.0.clone(), id);
+        self.new.id2name.insert(id, name);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.new = Default::default();
+        self.old = Default::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(name: &str) -> VertexName {
+        VertexName::copy_from(name.as_bytes())
+    }
+
+    #[test]
+    fn test_id_name_cache() {
+        let mut cache = IdNameCache::new(4);
+        cache.insert(Id(1), v("a"));
+        cache.insert(Id(2), v("b"));
+        assert_eq!(cache.lookup_name(Id(1)), Some(v("a")));
+        assert_eq!(cache.lookup_id(b"b"), Some(Id(2)));
+        assert_eq!(cache.lookup_id(b"c"), None);
+
+        // "a" and "b" move to the old generation.
+        cache.insert(Id(3), v("c"));
+        // "a" is promoted to the new generation.
+        assert_eq!(cache.lookup_id(b"a"), Some(Id(1)));
+        // "b" is dropped with the old generation.
+        cache.insert(Id(4), v("d"));
+        assert_eq!(cache.lookup_name(Id(2)), None);
+        assert_eq!(cache.lookup_name(Id(1)), Some(v("a")));
+        assert_eq!(cache.lookup_name(Id(4)), Some(v("d")));
+
+        cache.clear();
+        assert_eq!(cache.lookup_name(Id(1)), None);
+
+        let mut cache = IdNameCache::new(0);
+        cache.insert(Id(1), v("a"));
+        assert_eq!(cache.lookup_name(Id(1)), None);
+    }
+}
@@ -5,12 +5,14 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use std::borrow::Cow;
 use std::fmt;
 use std::fs;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Mutex;
 
 use byteorder::BigEndian;
 use byteorder::ReadBytesExt;
@@ -19,6 +21,8 @@ use indexedlog::log;
 use vlqencoding::VLQDecode;
 use vlqencoding::VLQEncode;
 
+use super::cache::IdNameCache;
+use super::IdMapEntries;
 use super::IdMapWrite;
 use crate::errors::bug;
 use crate::errors::programming;
@@ -26,10 +30,16 @@ use crate::errors::NotFoundError;
 use crate::id::Group;
 use crate::id::Id;
 use crate::id::VertexName;
+use crate::iddag::IdDag;
+use crate::iddag::IdDagAlgorithm;
+use crate::iddagstore::IdDagStore;
 use crate::ops::IdConvert;
+use crate::ops::Parents;
 use crate::ops::Persist;
 use crate::ops::PrefixLookup;
 use crate::ops::TryClone;
+use crate::IdSet;
+use crate::IdSpan;
 use crate::Result;
 use crate::VerLink;
 
@@ -41,6 +51,7 @@ pub struct IdMap {
     path: PathBuf,
     map_id: String,
     map_version: VerLink,
+    cache: Mutex<IdNameCache>,
 }
 
 impl IdMap {
@@ -53,6 +64,10 @@ impl IdMap {
     // - Clear non-master (only id->name mappings, being deprecated):
     //   CLRNM
 
+    /// Default number of `Id` <-> `VertexName` lookup results to keep in
+    /// memory.
+    const DEFAULT_CACHE_CAPACITY: usize = 16384;
+
     const INDEX_ID_TO_NAME: usize = 0;
     const INDEX_GROUP_NAME_TO_ID: usize = 1;
 
@@ -77,6 +92,17 @@ impl IdMap {
         let log = Self::log_open_options().open(path)?;
         Self::open_from_log(log)
     }
+
+    /// Open an existing [`IdMap`] in read-only mode.
+    ///
+    /// The [`IdMap`] is mmap-backed like [`IdMap::open`] and holds the shared
+    /// reader lock, but takes no write lock and writes no index files. This
+    /// is suitable for parallel readers. Persisting changes fails.
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let log = Self::log_open_options().read_only(true).open(path)?;
+        Self::open_from_log(log)
+    }
 }
 
 impl TryClone for IdMap {
@@ -86,6 +112,7 @@ impl TryClone for IdMap {
             path: self.path.clone(),
             map_id: self.map_id.clone(),
             map_version: self.map_version.clone(),
+            cache: Mutex::new(IdNameCache::new(self.cache.lock().unwrap().capacity())),
         };
         Ok(result)
     }
@@ -100,9 +127,18 @@ impl IdMap {
             path,
             map_id,
             map_version: VerLink::new(),
+            cache: Mutex::new(IdNameCache::new(Self::DEFAULT_CACHE_CAPACITY)),
         })
     }
 
+    /// Set the maximum number of `Id` <-> `VertexName` lookup results to
+    /// keep in memory. This speeds up repetitive lookups of the same
+    /// vertexes, for example, when rendering a graph. Setting it to 0
+    /// disables the cache.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        *self.cache.get_mut().unwrap() = IdNameCache::new(capacity);
+    }
+
     pub(crate) fn log_open_options() -> log::OpenOptions {
         assert!(Self::MAGIC_DELETION_PREFIX > &Id::MAX.0.to_be_bytes()[..]);
         log::OpenOptions::new()
@@ -177,12 +213,33 @@ impl IdMap {
 
     /// Find VertexName by a specified integer id.
     pub fn find_vertex_name_by_id(&self, id: Id) -> Result<Option<VertexName>> {
-        self.find_name_by_id(id)
-            .map(|v| v.map(|n| VertexName(self.log.slice_to_bytes(n))))
+        if let Some(name) = self.cache.lock().unwrap().lookup_name(id) {
+            return Ok(Some(name));
+        }
+        let name = self
+            .find_name_by_id(id)?
+            .map(|n| VertexName(self.log.slice_to_bytes(n)));
+        if let Some(name) = &name {
+            self.cache.lock().unwrap().insert(id, name.clone());
+        }
+        Ok(name)
     }
 
     /// Find the integer id matching the given name.
     pub fn find_id_by_name(&self, name: &[u8]) -> Result<Option<Id>> {
+        if let Some(id) = self.cache.lock().unwrap().lookup_id(name) {
+            return Ok(Some(id));
+        }
+        let id = self.find_id_by_name_uncached(name)?;
+        if let Some(id) = id {
+            let name = VertexName::copy_from(name);
+            self.cache.lock().unwrap().insert(id, name);
+        }
+        Ok(id)
+    }
+
+    /// Find the integer id matching the given name, bypassing the cache.
+    fn find_id_by_name_uncached(&self, name: &[u8]) -> Result<Option<Id>> {
         for group in Group::ALL.iter() {
             let mut group_name = Vec::with_capacity(Group::BYTES + name.len());
             group_name.extend_from_slice(&group.bytes());
@@ -267,32 +324,164 @@ impl IdMap {
         Ok(())
     }
 
+    /// Check that the id-to-name and name-to-id indexes agree with each
+    /// other, and that ids are contiguous in each group.
+    ///
+    /// Lookups bypass the in-memory cache, so the on-disk indexes are
+    /// checked.
+    ///
+    /// Broken indexes can be rebuilt by [`IdMap::repair`].
+    pub fn verify(&self) -> Result<IdMapVerifyReport> {
+        let mut report = IdMapVerifyReport::default();
+
+        // id -> name -> id
+        for group in Group::ALL.iter() {
+            let mut ids = Vec::new();
+            for (id, name) in self.find_range(group.min_id(), group.max_id())? {
+                ids.push(id);
+                let found = self.find_id_by_name_uncached(name)?;
+                if found != Some(id) {
+                    report.problems.push(IdMapProblem::IdToName {
+                        id,
+                        name: VertexName::copy_from(name),
+                        found,
+                    });
+                }
+            }
+            if let Some(&max_id) = ids.last() {
+                let present = IdSet::from_spans(ids);
+                let missing = IdSet::from((group.min_id(), max_id)).difference(&present);
+                if !missing.is_empty() {
+                    report.gaps.push((*group, missing));
+                }
+            }
+        }
+
+        // name -> id -> name
+        for group in Group::ALL.iter() {
+            for entry in self
+                .log
+                .lookup_prefix(Self::INDEX_GROUP_NAME_TO_ID, group.bytes())?
+            {
+                let (key, values) = entry?;
+                let name = VertexName::copy_from(&key[Group::BYTES..]);
+                for value in values {
+                    let mut value = value?;
+                    if value.len() < 8 {
+                        let name = name.clone();
+                        report.problems.push(IdMapProblem::Truncated { name });
+                        continue;
+                    }
+                    let id = Id(value.read_u64::<BigEndian>().unwrap());
+                    if id.group() != *group {
+                        report.problems.push(IdMapProblem::WrongGroup {
+                            name: name.clone(),
+                            id,
+                            group: *group,
+                        });
+                    }
+                    let found = self.find_name_by_id(id)?;
+                    if found != Some(name.as_ref()) {
+                        report.problems.push(IdMapProblem::NameToId {
+                            name: name.clone(),
+                            id,
+                            found: found.map(VertexName::copy_from),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Check that parents in `dag` segments match `parents` for vertexes
+    /// in this map.
+    ///
+    /// Vertexes not in `dag` are skipped. A parent that is not in this map
+    /// is reported as `None` in [`IdMapProblem::Parents`].
+    pub async fn verify_parents<Store: IdDagStore>(
+        &self,
+        dag: &IdDag<Store>,
+        parents: &dyn Parents,
+    ) -> Result<Vec<IdMapProblem>> {
+        let mut problems = Vec::new();
+        let all = dag.all()?;
+        for (id, name) in self.find_range(Id::MIN, Id::MAX)? {
+            if !all.contains(id) {
+                continue;
+            }
+            let name = VertexName::copy_from(name);
+            let segment_parents = dag.parent_ids(id)?;
+            let mut expected_parents = Vec::new();
+            for parent_name in parents.parent_names(name.clone()).await? {
+                expected_parents.push(self.find_id_by_name_uncached(parent_name.as_ref())?);
+            }
+            let matched = segment_parents.len() == expected_parents.len()
+                && segment_parents
+                    .iter()
+                    .zip(&expected_parents)
+                    .all(|(&p, &e)| Some(p) == e);
+            if !matched {
+                problems.push(IdMapProblem::Parents {
+                    id,
+                    name,
+                    segment_parents,
+                    expected_parents,
+                });
+            }
+        }
+        Ok(problems)
+    }
+
+    /// Rebuild the id-to-name and name-to-id indexes of the [`IdMap`] at
+    /// `path` from its log.
+    ///
+    /// Return a message useful for human consumption.
+    pub fn repair(path: impl AsRef<Path>) -> Result<String> {
+        let log = Self::log_open_options().open(path.as_ref())?;
+        Ok(log.rebuild_indexes(true)?)
+    }
+
     /// Find all (id, name) pairs in the `low..=high` range.
     fn find_range(&self, low: Id, high: Id) -> Result<Vec<(Id, &[u8])>> {
+        let mut items = Vec::new();
+        for entry in self.lookup_id_range(low, high)? {
+            items.extend(Self::decode_id_range_entry(entry)?);
+        }
+        Ok(items)
+    }
+
+    fn lookup_id_range(&self, low: Id, high: Id) -> Result<log::LogRangeIter> {
         let low = low.0.to_be_bytes();
         let high = high.0.to_be_bytes();
         let range = &low[..]..=&high[..];
+        Ok(self.log.lookup_range(Self::INDEX_ID_TO_NAME, range)?)
+    }
+
+    /// Decode an entry yielded by `lookup_id_range`.
+    fn decode_id_range_entry<'a>(
+        entry: indexedlog::Result<(Cow<'a, [u8]>, log::LogLookupIter<'a>)>,
+    ) -> Result<Vec<(Id, &'a [u8])>> {
+        let (key, values) = entry?;
+        let key: [u8; 8] = match key.as_ref().try_into() {
+            Ok(key) => key,
+            Err(_) => {
+                return bug("find_range got non-u64 keys in INDEX_ID_TO_NAME");
+            }
+        };
+        let id = Id(u64::from_be_bytes(key));
         let mut items = Vec::new();
-        for entry in self.log.lookup_range(Self::INDEX_ID_TO_NAME, range)? {
-            let (key, values) = entry?;
-            let key: [u8; 8] = match key.as_ref().try_into() {
-                Ok(key) => key,
-                Err(_) => {
-                    return bug("find_range got non-u64 keys in INDEX_ID_TO_NAME");
-                }
-            };
-            let id = Id(u64::from_be_bytes(key));
-            for value in values {
-                let value = value?;
-                if value.len() < 8 {
-                    return bug(format!(
-                        "find_range got entry {:?} shorter than expected",
-                        &value
-                    ));
-                }
-                let name: &[u8] = &value[9..];
-                items.push((id, name));
+        for value in values {
+            let value = value?;
+            if value.len() < 8 {
+                return bug(format!(
+                    "find_range got entry {:?} shorter than expected",
+                    &value
+                ));
             }
+            let name: &[u8] = &value[9..];
+            items.push((id, name));
         }
         Ok(items)
     }
@@ -309,6 +498,7 @@ impl IdMap {
         // will handle it.
         let data = encode_deletion_entry(&items);
         self.log.append(data)?;
+        self.cache.get_mut().unwrap().clear();
         // New map is not an "append-only" version of the previous map.
         // Re-create the VerLink to mark it as incompatible.
         self.map_version = VerLink::new();
@@ -378,6 +568,95 @@ fn decode_deletion_entry(data: &[u8]) -> Result<Vec<(Id, &[u8])>> {
     Ok(items)
 }
 
+/// Result of [`IdMap::verify`].
+#[derive(Debug, Default, Clone)]
+pub struct IdMapVerifyReport {
+    /// Entries where the id-to-name and name-to-id indexes disagree.
+    pub problems: Vec<IdMapProblem>,
+
+    /// Ids missing between the first id of a group and the largest id
+    /// assigned in that group. Gaps are expected for lazy graphs, where
+    /// the master group is only partially known locally.
+    pub gaps: Vec<(Group, IdSet)>,
+}
+
+impl IdMapVerifyReport {
+    /// Whether no problems or gaps were found.
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty() && self.gaps.is_empty()
+    }
+}
+
+/// A problem found by [`IdMap::verify`] or [`IdMap::verify_parents`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdMapProblem {
+    /// `id` maps to `name`, but `name` maps to `found`.
+    IdToName {
+        id: Id,
+        name: VertexName,
+        found: Option<Id>,
+    },
+
+    /// `name` maps to `id`, but `id` maps to `found`.
+    NameToId {
+        name: VertexName,
+        id: Id,
+        found: Option<VertexName>,
+    },
+
+    /// `name` maps to `id`, but is indexed in `group`.
+    WrongGroup {
+        name: VertexName,
+        id: Id,
+        group: Group,
+    },
+
+    /// The name-to-id entry of `name` is too short to contain an id.
+    Truncated { name: VertexName },
+
+    /// Segments say `id` has `segment_parents`, but its parents resolve to
+    /// `expected_parents`.
+    Parents {
+        id: Id,
+        name: VertexName,
+        segment_parents: Vec<Id>,
+        expected_parents: Vec<Option<Id>>,
+    },
+}
+
+impl fmt::Display for IdMapProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IdMapProblem::IdToName { id, name, found } => write!(
+                f,
+                "{:?} maps to {:?}, but {:?} maps to {:?}",
+                id, name, name, found
+            ),
+            IdMapProblem::NameToId { name, id, found } => write!(
+                f,
+                "{:?} maps to {:?}, but {:?} maps to {:?}",
+                name, id, id, found
+            ),
+            IdMapProblem::WrongGroup { name, id, group } => write!(
+                f,
+                "{:?} maps to {:?} in a wrong group {:?}",
+                name, id, group
+            ),
+            IdMapProblem::Truncated { name } => write!(f, "{:?} has a truncated entry", name),
+            IdMapProblem::Parents {
+                id,
+                name,
+                segment_parents,
+                expected_parents,
+            } => write!(
+                f,
+                "{:?} ({:?}) has parents {:?} in segments, but {:?} in the graph",
+                id, name, segment_parents, expected_parents
+            ),
+        }
+    }
+}
+
 impl fmt::Debug for IdMap {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "IdMap {{\n")?;
@@ -421,6 +700,16 @@ impl IdConvert for IdMap {
     async fn contains_vertex_name(&self, name: &VertexName) -> Result<bool> {
         Ok(self.find_id_by_name(name.as_ref())?.is_some())
     }
+    async fn vertex_name_batch(&self, ids: &[Id]) -> Result<Vec<Result<VertexName>>> {
+        let mut names = Vec::with_capacity(ids.len());
+        for &id in ids {
+            let name = self
+                .find_vertex_name_by_id(id)
+                .and_then(|name| name.ok_or_else(|| id.not_found_error()));
+            names.push(name);
+        }
+        Ok(names)
+    }
     async fn contains_vertex_id_locally(&self, ids: &[Id]) -> Result<Vec<bool>> {
         let mut list = Vec::with_capacity(ids.len());
         for &id in ids {
@@ -445,6 +734,24 @@ impl IdConvert for IdMap {
     }
 }
 
+impl IdMapEntries for IdMap {
+    fn entries_in_range(
+        &self,
+        span: IdSpan,
+    ) -> Result<Box<dyn Iterator<Item = Result<(Id, VertexName)>> + '_>> {
+        let iter = self
+            .lookup_id_range(span.low, span.high)?
+            .flat_map(move |entry| match Self::decode_id_range_entry(entry) {
+                Ok(items) => items
+                    .into_iter()
+                    .map(|(id, name)| Ok((id, VertexName(self.log.slice_to_bytes(name)))))
+                    .collect::<Vec<_>>(),
+                Err(err) => vec![Err(err)],
+            });
+        Ok(Box::new(iter))
+    }
+}
+
 #[async_trait::async_trait]
 impl IdMapWrite for IdMap {
     async fn insert(&mut self, id: Id, name: &[u8]) -> Result<()> {
@@ -479,6 +786,8 @@ impl Persist for IdMap {
     fn reload(&mut self, _lock: &Self::Lock) -> Result<()> {
         self.log.clear_dirty()?;
         self.log.sync()?;
+        // Entries might be removed by other processes.
+        self.cache.get_mut().unwrap().clear();
         Ok(())
     }
 
@@ -9,6 +9,7 @@ use std::collections::BTreeMap;
 use std::sync::atomic;
 use std::sync::atomic::AtomicU64;
 
+use super::IdMapEntries;
 use super::IdMapWrite;
 use crate::errors::NotFoundError;
 use crate::id::Group;
@@ -17,6 +18,7 @@ use crate::id::VertexName;
 use crate::ops::IdConvert;
 use crate::ops::Persist;
 use crate::ops::PrefixLookup;
+use crate::IdSpan;
 use crate::Result;
 use crate::VerLink;
 
@@ -166,6 +168,20 @@ impl IdConvert for MemIdMap {
     }
 }
 
+impl IdMapEntries for MemIdMap {
+    fn entries_in_range(
+        &self,
+        span: IdSpan,
+    ) -> Result<Box<dyn Iterator<Item = Result<(Id, VertexName)>> + '_>> {
+        let iter = self
+            .core
+            .id2name
+            .range(span.low..=span.high)
+            .map(|(id, name)| Ok((*id, name.clone())));
+        Ok(Box::new(iter))
+    }
+}
+
 // TODO: Reconsider re-assign master cases. Currently they are ignored.
 #[async_trait::async_trait]
 impl IdMapWrite for MemIdMap {
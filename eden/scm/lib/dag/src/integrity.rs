@@ -290,3 +290,127 @@ where
         Ok(problems)
     }
 }
+
+/// How thorough [`AbstractNameDag::verify`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VerifyLevel {
+    /// Only check that universally known vertexes are present locally.
+    Quick,
+
+    /// Also check segment properties on all levels.
+    Segments,
+
+    /// Also check that vertexes in segments can be resolved by the IdMap,
+    /// and the IdMap translates them back to the same `Id`s.
+    Full,
+}
+
+/// Problems found by [`AbstractNameDag::verify`].
+#[derive(Debug, Default, Clone)]
+pub struct VerifyReport {
+    /// Universally known `Id`s that are missing in the IdMap.
+    pub missing_universal_ids: Vec<Id>,
+
+    /// Problems about segments. See `CheckIntegrity::check_segments`.
+    pub segment_problems: Vec<String>,
+
+    /// Problems about the IdMap not matching segments.
+    pub idmap_problems: Vec<String>,
+}
+
+impl VerifyReport {
+    /// Whether no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.missing_universal_ids.is_empty()
+            && self.segment_problems.is_empty()
+            && self.idmap_problems.is_empty()
+    }
+}
+
+/// Number of `Id`s to resolve per IdMap batch in `verify`.
+const VERIFY_BATCH_SIZE: usize = 1000;
+
+impl<IS, M, P, S> AbstractNameDag<IdDag<IS>, M, P, S>
+where
+    IS: IdDagStore + Persist + 'static,
+    IdDag<IS>: TryClone,
+    M: TryClone + IdMapAssignHead + Persist + Send + Sync + 'static,
+    P: TryClone + Send + Sync + 'static,
+    S: TryClone + Persist + Send + Sync + 'static,
+{
+    /// Cross-check segments and the IdMap. Intended for fsck-like commands.
+    ///
+    /// Checks are cumulative: a higher `level` also runs the checks of lower
+    /// levels.
+    pub async fn verify(&self, level: VerifyLevel) -> Result<VerifyReport> {
+        let mut report = VerifyReport {
+            missing_universal_ids: self.check_universal_ids().await?,
+            ..Default::default()
+        };
+        if level >= VerifyLevel::Segments {
+            report.segment_problems = self.check_segments().await?;
+        }
+        if level >= VerifyLevel::Full {
+            report.idmap_problems = self.check_idmap().await?;
+        }
+        Ok(report)
+    }
+
+    /// Check that `Id`s in segments are known by the IdMap, and the IdMap
+    /// maps their names back to the same `Id`s.
+    ///
+    /// For lazy graphs, the master group is not fully known locally, and
+    /// is skipped.
+    async fn check_idmap(&self) -> Result<Vec<String>> {
+        let mut problems = Vec::new();
+        let groups: &[Group] = if self.is_vertex_lazy() {
+            &[Group::NON_MASTER]
+        } else {
+            &Group::ALL
+        };
+        let ids: Vec<Id> = self.dag.all_ids_in_groups(groups)?.iter_desc().collect();
+        tracing::debug!("checking {} ids in IdMap", ids.len());
+
+        for ids in ids.chunks(VERIFY_BATCH_SIZE) {
+            let exists = self.map.contains_vertex_id_locally(ids).await?;
+            let mut existing_ids = Vec::with_capacity(ids.len());
+            for (&id, b) in ids.iter().zip(exists) {
+                if b {
+                    existing_ids.push(id);
+                } else {
+                    problems.push(format!("{:?} is missing in IdMap", id));
+                }
+            }
+            let ids = existing_ids;
+            let names = self.map.vertex_name_batch(&ids).await?;
+            let mut resolved_ids = Vec::with_capacity(ids.len());
+            let mut resolved_names = Vec::with_capacity(ids.len());
+            for (id, name) in ids.into_iter().zip(names) {
+                match name {
+                    Ok(name) => {
+                        resolved_ids.push(id);
+                        resolved_names.push(name);
+                    }
+                    Err(e) => problems.push(format!("{:?} cannot be resolved: {:?}", id, e)),
+                }
+            }
+            let back_ids = self.map.vertex_id_batch(&resolved_names).await?;
+            for ((id, name), back_id) in resolved_ids.into_iter().zip(resolved_names).zip(back_ids)
+            {
+                match back_id {
+                    Ok(back_id) if back_id == id => {}
+                    Ok(back_id) => problems.push(format!(
+                        "{:?} maps to {:?} but {:?} maps to {:?}",
+                        id, &name, &name, back_id
+                    )),
+                    Err(e) => problems.push(format!(
+                        "{:?} maps to {:?} but {:?} cannot be resolved: {:?}",
+                        id, &name, &name, e
+                    )),
+                }
+            }
+        }
+
+        Ok(problems)
+    }
+}
@@ -12,11 +12,14 @@
 //!
 //! Building blocks for the commit graph used by source control.
 
+pub mod bisect;
 mod bsearch;
 mod default_impl;
 mod delegate;
+pub mod discovery;
 pub mod errors;
 mod fmt;
+pub mod head_ordering;
 pub mod iddag;
 pub mod iddagstore;
 pub mod idmap;
@@ -25,6 +28,7 @@ pub mod namedag;
 pub mod nameset;
 pub mod ops;
 pub mod protocol;
+pub mod query;
 pub mod render;
 pub mod segment;
 mod spanset;
@@ -40,12 +44,15 @@ pub use dag_types::Group;
 pub use dag_types::Id;
 pub use dag_types::Location;
 pub use dag_types::VertexName;
+pub use head_ordering::HeadOrdering;
 pub use iddag::FirstAncestorConstraint;
 pub use iddag::IdDag;
 pub use iddag::IdDagAlgorithm;
 pub use iddagstore::IdDagStore;
 #[cfg(any(test, feature = "indexedlog-backend"))]
 pub use idmap::IdMap;
+pub use integrity::VerifyLevel;
+pub use integrity::VerifyReport;
 #[cfg(any(test, feature = "indexedlog-backend"))]
 pub use namedag::NameDag;
 pub use namedag::NameDagBuilder;
@@ -70,6 +77,7 @@ pub type Set = NameSet;
 pub type IdSet = spanset::SpanSet;
 pub type IdSetIter<T> = spanset::SpanSetIter<T>;
 pub type IdSpan = spanset::Span;
+pub type IdSetBuilder = spanset::SpanSetBuilder;
 pub use namedag::MemNameDag as MemDag;
 pub use nameset::NameIter as SetIter;
 pub type Vertex = VertexName;
@@ -10,6 +10,7 @@
 //! Combination of IdMap and IdDag.
 
 use std::collections::BTreeMap;
+use std::collections::BinaryHeap;
 use std::collections::HashSet;
 use std::env::var;
 use std::fmt;
@@ -31,11 +32,13 @@ use crate::errors::bug;
 use crate::errors::programming;
 use crate::errors::DagError;
 use crate::errors::NotFoundError;
+use crate::head_ordering::HeadOrdering;
 use crate::id::Group;
 use crate::id::Id;
 use crate::id::VertexName;
 use crate::iddag::IdDag;
 use crate::iddag::IdDagAlgorithm;
+use crate::iddag::SegmentStats;
 use crate::iddagstore::IdDagStore;
 use crate::idmap::CoreMemIdMap;
 use crate::idmap::IdMapAssignHead;
@@ -82,14 +85,26 @@ mod builder;
 #[cfg(any(test, feature = "indexedlog-backend"))]
 mod indexedlog_namedag;
 mod mem_namedag;
+mod notify;
+mod phase;
+#[cfg(any(test, feature = "indexedlog-backend"))]
+mod set_cache;
+mod transaction;
 
 pub use builder::NameDagBuilder;
 #[cfg(any(test, feature = "indexedlog-backend"))]
+pub use indexedlog_namedag::DagStats;
+#[cfg(any(test, feature = "indexedlog-backend"))]
 pub use indexedlog_namedag::IndexedLogNameDagPath;
 #[cfg(any(test, feature = "indexedlog-backend"))]
 pub use indexedlog_namedag::NameDag;
 pub use mem_namedag::MemNameDag;
 pub use mem_namedag::MemNameDagPath;
+pub use notify::DagChange;
+pub use notify::DagChangeListener;
+#[cfg(any(test, feature = "indexedlog-backend"))]
+pub use set_cache::DerivedSetCache;
+pub use transaction::NameDagTransaction;
 
 pub struct AbstractNameDag<I, M, P, S>
 where
@@ -143,6 +158,15 @@ where
     /// A negative cache. Vertexes that are looked up remotely, and the remote
     /// confirmed the vertexes are outside the master group.
     missing_vertexes_confirmed_by_remote: Arc<RwLock<HashSet<VertexName>>>,
+
+    /// Decides the order of heads to assign ids in `add_heads_and_flush`.
+    head_ordering: Arc<dyn HeadOrdering>,
+
+    /// Public heads and the calculated public vertexes.
+    phase: phase::PhaseOverlay,
+
+    /// Called when vertexes are added or removed.
+    change_listeners: Vec<Arc<dyn DagChangeListener>>,
 }
 
 impl<D, M, P, S> AbstractNameDag<D, M, P, S>
@@ -210,18 +234,28 @@ where
         self.dag.reload(&dag_lock)?;
 
         // Build.
-        self.build_with_lock(parents, heads, &map_lock).await?;
+        let added_heads = self.new_heads_to_notify(heads).await?;
+        let heads = self
+            .head_ordering
+            .order_heads(&self.map, parents, heads)
+            .await?;
+        self.build_with_lock(parents, &heads, &map_lock).await?;
 
         // Write to disk.
         self.map.persist(&map_lock)?;
         self.dag.persist(&dag_lock)?;
         self.state.persist(&lock)?;
+        self.persist_phase().await?;
         drop(dag_lock);
         drop(map_lock);
         drop(lock);
 
         self.persisted_id_set = self.dag.all_ids_in_groups(&Group::ALL)?;
         debug_assert_eq!(self.dirty().await?.count().await?, 0);
+        self.notify_change(&DagChange {
+            added_heads,
+            ..Default::default()
+        });
         Ok(())
     }
 
@@ -260,12 +294,12 @@ where
 
         let parents: &(dyn DagAlgorithm + Send + Sync) = self;
         let non_master_heads: VertexListWithOptions = self.pending_heads.clone();
-        let seg_size = self.dag.get_new_segment_size();
-        new_name_dag.dag.set_new_segment_size(seg_size);
-        new_name_dag.set_remote_protocol(self.remote_protocol.clone());
-        new_name_dag.maybe_reuse_caches_from(self);
+        new_name_dag.copy_settings_from(self);
+        // Pending heads were reported by `add_heads`. Do not report them again.
+        let change_listeners = std::mem::take(&mut new_name_dag.change_listeners);
         let heads = heads.clone().chain(non_master_heads);
         new_name_dag.add_heads_and_flush(&parents, &heads).await?;
+        new_name_dag.change_listeners = change_listeners;
         *self = new_name_dag;
         Ok(())
     }
@@ -362,11 +396,22 @@ where
 
 impl<IS, M, P, S> AbstractNameDag<IdDag<IS>, M, P, S>
 where
-    IS: Send + Sync + 'static,
-    M: Send + Sync + 'static,
-    P: Send + Sync + 'static,
-    S: IntVersion + Send + Sync + 'static,
+    S: IntVersion,
 {
+    /// Copy settings, listeners and compatible caches from `other`.
+    /// Usually called when `self` is newly opened to replace `other`.
+    fn copy_settings_from(&mut self, other: &Self) {
+        self.dag
+            .set_new_segment_size(other.dag.get_new_segment_size());
+        self.dag
+            .set_defer_high_level_segments(other.dag.get_defer_high_level_segments());
+        self.remote_protocol = other.remote_protocol.clone();
+        self.head_ordering = other.head_ordering.clone();
+        self.phase = other.phase.clone();
+        self.change_listeners = other.change_listeners.clone();
+        self.maybe_reuse_caches_from(other);
+    }
+
     /// Attempt to reuse caches from `other` if two `NameDag`s are compatible.
     /// Usually called when `self` is newly created.
     fn maybe_reuse_caches_from(&mut self, other: &Self) {
@@ -410,6 +455,44 @@ where
         &mut self,
         parents: &dyn Parents,
         heads: &VertexListWithOptions,
+    ) -> Result<bool> {
+        self.add_heads_with_progress(parents, heads).await.1
+    }
+}
+
+impl<IS, M, P, S> AbstractNameDag<IdDag<IS>, M, P, S>
+where
+    IS: IdDagStore,
+    IdDag<IS>: TryClone,
+    M: TryClone + IdMapAssignHead + Send + Sync + 'static,
+    P: TryClone + Send + Sync + 'static,
+    S: TryClone + Send + Sync + 'static,
+{
+    /// Similar to `add_heads`, but also returns heads that were fully
+    /// processed, even if an error happened midway. For example, `parents`
+    /// might fail to fetch parents of a vertex.
+    ///
+    /// On error, the in-memory DAG, including the IdMap, contains the
+    /// completed heads and their ancestors, and nothing from the remaining
+    /// heads. So the caller can resume by calling `add_heads` with the
+    /// remaining heads.
+    pub async fn add_heads_with_progress(
+        &mut self,
+        parents: &dyn Parents,
+        heads: &VertexListWithOptions,
+    ) -> (Vec<VertexName>, Result<bool>) {
+        let mut completed = Vec::new();
+        let result = self
+            .add_heads_with_completed(parents, heads, &mut completed)
+            .await;
+        (completed, result)
+    }
+
+    async fn add_heads_with_completed(
+        &mut self,
+        parents: &dyn Parents,
+        heads: &VertexListWithOptions,
+        completed: &mut Vec<VertexName>,
     ) -> Result<bool> {
         self.invalidate_snapshot();
 
@@ -461,31 +544,57 @@ where
         let mut outcome = PreparedFlatSegments::default();
         let mut covered = self.dag().all_ids_in_groups(&Group::ALL)?;
         let mut reserved = calculate_initial_reserved(self, &covered, heads).await?;
-        for (head, opts) in heads.vertex_options() {
-            let need_assigning = match self
-                .vertex_id_with_max_group(&head, opts.highest_group)
-                .await?
-            {
-                Some(id) => !self.dag.contains_id(id)?,
-                None => true,
-            };
-            if need_assigning {
-                let group = opts.highest_group;
-                let prepared_segments = self
-                    .assign_head(head.clone(), parents, group, &mut covered, &reserved)
-                    .await?;
-                outcome.merge(prepared_segments);
-                if opts.reserve_size > 0 {
-                    let low = self.map.vertex_id(head.clone()).await? + 1;
-                    update_reserved(&mut reserved, &covered, low, opts.reserve_size);
+        let mut added_heads = Vec::new();
+        let result: Result<()> = async {
+            for (head, opts) in heads.vertex_options() {
+                let need_assigning = match self
+                    .vertex_id_with_max_group(&head, opts.highest_group)
+                    .await?
+                {
+                    Some(id) => !self.dag.contains_id(id)?,
+                    None => true,
+                };
+                if need_assigning {
+                    let group = opts.highest_group;
+                    let before = covered.clone();
+                    let prepared_segments = match self
+                        .assign_head(head.clone(), parents, group, &mut covered, &reserved)
+                        .await
+                    {
+                        Ok(segments) => segments,
+                        Err(e) => {
+                            // Remove ids assigned to ancestors of the failed
+                            // head. They are not in the IdDag.
+                            for span in covered.difference(&before).iter_span_desc() {
+                                self.map.remove_range(span.low, span.high).await?;
+                            }
+                            return Err(e);
+                        }
+                    };
+                    outcome.merge(prepared_segments);
+                    if opts.reserve_size > 0 {
+                        let low = self.map.vertex_id(head.clone()).await? + 1;
+                        update_reserved(&mut reserved, &covered, low, opts.reserve_size);
+                    }
+                    self.pending_heads.push((head.clone(), opts));
+                    added_heads.push(head.clone());
                 }
-                self.pending_heads.push((head, opts));
+                completed.push(head);
             }
+
+            Ok(())
         }
+        .await;
 
-        // Update segments in the NON_MASTER group.
+        // Update segments in the NON_MASTER group. This also happens if a
+        // head failed, so the IdDag covers the completed heads.
         self.dag
             .build_segments_from_prepared_flat_segments(&outcome)?;
+        self.notify_change(&DagChange {
+            added_heads,
+            ..Default::default()
+        });
+        result?;
 
         Ok(outcome.segment_count() > 0)
     }
@@ -512,17 +621,71 @@ where
         // the stripped segments.
         let mut new: Self = self.path.open()?;
         let (lock, map_lock, dag_lock) = new.reload()?;
-        new.set_remote_protocol(self.remote_protocol.clone());
-        new.maybe_reuse_caches_from(self);
+        new.copy_settings_from(self);
 
+        let removed_heads = new.stripped_heads_to_notify(set).await?;
         new.strip_with_lock(set, &map_lock).await?;
         new.persist(lock, map_lock, dag_lock)?;
+        new.persist_phase().await?;
 
         *self = new;
+        self.notify_change(&DagChange {
+            removed_heads,
+            ..Default::default()
+        });
         Ok(())
     }
 }
 
+/// Segment counts before and after [`AbstractNameDag::optimize`].
+#[derive(Debug, Clone)]
+pub struct OptimizeReport {
+    pub before: SegmentStats,
+    pub after: SegmentStats,
+}
+
+impl<IS, M, P, S> AbstractNameDag<IdDag<IS>, M, P, S>
+where
+    IS: IdDagStore + Persist + 'static,
+    IdDag<IS>: TryClone,
+    M: TryClone + IdMapAssignHead + Persist + Send + Sync + 'static,
+    P: TryClone + Open<OpenTarget = Self> + Send + Sync + 'static,
+    S: TryClone + IntVersion + Persist + Send + Sync + 'static,
+{
+    /// Rewrite segments on disk. Adjacent linear flat segments are merged
+    /// and high-level segments are rebuilt.
+    ///
+    /// Long-lived graphs might accumulate small flat segments that slow
+    /// down queries. This does not change the graph or the `Id`s.
+    pub fn optimize(&mut self) -> Result<OptimizeReport> {
+        if !self.pending_heads.is_empty() {
+            return programming(format!(
+                "optimize does not support pending heads ({:?})",
+                &self.pending_heads.vertexes(),
+            ));
+        }
+
+        let mut new: Self = self.path.open()?;
+        let (lock, map_lock, dag_lock) = new.reload()?;
+        new.copy_settings_from(self);
+
+        let before = new.dag.segment_stats()?;
+        if !new.dag.rebuild_segments()? {
+            return Ok(OptimizeReport {
+                after: before.clone(),
+                before,
+            });
+        }
+        let after = new.dag.segment_stats()?;
+        tracing::debug!(target: "dag::optimize", "segments: {:?} -> {:?}", &before, &after);
+        new.persist(lock, map_lock, dag_lock)?;
+        new.invalidate_snapshot();
+
+        *self = new;
+        Ok(OptimizeReport { before, after })
+    }
+}
+
 impl<IS, M, P, S> AbstractNameDag<IdDag<IS>, M, P, S>
 where
     IS: IdDagStore,
@@ -723,8 +886,8 @@ where
         // Constructs a new graph so we don't expose a broken `self` state on error.
         let mut new: Self = self.path.open()?;
         let (lock, map_lock, dag_lock) = new.reload()?;
-        new.set_remote_protocol(self.remote_protocol.clone());
-        new.maybe_reuse_caches_from(self);
+        new.copy_settings_from(self);
+        let added_heads = new.new_heads_to_notify(heads).await?;
 
         // Parents that should exist in the local graph. Look them up in 1 round-trip
         // and insert to the local graph.
@@ -912,7 +1075,7 @@ where
                             Ok(id) => {
                                 parent_client_ids.push(id);
                             }
-                            Err(crate::Error::VertexNotFound(_)) => {
+                            Err(crate::Error::VertexNotFound { .. }) => {
                                 missng_parent_server_ids.push(server_id);
                             }
                             Err(e) => return Err(e),
@@ -977,10 +1140,97 @@ where
 
         new.persist(lock, map_lock, dag_lock)?;
         *self = new;
+        self.notify_change(&DagChange {
+            added_heads,
+            ..Default::default()
+        });
         Ok(())
     }
 }
 
+impl<IS, M, P, S> AbstractNameDag<IdDag<IS>, M, P, S>
+where
+    IS: IdDagStore + Persist,
+    IdDag<IS>: TryClone + 'static,
+    M: TryClone + IdMapAssignHead + Persist + Send + Sync + 'static,
+    P: Open<OpenTarget = Self> + TryClone + Send + Sync + 'static,
+    S: IntVersion + TryClone + Persist + Send + Sync + 'static,
+{
+    /// Fast-forward the master group to match the master group of `other`,
+    /// for example, a dag provided by a server.
+    ///
+    /// The master group of `self` must be a prefix of the master group of
+    /// `other`, otherwise [`DagError::MasterDiverged`] is returned. Segments of the missing part are copied from `other` via
+    /// `export_pull_data` and `import_pull_data`. Ids are remapped if they
+    /// are taken locally. Vertexes are not visited one by one.
+    ///
+    /// Like `import_pull_data`, only heads and roots of the segments are
+    /// inserted to the local IdMap. Other names are resolved lazily using
+    /// the remote protocol, which should be able to answer questions about
+    /// `other`.
+    pub async fn graft_master(
+        &mut self,
+        other: &(impl DagAlgorithm + DagExportPullData + IdConvert + Sync),
+    ) -> Result<()> {
+        let local_master = self.master_group().await?;
+        let local_heads = self.heads(local_master.clone()).await?;
+        let local_heads: Vec<VertexName> = local_heads.iter().await?.try_collect().await?;
+        let known = other.contains_vertex_name_locally(&local_heads).await?;
+        let unknown: Vec<&VertexName> = local_heads
+            .iter()
+            .zip(known)
+            .filter_map(|(v, known)| if known { None } else { Some(v) })
+            .collect();
+        if !unknown.is_empty() {
+            return Err(DagError::MasterDiverged(format!(
+                "local master heads ({:?}) are not known by the other graph",
+                unknown
+            )));
+        }
+
+        // The local master group must be the ancestors of its heads in the
+        // other master group. Otherwise the graphs have different shapes.
+        let other_master = other.master_group().await?;
+        let local_heads_set = NameSet::from_static_names(local_heads.clone());
+        let not_master = local_heads_set.difference(&other_master);
+        let not_master: Vec<VertexName> = not_master.iter().await?.try_collect().await?;
+        if !not_master.is_empty() {
+            return Err(DagError::MasterDiverged(format!(
+                "local master heads ({:?}) are not in the master group of the other graph",
+                not_master
+            )));
+        }
+        let other_ancestors = other.ancestors(local_heads_set).await?;
+        let (local_count, other_count) =
+            (local_master.count().await?, other_ancestors.count().await?);
+        if local_count != other_count {
+            return Err(DagError::MasterDiverged(format!(
+                "local master group has {} vertexes but their ancestors in the other graph have {}",
+                local_count, other_count
+            )));
+        }
+
+        let other_heads = other.heads(other_master).await?;
+        let missing = other
+            .only(other_heads.clone(), NameSet::from_static_names(local_heads))
+            .await?;
+        if missing.is_empty().await? {
+            return Ok(());
+        }
+        let heads: Vec<VertexName> = other_heads
+            .intersection(&missing)
+            .iter()
+            .await?
+            .try_collect()
+            .await?;
+        tracing::debug!(target: "dag::graft", "graft master heads: {:?}", &heads);
+
+        let data = other.export_pull_data(&missing).await?;
+        let heads = VertexListWithOptions::from(heads).with_highest_group(Group::MASTER);
+        self.import_pull_data(data, &heads).await
+    }
+}
+
 #[async_trait::async_trait]
 impl<IS, M, P, S> DagExportCloneData for AbstractNameDag<IdDag<IS>, M, P, S>
 where
@@ -1067,6 +1317,40 @@ where
     }
 }
 
+impl<IS, M, P, S> AbstractNameDag<IdDag<IS>, M, P, S>
+where
+    IS: IdDagStore,
+    IdDag<IS>: TryClone,
+    M: TryClone + Send + Sync,
+    P: TryClone + Open<OpenTarget = Self> + Send + Sync,
+    S: TryClone + IntVersion + Send + Sync,
+{
+    /// Pick up changes written to disk by other processes or `NameDag`s.
+    ///
+    /// Reading does not take the write lock. A `NameDag` reads from an
+    /// immutable snapshot of the on-disk data taken at open time, so readers
+    /// are not blocked by concurrent writers. Call this to move to the latest
+    /// snapshot.
+    ///
+    /// Return `true` if new data was loaded, `false` if the on-disk data is
+    /// unchanged. Error if there are in-memory changes not yet flushed.
+    pub fn try_reload(&mut self) -> Result<bool> {
+        if !self.pending_heads.is_empty()
+            || self.dag.all()?.as_spans() != self.persisted_id_set.as_spans()
+        {
+            return programming("try_reload called with pending changes");
+        }
+
+        let mut new: Self = self.path.open()?;
+        if new.state.int_version() == self.state.int_version() {
+            return Ok(false);
+        }
+        new.copy_settings_from(self);
+        *self = new;
+        Ok(true)
+    }
+}
+
 impl<IS, M, P, S> AbstractNameDag<IdDag<IS>, M, P, S>
 where
     IS: IdDagStore,
@@ -1132,6 +1416,9 @@ where
                     missing_vertexes_confirmed_by_remote: Arc::clone(
                         &self.missing_vertexes_confirmed_by_remote,
                     ),
+                    head_ordering: self.head_ordering.clone(),
+                    phase: self.phase.clone(),
+                    change_listeners: self.change_listeners.clone(),
                 };
                 let result = Arc::new(cloned);
                 *snapshot = Some(Arc::clone(&result));
@@ -1159,6 +1446,61 @@ where
     pub(crate) fn get_remote_protocol(&self) -> Arc<dyn RemoteIdConvertProtocol> {
         self.remote_protocol.clone()
     }
+
+    /// Set the policy deciding the order of heads to assign ids in
+    /// `add_heads_and_flush`.
+    ///
+    /// By default, heads are assigned in the order specified by the caller.
+    /// See [`LongestBranchFirst`](crate::head_ordering::LongestBranchFirst)
+    /// for a policy that reduces fragmentation caused by merges.
+    pub fn set_head_ordering(&mut self, ordering: Arc<dyn HeadOrdering>) {
+        self.head_ordering = ordering;
+    }
+}
+
+impl<IS, M, P, S> AbstractNameDag<IdDag<IS>, M, P, S>
+where
+    IS: IdDagStore,
+    IdDag<IS>: TryClone + 'static,
+    M: TryClone + IdConvert + Sync + Send + 'static,
+    P: TryClone + Sync + Send + 'static,
+    S: TryClone + Sync + Send + 'static,
+{
+    /// Count `ancestors(name)`, including `name`.
+    ///
+    /// Unlike `ancestors(name).count()`, this only resolves `name` and does
+    /// not construct a set if the segments can answer the question directly.
+    pub async fn count_ancestors(&self, name: VertexName) -> Result<u64> {
+        let id = self.vertex_id(name).await?;
+        self.dag().count_ancestors(id)
+    }
+
+    /// Calculate `ancestors(set)` lazily, in descending `Id` order.
+    ///
+    /// Unlike `ancestors(set)`, this does not calculate the complete
+    /// ancestors first. Taking the first few items is cheap even if there
+    /// are millions of ancestors. Iterating through all ancestors is slower
+    /// than `ancestors(set)`, since parents are resolved one by one.
+    pub async fn ancestors_iter(&self, set: NameSet) -> Result<NameSet> {
+        let ids = self.to_id_set(&set).await?;
+        let snapshot = self.try_snapshot()?;
+        // Ancestors of heads(set) are ancestors(set).
+        let heads = snapshot.dag.heads(ids)?;
+        let iter = ancestors_id_iter(heads, move |id| snapshot.dag.parent_ids(id));
+        let map = self.id_map_snapshot()?;
+        let dag = self.dag_snapshot()?;
+        let result = NameSet::from_id_iter_idmap_dag(iter, map, dag);
+        result.hints().add_flags(Flags::ID_DESC | Flags::ANCESTORS);
+        Ok(result)
+    }
+
+    /// Count `roots::heads`, using span arithmetic without resolving the
+    /// vertexes in the range.
+    pub async fn count_range(&self, roots: NameSet, heads: NameSet) -> Result<u64> {
+        let roots = self.to_id_set(&roots).await?;
+        let heads = self.to_id_set(&heads).await?;
+        Ok(self.dag().range(roots, heads)?.count())
+    }
 }
 
 impl<IS, M, P, S> AbstractNameDag<IdDag<IS>, M, P, S>
@@ -1187,6 +1529,33 @@ where
     }
 }
 
+/// Iterate through ancestors of `heads` in descending `Id` order. Parents of
+/// an `Id` are only looked up after the `Id` is yielded.
+pub(crate) fn ancestors_id_iter(
+    heads: IdSet,
+    mut parent_ids: impl FnMut(Id) -> Result<Vec<Id>>,
+) -> impl Iterator<Item = Result<Id>> {
+    let mut to_visit: BinaryHeap<Id> = heads.iter_desc().collect();
+    let mut pending: Option<Id> = None;
+    std::iter::from_fn(move || {
+        if let Some(id) = pending.take() {
+            match parent_ids(id) {
+                Ok(parents) => to_visit.extend(parents),
+                Err(err) => {
+                    to_visit.clear();
+                    return Some(Err(err));
+                }
+            }
+        }
+        let id = to_visit.pop()?;
+        while to_visit.peek() == Some(&id) {
+            to_visit.pop();
+        }
+        pending = Some(id);
+        Some(Ok(id))
+    })
+}
+
 /// Calculate vertexes that are definitely not assigned (not in the IdMap,
 /// and not in the lazy part of the IdMap) according to
 /// `hint_pending_subdag`. This does not report all unassigned vertexes.
@@ -1627,6 +1996,40 @@ where
     }
 }
 
+impl<IS, M, P, S> AbstractNameDag<IdDag<IS>, M, P, S>
+where
+    IS: IdDagStore,
+    IdDag<IS>: TryClone + 'static,
+    M: TryClone + IdConvert + Sync + Send + 'static,
+    P: TryClone + Sync + Send + 'static,
+    S: TryClone + Sync + Send + 'static,
+{
+    /// A token that identifies the vertexes and edges of the graph.
+    ///
+    /// The token is a hash of the heads of the graph, assuming vertex names
+    /// commit to their parents, like commit hashes. So graphs with the same
+    /// vertexes and edges get the same token, regardless of how ids or
+    /// segments are assigned, or which process calculates it.
+    ///
+    /// Unlike `dag_version`, this is not changed by operations that only
+    /// change the segment layout, like building high-level segments.
+    ///
+    /// The same token does not imply the same ids. Use
+    /// [`IdMapToken`](protocol::IdMapToken) to check whether sets can be
+    /// exchanged as spans.
+    pub async fn version(&self) -> Result<u64> {
+        let heads = self.heads(self.all().await?).await?;
+        let mut heads: Vec<VertexName> = heads.iter().await?.try_collect().await?;
+        heads.sort_unstable();
+        let mut buf = Vec::new();
+        for head in heads {
+            buf.extend_from_slice(&(head.as_ref().len() as u64).to_le_bytes());
+            buf.extend_from_slice(head.as_ref());
+        }
+        Ok(utils::stable_hash(&buf))
+    }
+}
+
 // Dag operations. Those are just simple wrappers around [`IdDag`].
 // See [`IdDag`] for the actual implementations of these algorithms.
 
@@ -1701,8 +2104,33 @@ where
         }
         let spans = self.to_id_set(&set).await?;
         let spans = self.dag().ancestors(spans)?;
+        let heads = self.dag().heads_ancestors(spans.clone())?;
+        let depths = heads
+            .iter_desc()
+            .map(|id| self.dag().depth(id))
+            .collect::<Result<Vec<u64>>>()?;
+        let max_depth = depths.into_iter().max();
         let result = NameSet::from_spans_dag(spans, self)?;
         result.hints().add_flags(Flags::ANCESTORS);
+        if let Some(max_depth) = max_depth {
+            // A non-empty ancestors set always includes a root.
+            result.hints().set_min_depth(0).set_max_depth(max_depth);
+        }
+        Ok(result)
+    }
+
+    /// Calculates ancestors that are at most `depth` edges away from the
+    /// given set.
+    async fn ancestors_within(&self, set: NameSet, depth: u64) -> Result<NameSet> {
+        #[cfg(test)]
+        let set2 = set.clone();
+        let spans = self.to_id_set(&set).await?;
+        let spans = self.dag().ancestors_within(spans, depth)?;
+        let result = NameSet::from_spans_dag(spans, self)?;
+        #[cfg(test)]
+        {
+            result.assert_eq(crate::default_impl::ancestors_within(self, set2, depth).await?);
+        }
         Ok(result)
     }
 
@@ -1864,6 +2292,50 @@ where
         Ok(result)
     }
 
+    /// Calculates the depth (generation number) of `name`.
+    async fn depth(&self, name: VertexName) -> Result<u64> {
+        #[cfg(test)]
+        let result2 = crate::default_impl::depth(self, name.clone()).await?;
+        let id = self.vertex_id(name).await?;
+        let result = self.dag().depth(id)?;
+        #[cfg(test)]
+        {
+            assert_eq!(result, result2);
+        }
+        Ok(result)
+    }
+
+    /// Find a shortest path from `ancestor` to `descendant`.
+    async fn shortest_path(
+        &self,
+        ancestor: VertexName,
+        descendant: VertexName,
+    ) -> Result<Option<Vec<VertexName>>> {
+        #[cfg(test)]
+        let result2 =
+            crate::default_impl::shortest_path(self, ancestor.clone(), descendant.clone()).await?;
+        let ancestor_id = self.vertex_id(ancestor).await?;
+        let descendant_id = self.vertex_id(descendant).await?;
+        let result = match self.dag().shortest_path(ancestor_id, descendant_id)? {
+            None => None,
+            Some(ids) => Some(
+                self.vertex_name_batch(&ids)
+                    .await?
+                    .into_iter()
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+        };
+        #[cfg(test)]
+        {
+            // There might be multiple shortest paths.
+            assert_eq!(
+                result.as_ref().map(|p| p.len()),
+                result2.as_ref().map(|p| p.len())
+            );
+        }
+        Ok(result)
+    }
+
     /// Calculates "heads" of the ancestors of the given set. That is,
     /// Find Y, which is the smallest subset of set X, where `ancestors(Y)` is
     /// `ancestors(X)`.
@@ -1896,6 +2368,37 @@ where
         Ok(result)
     }
 
+    /// Calculates `ancestors(reachable) - ancestors(unreachable)`.
+    async fn only(&self, reachable: NameSet, unreachable: NameSet) -> Result<NameSet> {
+        #[cfg(test)]
+        let (reachable2, unreachable2) = (reachable.clone(), unreachable.clone());
+        let reachable = self.to_id_set(&reachable).await?;
+        let unreachable = self.to_id_set(&unreachable).await?;
+        let spans = self.dag().only(reachable, unreachable)?;
+        let result = NameSet::from_spans_dag(spans, self)?;
+        #[cfg(test)]
+        {
+            result.assert_eq(crate::default_impl::only(self, reachable2, unreachable2).await?);
+        }
+        Ok(result)
+    }
+
+    /// Calculates `ancestors(reachable) - ancestors(unreachable)`, and
+    /// `ancestors(unreachable)`.
+    async fn only_both(
+        &self,
+        reachable: NameSet,
+        unreachable: NameSet,
+    ) -> Result<(NameSet, NameSet)> {
+        let reachable = self.to_id_set(&reachable).await?;
+        let unreachable = self.to_id_set(&unreachable).await?;
+        let (spans, ancestors) = self.dag().only_both(reachable, unreachable)?;
+        let result = NameSet::from_spans_dag(spans, self)?;
+        let ancestors = NameSet::from_spans_dag(ancestors, self)?;
+        ancestors.hints().add_flags(Flags::ANCESTORS);
+        Ok((result, ancestors))
+    }
+
     /// Calculates the descendants of the given set.
     async fn descendants(&self, set: NameSet) -> Result<NameSet> {
         let spans = self.dag().descendants(self.to_id_set(&set).await?)?;
@@ -1983,7 +2486,7 @@ where
     async fn vertex_id(&self, name: VertexName) -> Result<Id> {
         match self.map.vertex_id(name.clone()).await {
             Ok(id) => Ok(id),
-            Err(crate::Error::VertexNotFound(_)) if self.is_vertex_lazy() => {
+            Err(crate::Error::VertexNotFound { .. }) if self.is_vertex_lazy() => {
                 if let Some(id) = self.overlay_map.read().unwrap().lookup_vertex_id(&name) {
                     return Ok(id);
                 }
@@ -97,6 +97,9 @@ where
             overlay_map_paths: Default::default(),
             remote_protocol: Arc::new(()),
             missing_vertexes_confirmed_by_remote: Default::default(),
+            head_ordering: Arc::new(()),
+            phase: Default::default(),
+            change_listeners: Vec::new(),
         };
         Ok(dag)
     }
@@ -5,23 +5,32 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
+use std::thread;
+use std::thread::JoinHandle;
 
 use indexedlog::multi;
 use indexedlog::DefaultOpenOptions;
 use indexedlog::OpenWithRepair;
 
+use super::phase::PhaseOverlay;
 use super::AbstractNameDag;
 use super::NameDagBuilder;
 use crate::errors::bug;
 use crate::iddag::IdDag;
+use crate::iddag::IdDagAlgorithm;
+use crate::iddagstore::IdDagStore;
 use crate::iddagstore::IndexedLogStore;
 use crate::idmap::IdMap;
+use crate::idmap::IdMapEntries;
+use crate::ops::DagAlgorithm;
 use crate::ops::IntVersion;
 use crate::ops::Open;
 use crate::ops::Persist;
 use crate::ops::TryClone;
+use crate::Level;
 use crate::Result;
 
 /// A DAG that uses VertexName instead of ids as vertexes.
@@ -37,6 +46,35 @@ pub struct NameDagState {
     mlog: Option<multi::MultiLog>,
 }
 
+/// Statistics about a [`NameDag`]. See [`NameDag::stats`].
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct DagStats {
+    /// Number of segments at each level. The `n`-th item is the number of
+    /// segments at level `n`.
+    pub segment_counts: Vec<usize>,
+
+    /// The highest segment level.
+    pub max_level: Level,
+
+    /// Number of ids covered by segments.
+    pub id_count: u64,
+
+    /// Average number of ids in a flat segment. Smaller values mean the
+    /// graph is more fragmented.
+    pub average_flat_segment_length: f64,
+
+    /// Number of entries in the `IdMap`. This can be smaller than
+    /// `id_count` for a lazy graph.
+    pub idmap_entry_count: usize,
+
+    /// Size of the `IdMap` files on disk, in bytes.
+    pub idmap_disk_size: u64,
+
+    /// Size of the segment files on disk, in bytes.
+    pub iddag_disk_size: u64,
+}
+
 /// Address to on-disk NameDag based on indexedlog.
 #[derive(Debug, Clone)]
 pub struct IndexedLogNameDagPath(pub PathBuf);
@@ -57,11 +95,16 @@ impl Open for IndexedLogNameDagPath {
         let dag = IdDag::open_from_store(IndexedLogStore::open_from_clean_log(dag_log)?)?;
         let state = NameDagState { mlog: Some(mlog) };
         let id = format!("ilog:{}", self.0.display());
-        let dag = NameDagBuilder::new_with_idmap_dag(map, dag)
+        let mut dag = NameDagBuilder::new_with_idmap_dag(map, dag)
             .with_path(self.clone())
             .with_state(state)
             .with_id(id)
             .build()?;
+        dag.phase = PhaseOverlay::load(
+            path.join("phase"),
+            dag.state.int_version(),
+            dag.dag.version(),
+        );
         Ok(dag)
     }
 }
@@ -81,6 +124,85 @@ impl NameDag {
         let path = IndexedLogNameDagPath(path);
         path.open()
     }
+
+    /// Skip building high-level segments when adding vertexes. This makes
+    /// `add_heads_and_flush` and `flush` faster. Use
+    /// `spawn_build_high_level_segments` to build the skipped segments
+    /// later.
+    pub fn set_defer_high_level_segments(&mut self, defer: bool) {
+        self.dag.set_defer_high_level_segments(defer);
+    }
+
+    /// Report segment counts, `IdMap` size, and disk usage. Useful for
+    /// monitoring fragmentation.
+    ///
+    /// This iterates through all segments and `IdMap` entries, so it can
+    /// take a while for a large graph.
+    pub fn stats(&self) -> Result<DagStats> {
+        let segment_counts = self.dag.segment_counts()?;
+        let max_level = self.dag.max_level()?;
+        let id_count = self.dag.all()?.count();
+        let average_flat_segment_length = match segment_counts.first() {
+            Some(&count) if count > 0 => id_count as f64 / count as f64,
+            _ => 0.0,
+        };
+        let mut idmap_entry_count = 0;
+        for entry in self.map.entries()? {
+            entry?;
+            idmap_entry_count += 1;
+        }
+        let dir = &self.path.0;
+        Ok(DagStats {
+            segment_counts,
+            max_level,
+            id_count,
+            average_flat_segment_length,
+            idmap_entry_count,
+            idmap_disk_size: dir_size(&dir.join("idmap2"))?,
+            iddag_disk_size: dir_size(&dir.join("iddag"))?,
+        })
+    }
+
+    /// Build missing high-level segments on a background thread, and write
+    /// them to disk. The thread returns the number of segments built.
+    ///
+    /// This `NameDag` is not changed. Use `try_reload` to pick up the new
+    /// segments after the thread completes.
+    pub fn spawn_build_high_level_segments(&self) -> JoinHandle<Result<usize>> {
+        let path = self.path.clone();
+        let seg_size = self.dag.get_new_segment_size();
+        thread::spawn(move || {
+            let mut dag = path.open()?;
+            dag.dag.set_new_segment_size(seg_size);
+            let (lock, map_lock, dag_lock) = dag.reload()?;
+            let count = dag.dag.build_missing_high_level_segments()?;
+            if count > 0 {
+                dag.persist(lock, map_lock, dag_lock)?;
+            }
+            Ok(count)
+        })
+    }
+}
+
+/// Total size of files in `dir`, recursively. Return 0 if `dir` does not
+/// exist.
+fn dir_size(dir: &Path) -> Result<u64> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let mut size = 0;
+    for entry in entries {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else {
+            size += meta.len();
+        }
+    }
+    Ok(size)
 }
 
 impl Persist for NameDagState {
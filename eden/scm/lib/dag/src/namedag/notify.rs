@@ -0,0 +1,117 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::sync::Arc;
+
+use futures::TryStreamExt;
+
+use super::AbstractNameDag;
+use crate::iddag::IdDag;
+use crate::iddagstore::IdDagStore;
+use crate::nameset::NameSet;
+use crate::ops::DagAlgorithm;
+use crate::ops::IdConvert;
+use crate::ops::TryClone;
+use crate::Result;
+use crate::VertexListWithOptions;
+use crate::VertexName;
+
+/// Describes a change to a `NameDag`. Delivered to [`DagChangeListener`]s.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DagChange {
+    /// Heads that were added, together with their ancestors that were not
+    /// in the graph.
+    pub added_heads: Vec<VertexName>,
+
+    /// Heads of vertexes that were removed.
+    pub removed_heads: Vec<VertexName>,
+}
+
+impl DagChange {
+    pub fn is_empty(&self) -> bool {
+        self.added_heads.is_empty() && self.removed_heads.is_empty()
+    }
+}
+
+/// Gets notified when vertexes are added to, or removed from a `NameDag`.
+///
+/// Listeners are called synchronously after the change is applied. They
+/// should be fast. For example, send the change to a channel and process
+/// it elsewhere.
+pub trait DagChangeListener: Send + Sync {
+    fn on_change(&self, change: &DagChange);
+}
+
+impl<F: Fn(&DagChange) + Send + Sync> DagChangeListener for F {
+    fn on_change(&self, change: &DagChange) {
+        self(change)
+    }
+}
+
+impl<IS, M, P, S> AbstractNameDag<IdDag<IS>, M, P, S>
+where
+    IS: IdDagStore,
+    IdDag<IS>: TryClone,
+    M: TryClone + IdConvert + Send + Sync + 'static,
+    P: TryClone + Send + Sync + 'static,
+    S: TryClone + Send + Sync + 'static,
+{
+    /// Call `listener` when vertexes are added or removed.
+    ///
+    /// `add_heads`, `add_heads_and_flush`, `import_pull_data`, `strip`, and
+    /// committed transactions notify listeners. `flush` does not, since it
+    /// only writes vertexes that were already reported by `add_heads`.
+    /// Changes made by other processes, picked up by `flush` or
+    /// `try_reload`, are not reported.
+    ///
+    /// Listeners are kept across `flush`, `strip`, and `try_reload`.
+    pub fn subscribe(&mut self, listener: Arc<dyn DagChangeListener>) {
+        self.change_listeners.push(listener);
+    }
+
+    /// Heads in `heads` that are not in the graph.
+    ///
+    /// Return an empty list if there are no listeners.
+    pub(crate) async fn new_heads_to_notify(
+        &self,
+        heads: &VertexListWithOptions,
+    ) -> Result<Vec<VertexName>> {
+        if self.change_listeners.is_empty() {
+            return Ok(Vec::new());
+        }
+        let heads = heads.vertexes();
+        let exists = self.contains_vertex_name_locally(&heads).await?;
+        let new_heads = heads
+            .into_iter()
+            .zip(exists)
+            .filter_map(|(v, exists)| if exists { None } else { Some(v) })
+            .collect();
+        Ok(new_heads)
+    }
+
+    /// Heads of vertexes that `strip(set)` would remove.
+    ///
+    /// Return an empty list if there are no listeners.
+    pub(crate) async fn stripped_heads_to_notify(&self, set: &NameSet) -> Result<Vec<VertexName>> {
+        if self.change_listeners.is_empty() {
+            return Ok(Vec::new());
+        }
+        let heads = self.heads(self.descendants(set.clone()).await?).await?;
+        heads.iter().await?.try_collect().await
+    }
+
+    /// Call listeners with `change`, unless it is empty.
+    pub(crate) fn notify_change(&self, change: &DagChange) {
+        if change.is_empty() {
+            return;
+        }
+        tracing::debug!(target: "dag::notify", "{:?}", change);
+        for listener in &self.change_listeners {
+            listener.on_change(change);
+        }
+    }
+}
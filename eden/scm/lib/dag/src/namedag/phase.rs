@@ -0,0 +1,214 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::AbstractNameDag;
+use crate::iddag::IdDag;
+use crate::iddag::IdDagAlgorithm;
+use crate::iddagstore::IdDagStore;
+use crate::nameset::hints::Flags;
+use crate::ops::DagAlgorithm;
+use crate::ops::IdConvert;
+use crate::ops::IntVersion;
+use crate::ops::TryClone;
+use crate::Group;
+use crate::IdSet;
+use crate::NameSet;
+use crate::Result;
+use crate::VerLink;
+use crate::VertexName;
+
+/// Tracks public vertexes of a `NameDag`. Vertexes that are not public are
+/// draft.
+///
+/// Public vertexes are `ancestors(public heads)`. Heads are stored as names
+/// so they survive `flush` reassigning non-master ids. The calculated spans
+/// are cached per dag version.
+///
+/// For an on-disk `NameDag`, the heads and spans are also written next to
+/// other dag state on flush, and loaded on open.
+#[derive(Clone, Default)]
+pub(crate) struct PhaseOverlay {
+    /// Heads of public vertexes. `None` means public heads are not set.
+    heads: Option<Vec<VertexName>>,
+
+    /// `ancestors(heads)` calculated for a dag version.
+    public: Arc<RwLock<Option<(VerLink, IdSet)>>>,
+
+    /// Where the heads and spans are persisted. `None` for in-memory dags.
+    path: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedPhase {
+    /// `int_version` of the dag state the spans were calculated for.
+    version: (u64, u64),
+    heads: Vec<VertexName>,
+    public: IdSet,
+}
+
+impl PhaseOverlay {
+    /// Load heads and spans persisted at `path`. The spans are only reused
+    /// if they were calculated for the on-disk `version`. Otherwise they are
+    /// calculated again from the heads.
+    pub(crate) fn load(path: PathBuf, version: (u64, u64), dag_version: &VerLink) -> Self {
+        let persisted: Option<PersistedPhase> = fs::read(&path)
+            .ok()
+            .and_then(|data| mincode::deserialize(&data).ok());
+        let phase = Self {
+            path: Some(path),
+            ..Default::default()
+        };
+        match persisted {
+            None => phase,
+            Some(persisted) => {
+                if persisted.version == version {
+                    *phase.public.write().unwrap() = Some((dag_version.clone(), persisted.public));
+                }
+                Self {
+                    heads: Some(persisted.heads),
+                    ..phase
+                }
+            }
+        }
+    }
+}
+
+impl<IS, M, P, S> AbstractNameDag<IdDag<IS>, M, P, S>
+where
+    IS: IdDagStore,
+    IdDag<IS>: TryClone + 'static,
+    M: TryClone + IdConvert + Sync + Send + 'static,
+    P: TryClone + Sync + Send + 'static,
+    S: TryClone + Sync + Send + 'static,
+{
+    /// Set heads of public vertexes. `public()` will return their ancestors.
+    ///
+    /// Heads that are not in the graph are ignored. Heads are kept across
+    /// `flush`, `strip`, and `try_reload`, and `public()` is updated
+    /// accordingly. For an on-disk `NameDag`, heads are written to disk on
+    /// the next `flush`.
+    ///
+    /// Pass `None` to stop tracking public heads. Then vertexes in the
+    /// `MASTER` group are considered public.
+    pub fn set_public_heads(&mut self, heads: Option<Vec<VertexName>>) {
+        self.phase = PhaseOverlay {
+            heads,
+            public: Default::default(),
+            path: self.phase.path.clone(),
+        };
+    }
+
+    /// Public vertexes.
+    pub async fn public(&self) -> Result<NameSet> {
+        let spans = self.public_id_set().await?;
+        let result = NameSet::from_spans_dag(spans, self)?;
+        result.hints().add_flags(Flags::ANCESTORS);
+        Ok(result)
+    }
+
+    /// Draft vertexes. That is, vertexes that are not public.
+    pub async fn draft(&self) -> Result<NameSet> {
+        let spans = self.dag().all()?.difference(&self.public_id_set().await?);
+        NameSet::from_spans_dag(spans, self)
+    }
+
+    async fn public_id_set(&self) -> Result<IdSet> {
+        let heads = match &self.phase.heads {
+            None => return self.dag().master_group(),
+            Some(heads) => heads,
+        };
+        if let Some((version, spans)) = &*self.phase.public.read().unwrap() {
+            if version == self.dag_version() {
+                return Ok(spans.clone());
+            }
+        }
+
+        let mut head_ids = Vec::with_capacity(heads.len());
+        for head in heads {
+            if let Some(id) = self.vertex_id_optional(head).await? {
+                head_ids.push(id);
+            }
+        }
+        let spans = self.dag().ancestors(IdSet::from_spans(head_ids))?;
+        tracing::debug!(target: "dag::phase", "public: {:?}", &spans);
+        *self.phase.public.write().unwrap() = Some((self.dag_version().clone(), spans.clone()));
+        Ok(spans)
+    }
+}
+
+impl<IS, M, P, S> AbstractNameDag<IdDag<IS>, M, P, S>
+where
+    IS: IdDagStore,
+    M: IdConvert,
+    S: IntVersion,
+{
+    /// Write public heads and `ancestors(public heads)` next to other dag
+    /// state. Called with the dag locked after ids are assigned, so the
+    /// spans match the on-disk graph.
+    pub(crate) async fn persist_phase(&self) -> Result<()> {
+        let path = match &self.phase.path {
+            None => return Ok(()),
+            Some(path) => path,
+        };
+        let heads = match &self.phase.heads {
+            None => {
+                return match fs::remove_file(path) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+                    _ => Ok(()),
+                };
+            }
+            Some(heads) => heads.clone(),
+        };
+
+        let mut head_ids = Vec::with_capacity(heads.len());
+        for head in &heads {
+            if let Some(id) = self
+                .map
+                .vertex_id_with_max_group(head, Group::NON_MASTER)
+                .await?
+            {
+                head_ids.push(id);
+            }
+        }
+        let public = self.dag.ancestors(IdSet::from_spans(head_ids))?;
+        *self.phase.public.write().unwrap() = Some((self.dag.version().clone(), public.clone()));
+
+        let persisted = PersistedPhase {
+            version: self.state.int_version(),
+            heads,
+            public,
+        };
+        let data = mincode::serialize(&persisted)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        write_atomic(path, &data)
+    }
+}
+
+#[cfg(any(test, feature = "indexedlog-backend"))]
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    indexedlog::utils::atomic_write_plain(path, data, false)?;
+    Ok(())
+}
+
+/// Only `NameDag`, which requires indexedlog, loads phases from a path.
+#[cfg(not(any(test, feature = "indexedlog-backend")))]
+fn write_atomic(path: &Path, _data: &[u8]) -> Result<()> {
+    crate::errors::programming(format!(
+        "phases cannot be written to {:?} without indexedlog",
+        path
+    ))
+}
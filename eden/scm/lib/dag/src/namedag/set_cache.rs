@@ -0,0 +1,119 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::NameDag;
+use crate::ops::IntVersion;
+use crate::ops::ToIdSet;
+use crate::ops::ToSet;
+use crate::IdSet;
+use crate::NameSet;
+use crate::Result;
+
+/// On-disk cache for expensive sets derived from a [`NameDag`], like
+/// `ancestors(master)`.
+///
+/// Entries are keyed by a query signature picked by the caller, and the
+/// on-disk version of the `NameDag`. Flushing or stripping changes the
+/// version, so outdated entries are ignored and replaced.
+///
+/// Sets are stored as `IdSet`s. So loading a cached set is cheap even if
+/// the set is huge. Each entry is stored in a file named by the hash of
+/// its signature, so long signatures do not exceed file name limits.
+/// The signature is also stored in the entry to detect hash collisions.
+pub struct DerivedSetCache {
+    dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    signature: String,
+    version: (u64, u64),
+    spans: IdSet,
+}
+
+impl DerivedSetCache {
+    /// Store cached sets in `dir`. The directory is created on demand.
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Get the set for `signature` from cache, or calculate it using
+    /// `calculate` and store it in cache.
+    ///
+    /// `signature` should identify the query that `calculate` runs, for
+    /// example, `"ancestors(master)"`.
+    ///
+    /// If `dag` has in-memory changes, or is not backed by the filesystem,
+    /// the cache is bypassed.
+    pub async fn get_or_calculate(
+        &self,
+        dag: &NameDag,
+        signature: &str,
+        calculate: impl Future<Output = Result<NameSet>>,
+    ) -> Result<NameSet> {
+        let version = dag.state.int_version();
+        if !dag.pending_heads.is_empty() || version == (0, 0) {
+            return calculate.await;
+        }
+
+        let path = self
+            .dir
+            .join(format!("{:016x}", indexedlog::utils::xxhash(signature)));
+        if let Some(spans) = read_entry(&path, signature, version) {
+            tracing::debug!(target: "dag::setcache", "hit {:?}", signature);
+            return dag.to_set(&spans);
+        }
+
+        tracing::debug!(target: "dag::setcache", "miss {:?}", signature);
+        let set = calculate.await?;
+        let spans = dag.to_id_set(&set).await?;
+        if let Err(err) = self.write_entry(&path, signature, version, spans) {
+            tracing::warn!(target: "dag::setcache", "cannot write {}: {}", path.display(), err);
+        }
+        Ok(set)
+    }
+
+    fn write_entry(
+        &self,
+        path: &Path,
+        signature: &str,
+        version: (u64, u64),
+        spans: IdSet,
+    ) -> Result<()> {
+        let entry = Entry {
+            signature: signature.to_string(),
+            version,
+            spans,
+        };
+        let data = mincode::serialize(&entry)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        fs::create_dir_all(&self.dir)?;
+        indexedlog::utils::atomic_write_plain(path, &data, false)?;
+        Ok(())
+    }
+}
+
+/// Read spans from `path` if it matches `signature` and `version`.
+fn read_entry(path: &Path, signature: &str, version: (u64, u64)) -> Option<IdSet> {
+    let data = fs::read(path).ok()?;
+    let entry: Entry = mincode::deserialize(&data).ok()?;
+    if entry.signature == signature && entry.version == version {
+        Some(entry.spans)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,137 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use crate::errors::programming;
+use crate::iddagstore::IdDagStore;
+use crate::idmap::IdMapAssignHead;
+use crate::namedag::AbstractNameDag;
+use crate::namedag::DagChange;
+use crate::nameset::NameSet;
+use crate::ops::IntVersion;
+use crate::ops::Open;
+use crate::ops::Parents;
+use crate::ops::Persist;
+use crate::ops::TryClone;
+use crate::IdDag;
+use crate::Result;
+use crate::VertexListWithOptions;
+
+/// Staged mutations to an `AbstractNameDag`.
+///
+/// Created by [`AbstractNameDag::transaction`]. Changes are applied to a
+/// copy of the graph that is reloaded from disk, with the on-disk locks held
+/// until the transaction ends. [`NameDagTransaction::commit`] writes the
+/// changes to disk and replaces the original graph. Dropping the
+/// transaction without committing discards the changes and leaves both the
+/// original graph and the on-disk data untouched.
+pub struct NameDagTransaction<'a, IS, M, P, S>
+where
+    IS: IdDagStore + Persist,
+    M: Persist + Send + Sync,
+    P: Send + Sync,
+    S: Persist + Send + Sync,
+{
+    dag: &'a mut AbstractNameDag<IdDag<IS>, M, P, S>,
+    new: AbstractNameDag<IdDag<IS>, M, P, S>,
+    lock: S::Lock,
+    map_lock: M::Lock,
+    dag_lock: IS::Lock,
+    change: DagChange,
+}
+
+impl<IS, M, P, S> AbstractNameDag<IdDag<IS>, M, P, S>
+where
+    IS: IdDagStore + Persist + 'static,
+    IdDag<IS>: TryClone + 'static,
+    M: TryClone + IdMapAssignHead + Persist + Send + Sync + 'static,
+    P: TryClone + Open<OpenTarget = Self> + Send + Sync + 'static,
+    S: TryClone + IntVersion + Persist + Send + Sync + 'static,
+{
+    /// Start a transaction. Mutations made via the returned
+    /// [`NameDagTransaction`] are written to disk together on `commit`,
+    /// or discarded on drop.
+    ///
+    /// The on-disk locks are held until the transaction ends. Other
+    /// writers will be blocked during the time.
+    pub fn transaction(&mut self) -> Result<NameDagTransaction<'_, IS, M, P, S>> {
+        if !self.pending_heads.is_empty() {
+            return programming(format!(
+                "transaction does not support pending heads ({:?})",
+                &self.pending_heads.vertexes(),
+            ));
+        }
+
+        let mut new: Self = self.path.open()?;
+        let (lock, map_lock, dag_lock) = new.reload()?;
+        new.copy_settings_from(self);
+
+        Ok(NameDagTransaction {
+            dag: self,
+            new,
+            lock,
+            map_lock,
+            dag_lock,
+            change: Default::default(),
+        })
+    }
+}
+
+impl<'a, IS, M, P, S> NameDagTransaction<'a, IS, M, P, S>
+where
+    IS: IdDagStore + Persist + 'static,
+    IdDag<IS>: TryClone + 'static,
+    M: TryClone + IdMapAssignHead + Persist + Send + Sync + 'static,
+    P: TryClone + Open<OpenTarget = AbstractNameDag<IdDag<IS>, M, P, S>> + Send + Sync + 'static,
+    S: TryClone + IntVersion + Persist + Send + Sync + 'static,
+{
+    /// Stage adding vertexes and their ancestors.
+    ///
+    /// Unlike `add_heads`, `heads` are assigned to groups according to their
+    /// `VertexOptions` right away, similar to `add_heads_and_flush`.
+    pub async fn add_heads(
+        &mut self,
+        parents: &dyn Parents,
+        heads: &VertexListWithOptions,
+    ) -> Result<()> {
+        self.new.invalidate_snapshot();
+        let added_heads = self.new.new_heads_to_notify(heads).await?;
+        self.new
+            .build_with_lock(parents, heads, &self.map_lock)
+            .await?;
+        self.change.added_heads.extend(added_heads);
+        Ok(())
+    }
+
+    /// Stage removing vertexes and their descendants.
+    pub async fn strip(&mut self, set: &NameSet) -> Result<()> {
+        let removed_heads = self.new.stripped_heads_to_notify(set).await?;
+        self.new.strip_with_lock(set, &self.map_lock).await?;
+        self.change.removed_heads.extend(removed_heads);
+        Ok(())
+    }
+
+    /// The graph with staged changes applied. Useful for queries.
+    pub fn staged(&self) -> &AbstractNameDag<IdDag<IS>, M, P, S> {
+        &self.new
+    }
+
+    /// Write staged changes to disk and update the original graph.
+    pub fn commit(self) -> Result<()> {
+        let Self {
+            dag,
+            mut new,
+            lock,
+            map_lock,
+            dag_lock,
+            change,
+        } = self;
+        new.persist(lock, map_lock, dag_lock)?;
+        *dag = new;
+        dag.notify_change(&change);
+        Ok(())
+    }
+}
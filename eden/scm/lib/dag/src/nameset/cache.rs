@@ -0,0 +1,143 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+use futures::StreamExt;
+
+use super::BoxVertexStream;
+use crate::Result;
+use crate::VertexName;
+
+/// Cached iteration, `count`, `first`, `last` results for sets that
+/// calculate them by iteration.
+///
+/// Sets are immutable. So the results can be reused once calculated.
+/// The first complete iteration is recorded. Later iterations, and
+/// `count`, `first`, `last`, are then served from the recorded names.
+#[derive(Default)]
+pub(crate) struct QueryCache {
+    names: Arc<RwLock<Option<Arc<Vec<VertexName>>>>>,
+    count: RwLock<Option<usize>>,
+    first: RwLock<Option<Option<VertexName>>>,
+    last: RwLock<Option<Option<VertexName>>>,
+}
+
+impl QueryCache {
+    /// Iterate from the recorded names, or use `calculate` and record the
+    /// names once the returned stream is fully consumed without errors.
+    pub(crate) async fn iter(
+        &self,
+        calculate: impl Future<Output = Result<BoxVertexStream>>,
+    ) -> Result<BoxVertexStream> {
+        if let Some(names) = self.names() {
+            return Ok(names_stream(names, false));
+        }
+        let iter = calculate.await?;
+        let state = (iter, Vec::new(), Some(self.names.clone()));
+        let stream = futures::stream::unfold(state, |(mut iter, mut names, cache)| async move {
+            match iter.next().await {
+                Some(Ok(name)) => {
+                    if cache.is_some() {
+                        names.push(name.clone());
+                    }
+                    Some((Ok(name), (iter, names, cache)))
+                }
+                // Do not record a partial iteration.
+                Some(Err(err)) => Some((Err(err), (iter, Vec::new(), None))),
+                None => {
+                    if let Some(cache) = cache {
+                        *cache.write().unwrap() = Some(Arc::new(names));
+                    }
+                    None
+                }
+            }
+        });
+        Ok(Box::pin(stream))
+    }
+
+    /// Iterate in reversed order from the recorded names, or use `calculate`.
+    pub(crate) async fn iter_rev(
+        &self,
+        calculate: impl Future<Output = Result<BoxVertexStream>>,
+    ) -> Result<BoxVertexStream> {
+        match self.names() {
+            Some(names) => Ok(names_stream(names, true)),
+            None => calculate.await,
+        }
+    }
+
+    pub(crate) async fn count(
+        &self,
+        calculate: impl Future<Output = Result<usize>>,
+    ) -> Result<usize> {
+        if let Some(names) = self.names() {
+            return Ok(names.len());
+        }
+        get_or_calculate(&self.count, calculate).await
+    }
+
+    pub(crate) async fn first(
+        &self,
+        calculate: impl Future<Output = Result<Option<VertexName>>>,
+    ) -> Result<Option<VertexName>> {
+        if let Some(names) = self.names() {
+            return Ok(names.first().cloned());
+        }
+        get_or_calculate(&self.first, calculate).await
+    }
+
+    pub(crate) async fn last(
+        &self,
+        calculate: impl Future<Output = Result<Option<VertexName>>>,
+    ) -> Result<Option<VertexName>> {
+        if let Some(names) = self.names() {
+            return Ok(names.last().cloned());
+        }
+        get_or_calculate(&self.last, calculate).await
+    }
+
+    fn names(&self) -> Option<Arc<Vec<VertexName>>> {
+        self.names.read().unwrap().clone()
+    }
+}
+
+/// Count items by consuming a stream.
+pub(crate) async fn count_iter(
+    iter: impl Future<Output = Result<BoxVertexStream>>,
+) -> Result<usize> {
+    let mut iter = iter.await?;
+    let mut count = 0;
+    while let Some(item) = iter.next().await {
+        item?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn names_stream(names: Arc<Vec<VertexName>>, reversed: bool) -> BoxVertexStream {
+    let len = names.len();
+    let iter = (0..len).map(move |i| {
+        let i = if reversed { len - 1 - i } else { i };
+        Ok::<_, crate::Error>(names[i].clone())
+    });
+    Box::pin(futures::stream::iter(iter))
+}
+
+async fn get_or_calculate<T: Clone>(
+    cache: &RwLock<Option<T>>,
+    calculate: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    if let Some(value) = &*cache.read().unwrap() {
+        return Ok(value.clone());
+    }
+    let value = calculate.await?;
+    *cache.write().unwrap() = Some(value.clone());
+    Ok(value)
+}
@@ -10,6 +10,9 @@ use std::fmt;
 
 use futures::StreamExt;
 
+use super::cache::count_iter;
+use super::cache::QueryCache;
+use super::contains_selected;
 use super::hints::Flags;
 use super::AsyncNameSetQuery;
 use super::BoxVertexStream;
@@ -26,6 +29,7 @@ pub struct DifferenceSet {
     lhs: NameSet,
     rhs: NameSet,
     hints: Hints,
+    cache: QueryCache,
 }
 
 struct Iter {
@@ -36,7 +40,7 @@ struct Iter {
 impl DifferenceSet {
     pub fn new(lhs: NameSet, rhs: NameSet) -> Self {
         let hints = Hints::new_inherit_idmap_dag(lhs.hints());
-        // Inherit flags, min/max Ids from lhs.
+        // Inherit flags, min/max Ids and depths from lhs.
         hints.add_flags(
             lhs.hints().flags()
                 & (Flags::EMPTY
@@ -51,32 +55,69 @@ impl DifferenceSet {
         if let Some(id) = lhs.hints().max_id() {
             hints.set_max_id(id);
         }
-        Self { lhs, rhs, hints }
+        if let Some(depth) = lhs.hints().min_depth() {
+            hints.set_min_depth(depth);
+        }
+        if let Some(depth) = lhs.hints().max_depth() {
+            hints.set_max_depth(depth);
+        }
+        Self {
+            lhs,
+            rhs,
+            hints,
+            cache: Default::default(),
+        }
     }
 }
 
 #[async_trait::async_trait]
 impl AsyncNameSetQuery for DifferenceSet {
     async fn iter(&self) -> Result<BoxVertexStream> {
-        let iter = Iter {
-            iter: self.lhs.iter().await?,
-            rhs: self.rhs.clone(),
+        let iter = async {
+            let iter = Iter {
+                iter: self.lhs.iter().await?,
+                rhs: self.rhs.clone(),
+            };
+            Ok::<_, crate::Error>(iter.into_stream())
         };
-        Ok(iter.into_stream())
+        self.cache.iter(iter).await
     }
 
     async fn iter_rev(&self) -> Result<BoxVertexStream> {
-        let iter = Iter {
-            iter: self.lhs.iter_rev().await?,
-            rhs: self.rhs.clone(),
+        let iter = async {
+            let iter = Iter {
+                iter: self.lhs.iter_rev().await?,
+                rhs: self.rhs.clone(),
+            };
+            Ok::<_, crate::Error>(iter.into_stream())
         };
-        Ok(iter.into_stream())
+        self.cache.iter_rev(iter).await
+    }
+
+    async fn count(&self) -> Result<usize> {
+        self.cache.count(count_iter(self.iter())).await
+    }
+
+    async fn first(&self) -> Result<Option<VertexName>> {
+        let first = async { self.iter().await?.next().await.transpose() };
+        self.cache.first(first).await
+    }
+
+    async fn last(&self) -> Result<Option<VertexName>> {
+        let last = async { self.iter_rev().await?.next().await.transpose() };
+        self.cache.last(last).await
     }
 
     async fn contains(&self, name: &VertexName) -> Result<bool> {
         Ok(self.lhs.contains(name).await? && !self.rhs.contains(name).await?)
     }
 
+    async fn contains_all(&self, names: &[VertexName]) -> Result<Vec<bool>> {
+        let lhs = self.lhs.contains_all(names).await?;
+        let rhs = contains_selected(&self.rhs, names, &lhs).await?;
+        Ok(lhs.into_iter().zip(rhs).map(|(l, r)| l && !r).collect())
+    }
+
     async fn contains_fast(&self, name: &VertexName) -> Result<Option<bool>> {
         let lhs_contains = self.lhs.contains_fast(name).await?;
         if lhs_contains == Some(false) {
@@ -134,6 +175,10 @@ impl Iter {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::sync::Arc;
+
     use nonblocking::non_blocking as nb;
 
     use super::super::tests::*;
@@ -164,6 +209,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_difference_cached_count_first_last() -> Result<()> {
+        let lhs = NameSet::from_query(VecQuery::from_bytes(b"\x11\x22\x33"));
+        let checked = Arc::new(AtomicUsize::new(0));
+        let rhs = lhs.filter({
+            let checked = checked.clone();
+            Box::new(move |name: &VertexName| {
+                checked.fetch_add(1, SeqCst);
+                let is_22 = name == &to_name(0x22);
+                Box::pin(async move { Ok(is_22) })
+            })
+        });
+        let set = DifferenceSet::new(lhs, rhs);
+
+        assert_eq!(nb(set.count())??, 2);
+        let checked_after_count = checked.load(SeqCst);
+        assert_eq!(nb(set.count())??, 2);
+        assert_eq!(checked.load(SeqCst), checked_after_count);
+
+        assert_eq!(shorten_name(nb(set.first())??.unwrap()), "11");
+        assert_eq!(shorten_name(nb(set.last())??.unwrap()), "33");
+        let checked_after_first_last = checked.load(SeqCst);
+        assert_eq!(shorten_name(nb(set.first())??.unwrap()), "11");
+        assert_eq!(shorten_name(nb(set.last())??.unwrap()), "33");
+        assert_eq!(checked.load(SeqCst), checked_after_first_last);
+        Ok(())
+    }
+
     quickcheck::quickcheck! {
         fn test_difference_quickcheck(a: Vec<u8>, b: Vec<u8>) -> bool {
             let set = difference(&a, &b);
@@ -49,6 +49,12 @@ bitflags! {
 
         /// The set contains ancestors. If X in set, any ancestor of X is also in set.
         const ANCESTORS = 0x100;
+
+        /// Minimal depth (generation number) is known.
+        const HAS_MIN_DEPTH = 0x200;
+
+        /// Maximum depth (generation number) is known.
+        const HAS_MAX_DEPTH = 0x400;
     }
 }
 
@@ -59,6 +65,8 @@ pub struct Hints {
     flags: AtomicU32,
     min_id: AtomicU64,
     max_id: AtomicU64,
+    min_depth: AtomicU64,
+    max_depth: AtomicU64,
     id_map: IdMapSnapshot,
     dag: DagSnapshot,
 }
@@ -142,6 +150,24 @@ impl Hints {
         }
     }
 
+    /// Lower bound of depths of vertexes in the set.
+    pub fn min_depth(&self) -> Option<u64> {
+        if self.contains(Flags::HAS_MIN_DEPTH) {
+            Some(self.min_depth.load(Acquire))
+        } else {
+            None
+        }
+    }
+
+    /// Upper bound of depths of vertexes in the set.
+    pub fn max_depth(&self) -> Option<u64> {
+        if self.contains(Flags::HAS_MAX_DEPTH) {
+            Some(self.max_depth.load(Acquire))
+        } else {
+            None
+        }
+    }
+
     pub fn update_flags_with(&self, func: impl Fn(Flags) -> Flags) -> &Self {
         let mut flags = func(self.flags());
         // Automatically add "derived" flags.
@@ -177,6 +203,42 @@ impl Hints {
         self
     }
 
+    pub fn set_min_depth(&self, min_depth: u64) -> &Self {
+        self.min_depth.store(min_depth, Release);
+        self.add_flags(Flags::HAS_MIN_DEPTH);
+        self
+    }
+
+    pub fn set_max_depth(&self, max_depth: u64) -> &Self {
+        self.max_depth.store(max_depth, Release);
+        self.add_flags(Flags::HAS_MAX_DEPTH);
+        self
+    }
+
+    /// Set depth bounds for the union of sets using `hints_list`.
+    pub(crate) fn union_depth(&self, hints_list: &[&Hints]) -> &Self {
+        let min_depths: Option<Vec<u64>> = hints_list.iter().map(|h| h.min_depth()).collect();
+        if let Some(depth) = min_depths.and_then(|ds| ds.into_iter().min()) {
+            self.set_min_depth(depth);
+        }
+        let max_depths: Option<Vec<u64>> = hints_list.iter().map(|h| h.max_depth()).collect();
+        if let Some(depth) = max_depths.and_then(|ds| ds.into_iter().max()) {
+            self.set_max_depth(depth);
+        }
+        self
+    }
+
+    /// Set depth bounds for the intersection of sets using `hints_list`.
+    pub(crate) fn intersect_depth(&self, hints_list: &[&Hints]) -> &Self {
+        if let Some(depth) = hints_list.iter().filter_map(|h| h.min_depth()).max() {
+            self.set_min_depth(depth);
+        }
+        if let Some(depth) = hints_list.iter().filter_map(|h| h.max_depth()).min() {
+            self.set_max_depth(depth);
+        }
+        self
+    }
+
     pub fn inherit_flags_min_max_id(&self, other: &Hints) -> &Self {
         self.update_flags_with(|_| other.flags());
         if let Some(id) = other.min_id() {
@@ -185,6 +247,12 @@ impl Hints {
         if let Some(id) = other.max_id() {
             self.set_max_id(id);
         }
+        if let Some(depth) = other.min_depth() {
+            self.set_min_depth(depth);
+        }
+        if let Some(depth) = other.max_depth() {
+            self.set_max_depth(depth);
+        }
         self
     }
 
@@ -279,6 +347,8 @@ impl Clone for Hints {
             flags: AtomicU32::new(self.flags.load(Acquire)),
             min_id: AtomicU64::new(self.min_id.load(Acquire)),
             max_id: AtomicU64::new(self.max_id.load(Acquire)),
+            min_depth: AtomicU64::new(self.min_depth.load(Acquire)),
+            max_depth: AtomicU64::new(self.max_depth.load(Acquire)),
             id_map: self.id_map.clone(),
             dag: self.dag.clone(),
         }
@@ -290,7 +360,11 @@ impl fmt::Debug for Hints {
         write!(
             f,
             "Hints({:?}",
-            self.flags() - (Flags::HAS_MIN_ID | Flags::HAS_MAX_ID)
+            self.flags()
+                - (Flags::HAS_MIN_ID
+                    | Flags::HAS_MAX_ID
+                    | Flags::HAS_MIN_DEPTH
+                    | Flags::HAS_MAX_DEPTH)
         )?;
         match (self.min_id(), self.max_id()) {
             (Some(min), Some(max)) => write!(f, ", {}..={}", min.0, max.0)?,
@@ -34,6 +34,14 @@ pub struct IdStaticSet {
     hints: Hints,
 }
 
+/// Number of ids to translate at once during iteration, if the ids are
+/// known locally.
+const LOCAL_BATCH_SIZE: usize = 256;
+
+/// Number of ids to translate at once during iteration, if the ids might
+/// need to be resolved remotely.
+const REMOTE_BATCH_SIZE: usize = 131072;
+
 struct Iter {
     iter: IdSetIter<IdSet>,
     map: Arc<dyn IdConvert + Send + Sync>,
@@ -63,37 +71,39 @@ impl Iter {
                     .contains_vertex_id_locally(&[id])
                     .await
                     .unwrap_or_default();
-                if contains == &[true] {
-                    Some((map.vertex_name(id).await, self))
+                // Translate ids in batch to avoid one lookup per item.
+                // If `id` is lazy, use a larger batch to reduce remote
+                // round-trips.
+                let batch_size = if contains == [true] {
+                    LOCAL_BATCH_SIZE
                 } else {
-                    // On demand prefetch in batch.
-                    let batch_size = 131072;
-                    let mut ids = Vec::with_capacity(batch_size);
-                    ids.push(id);
-                    for _ in ids.len()..batch_size {
-                        if let Some(id) = if self.reversed {
-                            self.iter.next_back()
-                        } else {
-                            self.iter.next()
-                        } {
-                            ids.push(id);
-                        } else {
-                            break;
-                        }
-                    }
-                    ids.reverse();
-                    self.buf = match self.map.vertex_name_batch(&ids).await {
-                        Err(e) => return Some((Err(e), self)),
-                        Ok(names) => names,
-                    };
-                    if self.buf.len() != ids.len() {
-                        let result =
-                            crate::errors::bug("vertex_name_batch does not return enough items");
-                        return Some((result, self));
+                    REMOTE_BATCH_SIZE
+                };
+                let mut ids = Vec::with_capacity(batch_size);
+                ids.push(id);
+                for _ in ids.len()..batch_size {
+                    if let Some(id) = if self.reversed {
+                        self.iter.next_back()
+                    } else {
+                        self.iter.next()
+                    } {
+                        ids.push(id);
+                    } else {
+                        break;
                     }
-                    let name = self.buf.pop().expect("buf is not empty");
-                    Some((name, self))
                 }
+                ids.reverse();
+                self.buf = match self.map.vertex_name_batch(&ids).await {
+                    Err(e) => return Some((Err(e), self)),
+                    Ok(names) => names,
+                };
+                if self.buf.len() != ids.len() {
+                    let result =
+                        crate::errors::bug("vertex_name_batch does not return enough items");
+                    return Some((result, self));
+                }
+                let name = self.buf.pop().expect("buf is not empty");
+                Some((name, self))
             }
         }
     }
@@ -180,6 +190,16 @@ impl IdStaticSet {
             dag,
         }
     }
+
+    /// The `Id`s in this set.
+    pub fn spans(&self) -> &IdSet {
+        &self.spans
+    }
+
+    /// The `IdMap` used to interpret `Id`s in this set.
+    pub fn id_map(&self) -> &Arc<dyn IdConvert + Send + Sync> {
+        &self.map
+    }
 }
 
 #[async_trait::async_trait]
@@ -248,6 +268,24 @@ impl AsyncNameSetQuery for IdStaticSet {
         Ok(result)
     }
 
+    async fn contains_all(&self, names: &[VertexName]) -> Result<Vec<bool>> {
+        // Use the same local lookup as `contains`. Names that are not known
+        // locally are not in `spans`, and are not resolved remotely.
+        let mut result = Vec::with_capacity(names.len());
+        for name in names {
+            let contained = match self
+                .map
+                .vertex_id_with_max_group(name, Group::NON_MASTER)
+                .await?
+            {
+                Some(id) => self.spans.contains(id),
+                None => false,
+            };
+            result.push(contained);
+        }
+        Ok(result)
+    }
+
     async fn contains_fast(&self, name: &VertexName) -> Result<Option<bool>> {
         self.contains(name).await.map(Some)
     }
@@ -303,6 +341,33 @@ pub(crate) mod tests {
         })
     }
 
+    #[test]
+    fn test_to_spans() -> Result<()> {
+        with_dag(|dag| {
+            let abcd = r(dag.ancestors("D".into()))?;
+            let spans = abcd.to_spans(dag).unwrap();
+            assert_eq!(format!("{:?}", &spans), "0..=3");
+            let id_static = abcd.as_any().downcast_ref::<IdStaticSet>().unwrap();
+            assert_eq!(format!("{:?}", id_static.spans()), "0..=3");
+
+            // Incompatible IdMap.
+            let other_dag = crate::MemDag::new();
+            assert!(abcd.to_spans(&other_dag).is_none());
+
+            // Not backed by IdSet.
+            let static_set = NameSet::from_static_names(vec!["A".into()]);
+            assert!(static_set.to_spans(dag).is_none());
+
+            let set = NameSet::from_spans_idmap_dag(
+                spans,
+                id_static.id_map().clone(),
+                id_static.dag.clone(),
+            );
+            assert_eq!(format!("{:?}", &set), "<spans [A:D+0:3]>");
+            Ok(())
+        })
+    }
+
     #[test]
     fn test_dag_fast_paths() -> Result<()> {
         with_dag(|dag| {
@@ -314,6 +379,8 @@ pub(crate) mod tests {
 
             assert!(nb(abcd.contains(&vec![b'A'].into()))?);
             assert!(!nb(abcd.contains(&vec![b'E'].into()))?);
+            let names: Vec<VertexName> = vec!["E".into(), "A".into(), "X".into(), "D".into()];
+            assert_eq!(nb(abcd.contains_all(&names))?, [false, true, false, true]);
 
             // should not be "<and <...> <...>>"
             assert_eq!(format!("{:?}", &ab), "<spans [A:B+0:1]>");
@@ -323,11 +390,22 @@ pub(crate) mod tests {
             // should not be "<or <...> <...>>"
             assert_eq!(format!("{:?}", &abcdefg), "<spans [A:G+0:6]>");
 
+            let abef = r(dag.ancestors("F".into()))?;
+            let union_all = NameSet::union_all(vec![cd.clone(), ab.clone(), abef]);
+            check_invariants(union_all.deref())?;
+            // should not be "<or <...> <...> <...>>"
+            assert_eq!(format!("{:?}", &union_all), "<spans [A:F+0:5]>");
+
             let cd = abcd.difference(&abefg);
             check_invariants(cd.deref())?;
             // should not be "<difference <...> <...>>"
             assert_eq!(format!("{:?}", &cd), "<spans [C:D+2:3]>");
 
+            assert!(nb(ab.is_subset(&abcd))?);
+            assert!(!nb(abcd.is_subset(&ab))?);
+            assert!(nb(abcdefg.is_equal(&(abefg.clone() | cd.clone())))?);
+            assert!(!nb(abcd.is_equal(&abefg))?);
+
             Ok(())
         })
     }
@@ -374,6 +452,10 @@ pub(crate) mod tests {
                 assert_eq!(f(a1() & a2()), "<and <spans [A:G+0:6]> <spans [A:G+0:6]>>");
                 assert_eq!(f(a1() | a2()), "<or <spans [A:G+0:6]> <spans [A:G+0:6]>>");
                 assert_eq!(f(a1() - a2()), "<diff <spans [A:G+0:6]> <spans [A:G+0:6]>>");
+                assert_eq!(
+                    f(NameSet::union_all(vec![a1(), a2(), abcd.clone()])),
+                    "<or <spans [A:G+0:6]> <spans [A:G+0:6]> <spans [A:D+0:3]>>"
+                );
 
                 // No fast path for manually constructed StaticSet either, because
                 // the StaticSets do not have DAG associated to test compatibility.
@@ -11,6 +11,9 @@ use std::fmt;
 
 use futures::StreamExt;
 
+use super::cache::count_iter;
+use super::cache::QueryCache;
+use super::contains_selected;
 use super::hints::Flags;
 use super::AsyncNameSetQuery;
 use super::BoxVertexStream;
@@ -28,6 +31,7 @@ pub struct IntersectionSet {
     lhs: NameSet,
     rhs: NameSet,
     hints: Hints,
+    cache: QueryCache,
 }
 
 struct Iter {
@@ -139,7 +143,17 @@ impl IntersectionSet {
             }
             (None, None) => {}
         }
-        Self { lhs, rhs, hints }
+        if hints.dag_version() >= rhs.hints().dag_version() {
+            hints.intersect_depth(&[lhs.hints(), rhs.hints()]);
+        } else {
+            hints.intersect_depth(&[lhs.hints()]);
+        }
+        Self {
+            lhs,
+            rhs,
+            hints,
+            cache: Default::default(),
+        }
     }
 
     fn is_rhs_id_map_comapatible(&self) -> bool {
@@ -176,13 +190,16 @@ impl AsyncNameSetQuery for IntersectionSet {
             None
         };
 
-        let iter = Iter {
-            iter: self.lhs.iter().await?,
-            rhs: self.rhs.clone(),
-            ended: false,
-            stop_condition,
+        let iter = async {
+            let iter = Iter {
+                iter: self.lhs.iter().await?,
+                rhs: self.rhs.clone(),
+                ended: false,
+                stop_condition,
+            };
+            Ok::<_, crate::Error>(iter.into_stream())
         };
-        Ok(iter.into_stream())
+        self.cache.iter(iter).await
     }
 
     async fn iter_rev(&self) -> Result<BoxVertexStream> {
@@ -210,19 +227,41 @@ impl AsyncNameSetQuery for IntersectionSet {
             None
         };
 
-        let iter = Iter {
-            iter: self.lhs.iter_rev().await?,
-            rhs: self.rhs.clone(),
-            ended: false,
-            stop_condition,
+        let iter = async {
+            let iter = Iter {
+                iter: self.lhs.iter_rev().await?,
+                rhs: self.rhs.clone(),
+                ended: false,
+                stop_condition,
+            };
+            Ok::<_, crate::Error>(iter.into_stream())
         };
-        Ok(iter.into_stream())
+        self.cache.iter_rev(iter).await
+    }
+
+    async fn count(&self) -> Result<usize> {
+        self.cache.count(count_iter(self.iter())).await
+    }
+
+    async fn first(&self) -> Result<Option<VertexName>> {
+        let first = async { self.iter().await?.next().await.transpose() };
+        self.cache.first(first).await
+    }
+
+    async fn last(&self) -> Result<Option<VertexName>> {
+        let last = async { self.iter_rev().await?.next().await.transpose() };
+        self.cache.last(last).await
     }
 
     async fn contains(&self, name: &VertexName) -> Result<bool> {
         Ok(self.lhs.contains(name).await? && self.rhs.contains(name).await?)
     }
 
+    async fn contains_all(&self, names: &[VertexName]) -> Result<Vec<bool>> {
+        let lhs = self.lhs.contains_all(names).await?;
+        contains_selected(&self.rhs, names, &lhs).await
+    }
+
     async fn contains_fast(&self, name: &VertexName) -> Result<Option<bool>> {
         for set in &[&self.lhs, &self.rhs] {
             let contains = set.contains_fast(name).await?;
@@ -137,6 +137,22 @@ impl AsyncNameSetQuery for MetaSet {
         }
     }
 
+    async fn contains_all(&self, names: &[VertexName]) -> Result<Vec<bool>> {
+        match self.evaluated() {
+            Some(set) => set.contains_all(names).await,
+            None => match &self.contains {
+                Some(f) => {
+                    let mut result = Vec::with_capacity(names.len());
+                    for name in names {
+                        result.push(f(self, name).await?);
+                    }
+                    Ok(result)
+                }
+                None => self.evaluate().await?.contains_all(names).await,
+            },
+        }
+    }
+
     async fn contains_fast(&self, name: &VertexName) -> Result<Option<bool>> {
         match &self.contains {
             Some(f) => Ok(Some(f(self, name).await?)),
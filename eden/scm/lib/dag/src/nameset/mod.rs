@@ -15,7 +15,9 @@ use std::fmt;
 use std::fmt::Debug;
 use std::ops::BitAnd;
 use std::ops::BitOr;
+use std::ops::BitXor;
 use std::ops::Deref;
+use std::ops::Range;
 use std::ops::Sub;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -35,6 +37,7 @@ use crate::IdSet;
 use crate::Result;
 use crate::VertexName;
 
+mod cache;
 pub mod difference;
 pub mod hints;
 pub mod id_lazy;
@@ -46,6 +49,7 @@ pub mod legacy;
 pub mod meta;
 pub mod slice;
 pub mod r#static;
+pub mod symmetric_difference;
 pub mod union;
 
 use self::hints::Flags;
@@ -206,6 +210,9 @@ impl NameSet {
                     this.map.clone(),
                     this.dag.clone(),
                 );
+                result
+                    .hints()
+                    .intersect_depth(&[AsyncNameSetQuery::hints(this)]);
                 tracing::debug!(
                     "difference(x={:.6?}, y={:.6?}) = {:.6?} (fast path 3)",
                     self,
@@ -263,6 +270,10 @@ impl NameSet {
                     pick(order, &this.map, &other.map).clone(),
                     pick(order, &this.dag, &other.dag).clone(),
                 );
+                result.hints().intersect_depth(&[
+                    AsyncNameSetQuery::hints(this),
+                    AsyncNameSetQuery::hints(other),
+                ]);
                 tracing::debug!(
                     "intersection(x={:.6?}, y={:.6?}) = {:?} (IdStatic fast path)",
                     self,
@@ -309,6 +320,10 @@ impl NameSet {
                     pick(order, &this.map, &other.map).clone(),
                     pick(order, &this.dag, &other.dag).clone(),
                 );
+                result.hints().union_depth(&[
+                    AsyncNameSetQuery::hints(this),
+                    AsyncNameSetQuery::hints(other),
+                ]);
                 tracing::debug!(
                     "union(x={:.6?}, y={:.6?}) = {:.6?} (fast path 3)",
                     self,
@@ -322,6 +337,160 @@ impl NameSet {
         Self::from_query(union::UnionSet::new(self.clone(), other.clone()))
     }
 
+    /// Calculates the union of many sets.
+    ///
+    /// Unlike calling `union` repetitively, this does not create a deep
+    /// chain of union sets, which is slow to iterate.
+    pub fn union_all(sets: Vec<NameSet>) -> NameSet {
+        let mut sets: Vec<NameSet> = sets
+            .into_iter()
+            .filter(|set| !set.hints().contains(Flags::EMPTY))
+            .collect();
+        if sets.len() <= 1 {
+            return sets.pop().unwrap_or_else(Self::empty);
+        }
+
+        // Fast path: all sets are IdStaticSet with compatible IdMaps.
+        let id_sets: Vec<&IdStaticSet> = sets
+            .iter()
+            .filter_map(|set| set.as_any().downcast_ref::<IdStaticSet>())
+            .collect();
+        if id_sets.len() == sets.len() {
+            let mut best = id_sets[0];
+            let mut compatible = true;
+            for &set in &id_sets[1..] {
+                match best.map.map_version().partial_cmp(set.map.map_version()) {
+                    Some(order) => best = pick(order, best, set),
+                    None => {
+                        compatible = false;
+                        break;
+                    }
+                }
+            }
+            if compatible {
+                let spans: Vec<IdSet> = id_sets.iter().map(|set| set.spans.clone()).collect();
+                let result = Self::from_spans_idmap_dag(
+                    IdSet::union_all(&spans),
+                    best.map.clone(),
+                    best.dag.clone(),
+                );
+                let hints_list: Vec<&Hints> = id_sets
+                    .iter()
+                    .map(|set| AsyncNameSetQuery::hints(*set))
+                    .collect();
+                result.hints().union_depth(&hints_list);
+                tracing::debug!(
+                    "union_all({} sets) = {:.6?} (fast path)",
+                    sets.len(),
+                    &result
+                );
+                return result;
+            }
+        }
+
+        // Slow path: iterate each set once.
+        tracing::debug!("union_all({} sets) (slow path)", sets.len());
+        Self::from_query(union::UnionAllSet::new(sets))
+    }
+
+    /// Calculates the subset that is in exactly one of the two sets.
+    pub fn symmetric_difference(&self, other: &NameSet) -> NameSet {
+        if other.hints().contains(Flags::EMPTY) {
+            tracing::debug!(
+                "symmetric_difference(x={:.6?}, y={:.6?}) = x (fast path 1)",
+                self,
+                other
+            );
+            return self.clone();
+        }
+        if self.hints().contains(Flags::EMPTY) {
+            tracing::debug!(
+                "symmetric_difference(x={:.6?}, y={:.6?}) = y (fast path 2)",
+                self,
+                other
+            );
+            return other.clone();
+        }
+        if let (Some(this), Some(other)) = (
+            self.as_any().downcast_ref::<IdStaticSet>(),
+            other.as_any().downcast_ref::<IdStaticSet>(),
+        ) {
+            let order = this.map.map_version().partial_cmp(other.map.map_version());
+            if let Some(order) = order {
+                // Fast path for IdStaticSet
+                let result = Self::from_spans_idmap_dag(
+                    this.spans.symmetric_difference(&other.spans),
+                    pick(order, &this.map, &other.map).clone(),
+                    pick(order, &this.dag, &other.dag).clone(),
+                );
+                tracing::debug!(
+                    "symmetric_difference(x={:.6?}, y={:.6?}) = {:.6?} (fast path 3)",
+                    self,
+                    other,
+                    &result
+                );
+                return result;
+            }
+        }
+        tracing::debug!(
+            "symmetric_difference(x={:.6?}, y={:.6?}) (slow path)",
+            self,
+            other
+        );
+        Self::from_query(symmetric_difference::SymmetricDifferenceSet::new(
+            self.clone(),
+            other.clone(),
+        ))
+    }
+
+    /// Test if all vertexes in this set are also in `other`.
+    pub async fn is_subset(&self, other: &NameSet) -> Result<bool> {
+        if let (Some(this), Some(other)) = (
+            self.as_any().downcast_ref::<IdStaticSet>(),
+            other.as_any().downcast_ref::<IdStaticSet>(),
+        ) {
+            let order = this.map.map_version().partial_cmp(other.map.map_version());
+            if order.is_some() {
+                // Fast path for IdStaticSet
+                let result = this.spans.difference(&other.spans).is_empty();
+                tracing::debug!(
+                    "is_subset(x={:.6?}, y={:.6?}) = {} (fast path)",
+                    self,
+                    other,
+                    result
+                );
+                return Ok(result);
+            }
+        }
+        tracing::debug!("is_subset(x={:.6?}, y={:.6?}) (slow path)", self, other);
+        self.difference(other).is_empty().await
+    }
+
+    /// Test if this set and `other` contain the same vertexes.
+    /// The iteration order is not considered.
+    pub async fn is_equal(&self, other: &NameSet) -> Result<bool> {
+        if let (Some(this), Some(other)) = (
+            self.as_any().downcast_ref::<IdStaticSet>(),
+            other.as_any().downcast_ref::<IdStaticSet>(),
+        ) {
+            let order = this.map.map_version().partial_cmp(other.map.map_version());
+            if order.is_some() {
+                // Fast path for IdStaticSet
+                let result = this.spans.count() == other.spans.count()
+                    && this.spans.difference(&other.spans).is_empty();
+                tracing::debug!(
+                    "is_equal(x={:.6?}, y={:.6?}) = {} (fast path)",
+                    self,
+                    other,
+                    result
+                );
+                return Ok(result);
+            }
+        }
+        tracing::debug!("is_equal(x={:.6?}, y={:.6?}) (slow path)", self, other);
+        Ok(self.is_subset(other).await? && other.is_subset(self).await?)
+    }
+
     /// Filter using the given async function. If `filter_func` returns `true`
     /// for a vertex, then the vertex will be taken, other it will be skipped.
     pub fn filter(
@@ -375,6 +544,59 @@ impl NameSet {
         result
     }
 
+    /// Present a renamed view of the set.
+    ///
+    /// `map_func` translates names in this set to names in the new set.
+    /// `inverse_func` translates names in the new set back, or returns
+    /// `None` if a name cannot be in the new set. The two functions should be
+    /// consistent, and `map_func` should not map different names to a same
+    /// name.
+    ///
+    /// The new set is lazy. `map_func` is called during iteration.
+    /// `contains` uses `inverse_func` and does not iterate the set.
+    ///
+    /// The new set is not associated with a dag or IdMap, so it cannot use
+    /// fast paths based on ids.
+    pub fn map(
+        &self,
+        map_func: impl Fn(VertexName) -> VertexName + Send + Sync + 'static,
+        inverse_func: impl Fn(&VertexName) -> Option<VertexName> + Send + Sync + 'static,
+    ) -> Self {
+        let map_func = Arc::new(map_func);
+        let this = self.clone();
+        let hints = Hints::default();
+        if self.hints().contains(Flags::EMPTY) {
+            hints.add_flags(Flags::EMPTY);
+        }
+        Self::from_async_evaluate_contains(
+            Box::new({
+                let this = this.clone();
+                let hints = hints.clone();
+                move || {
+                    let map_func = map_func.clone();
+                    let this = this.clone();
+                    let hints = hints.clone();
+                    Box::pin(async move {
+                        let stream = this.0.iter().await?;
+                        let stream = stream.map(move |v| v.map(&*map_func));
+                        Ok(Self::from_stream(Box::pin(stream), hints))
+                    })
+                }
+            }),
+            Box::new(move |_, v| {
+                let this = this.clone();
+                let original = inverse_func(v);
+                Box::pin(async move {
+                    match original {
+                        Some(original) => this.0.contains(&original).await,
+                        None => Ok(false),
+                    }
+                })
+            }),
+            hints,
+        )
+    }
+
     /// Convert the set to a graph containing only the vertexes in the set. This can be slow on
     /// larger sets.
     pub async fn to_parents(&self) -> Result<Option<impl Parents>> {
@@ -466,6 +688,23 @@ impl NameSet {
         }
     }
 
+    /// Take items in the `range` of the iteration order.
+    pub fn slice(&self, range: Range<u64>) -> NameSet {
+        let (skip, take) = (range.start, range.end.saturating_sub(range.start));
+        if skip == 0 {
+            return self.take(take);
+        }
+        if let Some(set) = self.as_any().downcast_ref::<IdStaticSet>() {
+            tracing::debug!("slice(x={:.6?}, {:?}) (fast path)", self, range);
+            let spans = set.spans.skip(skip).take(take);
+            Self::from_spans_idmap_dag(spans, set.map.clone(), set.dag.clone())
+        } else {
+            tracing::debug!("slice(x={:.6?}, {:?}) (slow path)", self, range);
+            let set = slice::SliceSet::new(self.clone(), skip, Some(take));
+            Self::from_query(set)
+        }
+    }
+
     /// Converts to `(IdSet, IdConvert)` pair in O(1). If the underlying set
     /// cannot provide such information in O(1), return `None`.
     ///
@@ -476,6 +715,22 @@ impl NameSet {
         let id_set = self.as_any().downcast_ref::<IdStaticSet>()?.spans.clone();
         Some((id_set, id_map))
     }
+
+    /// Converts to `IdSet` in O(1), if the `Id`s in this set can be
+    /// interpreted using `map`. That is, this set is backed by an `IdSet`,
+    /// and `map` is the same as, or an append-only version of, the `IdMap`
+    /// used by this set. Otherwise, return `None`.
+    ///
+    /// Useful for custom algorithms in the `Id` domain. Use
+    /// [`NameSet::from_spans_idmap_dag`] to convert the result back.
+    pub fn to_spans(&self, map: &dyn IdConvert) -> Option<IdSet> {
+        let set = self.as_any().downcast_ref::<IdStaticSet>()?;
+        if set.map.map_version() <= map.map_version() {
+            Some(set.spans.clone())
+        } else {
+            None
+        }
+    }
 }
 
 impl BitAnd for NameSet {
@@ -502,6 +757,14 @@ impl Sub for NameSet {
     }
 }
 
+impl BitXor for NameSet {
+    type Output = Self;
+
+    fn bitxor(self, other: Self) -> Self {
+        self.symmetric_difference(&other)
+    }
+}
+
 impl Deref for NameSet {
     type Target = dyn AsyncNameSetQuery;
 
@@ -572,6 +835,16 @@ pub trait AsyncNameSetQuery: Any + Debug + Send + Sync {
         Ok(false)
     }
 
+    /// Test if this set contains each of the given names.
+    /// The returned `Vec` has the same length and order as `names`.
+    async fn contains_all(&self, names: &[VertexName]) -> Result<Vec<bool>> {
+        let mut result = Vec::with_capacity(names.len());
+        for name in names {
+            result.push(self.contains(name).await?);
+        }
+        Ok(result)
+    }
+
     /// Test contains in less than O(N) time.
     /// Returns None if cannot achieve in less than O(N) time.
     async fn contains_fast(&self, name: &VertexName) -> Result<Option<bool>> {
@@ -614,6 +887,9 @@ pub trait SyncNameSetQuery {
     /// Test if this set contains a given name.
     fn contains(&self, name: &VertexName) -> Result<bool>;
 
+    /// Test if this set contains each of the given names.
+    fn contains_all(&self, names: &[VertexName]) -> Result<Vec<bool>>;
+
     /// For downcasting.
     fn as_any(&self) -> &dyn Any;
 
@@ -653,6 +929,10 @@ impl<T: AsyncNameSetQuery> SyncNameSetQuery for T {
         non_blocking(AsyncNameSetQuery::contains(self, name))?
     }
 
+    fn contains_all(&self, names: &[VertexName]) -> Result<Vec<bool>> {
+        non_blocking(AsyncNameSetQuery::contains_all(self, names))?
+    }
+
     fn as_any(&self) -> &dyn Any {
         AsyncNameSetQuery::as_any(self)
     }
@@ -695,6 +975,10 @@ impl SyncNameSetQuery for NameSet {
         non_blocking(AsyncNameSetQuery::contains(self.0.deref(), name))?
     }
 
+    fn contains_all(&self, names: &[VertexName]) -> Result<Vec<bool>> {
+        non_blocking(AsyncNameSetQuery::contains_all(self.0.deref(), names))?
+    }
+
     fn as_any(&self) -> &dyn Any {
         AsyncNameSetQuery::as_any(self.0.deref())
     }
@@ -735,6 +1019,34 @@ impl Iterator for NonblockingNameIter {
     }
 }
 
+/// Call `set.contains_all` with names that are `selected`.
+///
+/// Return a `Vec` with the same length and order as `names`. Names that
+/// are not selected are `false`. This is used by set combinators to
+/// forward batched `contains_all` calls.
+pub(crate) async fn contains_selected(
+    set: &NameSet,
+    names: &[VertexName],
+    selected: &[bool],
+) -> Result<Vec<bool>> {
+    let selected_names: Vec<VertexName> = names
+        .iter()
+        .zip(selected)
+        .filter_map(|(name, &s)| if s { Some(name.clone()) } else { None })
+        .collect();
+    let mut contained = if selected_names.is_empty() {
+        Vec::new()
+    } else {
+        set.contains_all(&selected_names).await?
+    }
+    .into_iter();
+    let result = selected
+        .iter()
+        .map(|&s| s && contained.next().unwrap_or(false))
+        .collect();
+    Ok(result)
+}
+
 fn to_iter(stream: BoxVertexStream) -> Box<dyn NameIter> {
     Box::new(NonblockingNameIter(stream))
 }
@@ -901,6 +1213,10 @@ pub(crate) mod tests {
         assert!(!SyncNameSetQuery::is_empty(&query)?);
         assert!(SyncNameSetQuery::contains(&query, &to_name(0xef))?);
         assert!(!SyncNameSetQuery::contains(&query, &to_name(0))?);
+        assert_eq!(
+            SyncNameSetQuery::contains_all(&query, &[to_name(0), to_name(0xcd)])?,
+            [false, true]
+        );
         Ok(())
     }
 
@@ -960,6 +1276,92 @@ pub(crate) mod tests {
         assert_eq!(s(ab.clone() | bc.clone()), ["61", "62", "63"]);
         assert_eq!(s(ab.clone() & bc.clone()), ["62"]);
         assert_eq!(s(ab.clone() - bc.clone()), ["61"]);
+        assert_eq!(s(ab.clone() ^ bc.clone()), ["61", "63"]);
+        let cd: NameSet = "c d".into();
+        assert_eq!(
+            s(NameSet::union_all(vec![
+                ab.clone(),
+                NameSet::empty(),
+                bc,
+                cd
+            ])),
+            ["61", "62", "63", "64"]
+        );
+        assert_eq!(s(NameSet::union_all(vec![ab])), ["61", "62"]);
+        assert!(s(NameSet::union_all(Vec::new())).is_empty());
+
+        let ab: NameSet = "a b".into();
+        let ba: NameSet = "b a".into();
+        let abc: NameSet = "a b c".into();
+        assert!(nb(ab.is_subset(&abc)).unwrap());
+        assert!(!nb(abc.is_subset(&ab)).unwrap());
+        assert!(nb(NameSet::empty().is_subset(&ab)).unwrap());
+        assert!(nb(ab.is_equal(&ba)).unwrap());
+        assert!(!nb(ab.is_equal(&abc)).unwrap());
+    }
+
+    #[test]
+    fn test_contains_all_combinators() -> Result<()> {
+        let ab: NameSet = "a b".into();
+        let bc: NameSet = "b c".into();
+        let cd: NameSet = "c d".into();
+        let names: Vec<VertexName> = "a b c d e"
+            .split(' ')
+            .map(|s| VertexName::copy_from(s.as_bytes()))
+            .collect();
+        let sets = vec![
+            NameSet::from_query(union::UnionSet::new(ab.clone(), bc.clone())),
+            NameSet::from_query(union::UnionAllSet::new(vec![ab.clone(), bc.clone(), cd])),
+            NameSet::from_query(intersection::IntersectionSet::new(ab.clone(), bc.clone())),
+            NameSet::from_query(difference::DifferenceSet::new(ab.clone(), bc.clone())),
+            NameSet::from_query(symmetric_difference::SymmetricDifferenceSet::new(ab, bc)),
+        ];
+        for set in sets {
+            let expected = names
+                .iter()
+                .map(|name| SyncNameSetQuery::contains(&set, name))
+                .collect::<Result<Vec<bool>>>()?;
+            assert_eq!(SyncNameSetQuery::contains_all(&set, &names)?, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_combinators_cache_iteration() -> Result<()> {
+        use std::sync::atomic::AtomicUsize;
+        use std::sync::atomic::Ordering::SeqCst;
+
+        let lhs: NameSet = "a b c".into();
+        let checked = Arc::new(AtomicUsize::new(0));
+        let rhs = lhs.filter({
+            let checked = checked.clone();
+            Box::new(move |name: &VertexName| {
+                checked.fetch_add(1, SeqCst);
+                let is_b = name.as_ref() == b"b";
+                Box::pin(async move { Ok(is_b) })
+            })
+        });
+        let sets = vec![
+            NameSet::from_query(union::UnionSet::new(lhs.clone(), rhs.clone())),
+            NameSet::from_query(union::UnionAllSet::new(vec![lhs.clone(), rhs.clone()])),
+            NameSet::from_query(intersection::IntersectionSet::new(lhs.clone(), rhs.clone())),
+            NameSet::from_query(difference::DifferenceSet::new(lhs.clone(), rhs.clone())),
+            NameSet::from_query(symmetric_difference::SymmetricDifferenceSet::new(lhs, rhs)),
+        ];
+        let names = |iter: Box<dyn NameIter>| -> Result<Vec<VertexName>> { iter.collect() };
+        for set in sets {
+            let expected = names(SyncNameSetQuery::iter(&set)?)?;
+            let checked_after_iter = checked.load(SeqCst);
+            assert_eq!(names(SyncNameSetQuery::iter(&set)?)?, expected);
+            let mut rev = names(SyncNameSetQuery::iter_rev(&set)?)?;
+            rev.reverse();
+            assert_eq!(rev, expected);
+            assert_eq!(SyncNameSetQuery::count(&set)?, expected.len());
+            assert_eq!(SyncNameSetQuery::first(&set)?, expected.first().cloned());
+            assert_eq!(SyncNameSetQuery::last(&set)?, expected.last().cloned());
+            assert_eq!(checked.load(SeqCst), checked_after_iter, "{:?}", &set);
+        }
+        Ok(())
     }
 
     #[test]
@@ -969,6 +1371,9 @@ pub(crate) mod tests {
         assert_eq!(d(s.take(2)), "<static [a, b]>");
         assert_eq!(d(s.skip(2)), "<static [c, d]>");
         assert_eq!(d(s.skip(1).take(2)), "<static [b, c]>");
+        assert_eq!(d(s.slice(1..3)), "<static [b, c]>");
+        assert_eq!(d(s.slice(0..1)), "<static [a]>");
+        assert_eq!(d(s.slice(3..2)), "<empty>");
     }
 
     #[test]
@@ -1173,6 +1578,31 @@ pub(crate) mod tests {
         })
     }
 
+    #[test]
+    fn test_map() {
+        let abc: NameSet = "a b c".into();
+        let upper = |v: VertexName| VertexName::copy_from(&v.as_ref().to_ascii_uppercase());
+        let lower = |v: &VertexName| {
+            if v.as_ref().iter().all(|b| b.is_ascii_uppercase()) {
+                Some(VertexName::copy_from(&v.as_ref().to_ascii_lowercase()))
+            } else {
+                None
+            }
+        };
+        let mapped = abc.map(upper, lower);
+        check_invariants(mapped.0.as_ref()).unwrap();
+        assert_eq!(shorten_iter(mapped.iter()), ["41", "42", "43"]);
+        let v = |s: &str| VertexName::copy_from(s.as_bytes());
+        assert!(mapped.contains(&v("B")).unwrap());
+        assert!(!mapped.contains(&v("D")).unwrap());
+        assert!(!mapped.contains(&v("b")).unwrap());
+        assert_eq!(mapped.count().unwrap(), 3);
+
+        let empty = NameSet::empty().map(upper, lower);
+        assert!(empty.hints().contains(Flags::EMPTY));
+        assert_eq!(empty.count().unwrap(), 0);
+    }
+
     // Print hints for &, |, - operations.
     fn hints_ops(lhs: &NameSet, rhs: &NameSet) -> Vec<String> {
         vec![
@@ -0,0 +1,204 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::any::Any;
+use std::fmt;
+
+use futures::StreamExt;
+
+use super::cache::count_iter;
+use super::cache::QueryCache;
+use super::hints::Flags;
+use super::AsyncNameSetQuery;
+use super::BoxVertexStream;
+use super::Hints;
+use super::NameSet;
+use crate::fmt::write_debug;
+use crate::Result;
+use crate::VertexName;
+
+/// Names that are in exactly one of 2 sets.
+///
+/// Names only in the first set are iterated first, then names only in the
+/// second set. Each set is iterated once, and checked against the other
+/// set using `contains`.
+pub struct SymmetricDifferenceSet {
+    sets: [NameSet; 2],
+    hints: Hints,
+    cache: QueryCache,
+}
+
+impl SymmetricDifferenceSet {
+    pub fn new(lhs: NameSet, rhs: NameSet) -> Self {
+        let hints = Hints::union(&[lhs.hints(), rhs.hints()]);
+        if hints.id_map().is_some() {
+            if let (Some(id1), Some(id2)) = (lhs.hints().min_id(), rhs.hints().min_id()) {
+                hints.set_min_id(id1.min(id2));
+            }
+            if let (Some(id1), Some(id2)) = (lhs.hints().max_id(), rhs.hints().max_id()) {
+                hints.set_max_id(id1.max(id2));
+            }
+        };
+        if hints.dag().is_some() {
+            hints.union_depth(&[lhs.hints(), rhs.hints()]);
+        }
+        if lhs.hints().contains(Flags::FILTER) || rhs.hints().contains(Flags::FILTER) {
+            hints.add_flags(Flags::FILTER);
+        }
+        Self {
+            sets: [lhs, rhs],
+            hints,
+            cache: Default::default(),
+        }
+    }
+}
+
+/// Skip names in `iter` that are also in `other`.
+fn exclude(iter: BoxVertexStream, other: NameSet) -> BoxVertexStream {
+    let iter = iter.filter_map(move |item| {
+        let other = other.clone();
+        async move {
+            match item {
+                Ok(name) => match other.contains(&name).await {
+                    Ok(true) => None,
+                    Ok(false) => Some(Ok(name)),
+                    Err(err) => Some(Err(err)),
+                },
+                Err(err) => Some(Err(err)),
+            }
+        }
+    });
+    Box::pin(iter)
+}
+
+#[async_trait::async_trait]
+impl AsyncNameSetQuery for SymmetricDifferenceSet {
+    async fn iter(&self) -> Result<BoxVertexStream> {
+        let [lhs, rhs] = &self.sets;
+        let iter = async {
+            let lhs_iter = exclude(lhs.iter().await?, rhs.clone());
+            let rhs_iter = exclude(rhs.iter().await?, lhs.clone());
+            Ok::<BoxVertexStream, crate::Error>(Box::pin(lhs_iter.chain(rhs_iter)))
+        };
+        self.cache.iter(iter).await
+    }
+
+    async fn iter_rev(&self) -> Result<BoxVertexStream> {
+        let [lhs, rhs] = &self.sets;
+        let iter = async {
+            let lhs_iter = exclude(lhs.iter_rev().await?, rhs.clone());
+            let rhs_iter = exclude(rhs.iter_rev().await?, lhs.clone());
+            Ok::<BoxVertexStream, crate::Error>(Box::pin(rhs_iter.chain(lhs_iter)))
+        };
+        self.cache.iter_rev(iter).await
+    }
+
+    async fn count(&self) -> Result<usize> {
+        self.cache.count(count_iter(self.iter())).await
+    }
+
+    async fn first(&self) -> Result<Option<VertexName>> {
+        let first = async { self.iter().await?.next().await.transpose() };
+        self.cache.first(first).await
+    }
+
+    async fn last(&self) -> Result<Option<VertexName>> {
+        let last = async { self.iter_rev().await?.next().await.transpose() };
+        self.cache.last(last).await
+    }
+
+    async fn contains(&self, name: &VertexName) -> Result<bool> {
+        let [lhs, rhs] = &self.sets;
+        Ok(lhs.contains(name).await? != rhs.contains(name).await?)
+    }
+
+    async fn contains_all(&self, names: &[VertexName]) -> Result<Vec<bool>> {
+        let [lhs, rhs] = &self.sets;
+        let lhs = lhs.contains_all(names).await?;
+        let rhs = rhs.contains_all(names).await?;
+        Ok(lhs.into_iter().zip(rhs).map(|(l, r)| l != r).collect())
+    }
+
+    async fn contains_fast(&self, name: &VertexName) -> Result<Option<bool>> {
+        let [lhs, rhs] = &self.sets;
+        let result = match (
+            lhs.contains_fast(name).await?,
+            rhs.contains_fast(name).await?,
+        ) {
+            (Some(lhs_contains), Some(rhs_contains)) => Some(lhs_contains != rhs_contains),
+            _ => None,
+        };
+        Ok(result)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn hints(&self) -> &Hints {
+        &self.hints
+    }
+}
+
+impl fmt::Debug for SymmetricDifferenceSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<xor")?;
+        write_debug(f, &self.sets[0])?;
+        write_debug(f, &self.sets[1])?;
+        write!(f, ">")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::super::tests::*;
+    use super::*;
+
+    fn symmetric_difference(a: &[u8], b: &[u8]) -> SymmetricDifferenceSet {
+        let a = NameSet::from_query(VecQuery::from_bytes(a));
+        let b = NameSet::from_query(VecQuery::from_bytes(b));
+        SymmetricDifferenceSet::new(a, b)
+    }
+
+    #[test]
+    fn test_symmetric_difference_basic() -> Result<()> {
+        let set = symmetric_difference(b"\x11\x33\x22", b"\x44\x11\x55\x33");
+        check_invariants(&set)?;
+        assert_eq!(shorten_iter(ni(set.iter())), ["22", "44", "55"]);
+        assert_eq!(shorten_iter(ni(set.iter_rev())), ["55", "44", "22"]);
+        assert!(!nb(set.is_empty())?);
+        assert_eq!(nb(set.count())?, 3);
+        assert_eq!(shorten_name(nb(set.first())?.unwrap()), "22");
+        assert_eq!(shorten_name(nb(set.last())?.unwrap()), "55");
+        for &b in b"\x22\x44\x55".iter() {
+            assert!(nb(set.contains(&to_name(b)))?);
+        }
+        for &b in b"\x11\x33\x66".iter() {
+            assert!(!nb(set.contains(&to_name(b)))?);
+        }
+        Ok(())
+    }
+
+    quickcheck::quickcheck! {
+        fn test_symmetric_difference_quickcheck(a: Vec<u8>, b: Vec<u8>) -> bool {
+            let set = symmetric_difference(&a, &b);
+            check_invariants(&set).unwrap();
+
+            let a: HashSet<u8> = a.into_iter().collect();
+            let b: HashSet<u8> = b.into_iter().collect();
+            let count = nb(set.count()).unwrap();
+            assert_eq!(count, a.symmetric_difference(&b).count());
+
+            assert!(a.symmetric_difference(&b).all(|&b| nb(set.contains(&to_name(b))).ok() == Some(true)));
+            assert!(a.intersection(&b).all(|&b| nb(set.contains(&to_name(b))).ok() == Some(false)));
+
+            true
+        }
+    }
+}
@@ -7,15 +7,20 @@
 
 use std::any::Any;
 use std::fmt;
+use std::sync::Arc;
 
 use futures::StreamExt;
 
+use super::cache::count_iter;
+use super::cache::QueryCache;
+use super::contains_selected;
 use super::hints::Flags;
 use super::AsyncNameSetQuery;
 use super::BoxVertexStream;
 use super::Hints;
 use super::NameSet;
 use crate::fmt::write_debug;
+use crate::Id;
 use crate::Result;
 use crate::VertexName;
 
@@ -26,6 +31,7 @@ use crate::VertexName;
 pub struct UnionSet {
     sets: [NameSet; 2],
     hints: Hints,
+    cache: QueryCache,
 }
 
 impl UnionSet {
@@ -39,6 +45,9 @@ impl UnionSet {
                 hints.set_max_id(id1.max(id2));
             }
         };
+        if hints.dag().is_some() {
+            hints.union_depth(&[lhs.hints(), rhs.hints()]);
+        }
         hints.add_flags(lhs.hints().flags() & rhs.hints().flags() & Flags::ANCESTORS);
         if lhs.hints().contains(Flags::FILTER) || rhs.hints().contains(Flags::FILTER) {
             hints.add_flags(Flags::FILTER);
@@ -46,6 +55,7 @@ impl UnionSet {
         Self {
             sets: [lhs, rhs],
             hints,
+            cache: Default::default(),
         }
     }
 }
@@ -54,35 +64,54 @@ impl UnionSet {
 impl AsyncNameSetQuery for UnionSet {
     async fn iter(&self) -> Result<BoxVertexStream> {
         debug_assert_eq!(self.sets.len(), 2);
-        let diff = self.sets[1].clone() - self.sets[0].clone();
-        let diff_iter = diff.iter().await?;
-        let set0_iter = self.sets[0].iter().await?;
-        let iter = set0_iter.chain(diff_iter);
-        Ok(Box::pin(iter))
+        let iter = async {
+            let diff = self.sets[1].clone() - self.sets[0].clone();
+            let diff_iter = diff.iter().await?;
+            let set0_iter = self.sets[0].iter().await?;
+            let iter = set0_iter.chain(diff_iter);
+            Ok::<BoxVertexStream, crate::Error>(Box::pin(iter))
+        };
+        self.cache.iter(iter).await
     }
 
     async fn iter_rev(&self) -> Result<BoxVertexStream> {
         debug_assert_eq!(self.sets.len(), 2);
-        let diff = self.sets[1].clone() - self.sets[0].clone();
-        let diff_iter = diff.iter_rev().await?;
-        let set0_iter = self.sets[0].iter_rev().await?;
-        let iter = diff_iter.chain(set0_iter);
-        Ok(Box::pin(iter))
+        let iter = async {
+            let diff = self.sets[1].clone() - self.sets[0].clone();
+            let diff_iter = diff.iter_rev().await?;
+            let set0_iter = self.sets[0].iter_rev().await?;
+            let iter = diff_iter.chain(set0_iter);
+            Ok::<BoxVertexStream, crate::Error>(Box::pin(iter))
+        };
+        self.cache.iter_rev(iter).await
     }
 
     async fn count(&self) -> Result<usize> {
         debug_assert_eq!(self.sets.len(), 2);
         // This is more efficient if sets[0] is a large set that has a fast path
         // for "count()".
-        let mut count = self.sets[0].count().await?;
-        let mut iter = self.sets[1].iter().await?;
-        while let Some(item) = iter.next().await {
-            let name = item?;
-            if !self.sets[0].contains(&name).await? {
-                count += 1;
+        let count = async {
+            let mut count = self.sets[0].count().await?;
+            let mut iter = self.sets[1].iter().await?;
+            while let Some(item) = iter.next().await {
+                let name = item?;
+                if !self.sets[0].contains(&name).await? {
+                    count += 1;
+                }
             }
-        }
-        Ok(count)
+            Ok::<_, crate::Error>(count)
+        };
+        self.cache.count(count).await
+    }
+
+    async fn first(&self) -> Result<Option<VertexName>> {
+        let first = async { self.iter().await?.next().await.transpose() };
+        self.cache.first(first).await
+    }
+
+    async fn last(&self) -> Result<Option<VertexName>> {
+        let last = async { self.iter_rev().await?.next().await.transpose() };
+        self.cache.last(last).await
     }
 
     async fn is_empty(&self) -> Result<bool> {
@@ -103,6 +132,13 @@ impl AsyncNameSetQuery for UnionSet {
         Ok(false)
     }
 
+    async fn contains_all(&self, names: &[VertexName]) -> Result<Vec<bool>> {
+        let lhs = self.sets[0].contains_all(names).await?;
+        let not_lhs: Vec<bool> = lhs.iter().map(|&c| !c).collect();
+        let rhs = contains_selected(&self.sets[1], names, &not_lhs).await?;
+        Ok(lhs.into_iter().zip(rhs).map(|(l, r)| l || r).collect())
+    }
+
     async fn contains_fast(&self, name: &VertexName) -> Result<Option<bool>> {
         for set in &self.sets {
             if let Some(result) = set.contains_fast(name).await? {
@@ -130,6 +166,190 @@ impl fmt::Debug for UnionSet {
     }
 }
 
+/// Union of many sets.
+///
+/// The order is preserved. Sets are iterated one by one, with names that are
+/// in earlier sets skipped. Unlike a tree of [`UnionSet`]s, each set is only
+/// iterated once.
+pub struct UnionAllSet {
+    sets: Vec<NameSet>,
+    hints: Hints,
+    cache: QueryCache,
+}
+
+impl UnionAllSet {
+    pub fn new(sets: Vec<NameSet>) -> Self {
+        let hints_list: Vec<&Hints> = sets.iter().map(|s| s.hints()).collect();
+        let hints = Hints::union(&hints_list);
+        if hints.id_map().is_some() {
+            let min_ids: Option<Vec<Id>> = hints_list.iter().map(|h| h.min_id()).collect();
+            if let Some(id) = min_ids.and_then(|ids| ids.into_iter().min()) {
+                hints.set_min_id(id);
+            }
+            let max_ids: Option<Vec<Id>> = hints_list.iter().map(|h| h.max_id()).collect();
+            if let Some(id) = max_ids.and_then(|ids| ids.into_iter().max()) {
+                hints.set_max_id(id);
+            }
+        }
+        if hints.dag().is_some() {
+            hints.union_depth(&hints_list);
+        }
+        let flags = hints_list
+            .iter()
+            .fold(Flags::ANCESTORS, |flags, h| flags & h.flags());
+        hints.add_flags(flags);
+        if hints_list.iter().any(|h| h.contains(Flags::FILTER)) {
+            hints.add_flags(Flags::FILTER);
+        }
+        Self {
+            sets,
+            hints,
+            cache: Default::default(),
+        }
+    }
+
+    /// Iterate `sets[i]`, skipping names in `sets[..i]`.
+    async fn iter_nth(&self, i: usize, reversed: bool) -> Result<BoxVertexStream> {
+        let iter = if reversed {
+            self.sets[i].iter_rev().await?
+        } else {
+            self.sets[i].iter().await?
+        };
+        if i == 0 {
+            return Ok(iter);
+        }
+        let earlier: Arc<Vec<NameSet>> = Arc::new(self.sets[..i].to_vec());
+        let iter = iter.filter_map(move |item| {
+            let earlier = earlier.clone();
+            async move {
+                let name = match item {
+                    Ok(name) => name,
+                    Err(err) => return Some(Err(err)),
+                };
+                for set in earlier.iter() {
+                    match set.contains(&name).await {
+                        Ok(true) => return None,
+                        Ok(false) => {}
+                        Err(err) => return Some(Err(err)),
+                    }
+                }
+                Some(Ok(name))
+            }
+        });
+        Ok(Box::pin(iter))
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncNameSetQuery for UnionAllSet {
+    async fn iter(&self) -> Result<BoxVertexStream> {
+        let iter = async {
+            let mut iters = Vec::with_capacity(self.sets.len());
+            for i in 0..self.sets.len() {
+                iters.push(self.iter_nth(i, false).await?);
+            }
+            Ok::<BoxVertexStream, crate::Error>(Box::pin(futures::stream::iter(iters).flatten()))
+        };
+        self.cache.iter(iter).await
+    }
+
+    async fn iter_rev(&self) -> Result<BoxVertexStream> {
+        let iter = async {
+            let mut iters = Vec::with_capacity(self.sets.len());
+            for i in (0..self.sets.len()).rev() {
+                iters.push(self.iter_nth(i, true).await?);
+            }
+            Ok::<BoxVertexStream, crate::Error>(Box::pin(futures::stream::iter(iters).flatten()))
+        };
+        self.cache.iter_rev(iter).await
+    }
+
+    async fn count(&self) -> Result<usize> {
+        let count = async {
+            let mut count = 0;
+            for i in 0..self.sets.len() {
+                count += if i == 0 {
+                    self.sets[0].count().await?
+                } else {
+                    count_iter(self.iter_nth(i, false)).await?
+                };
+            }
+            Ok::<_, crate::Error>(count)
+        };
+        self.cache.count(count).await
+    }
+
+    async fn first(&self) -> Result<Option<VertexName>> {
+        let first = async { self.iter().await?.next().await.transpose() };
+        self.cache.first(first).await
+    }
+
+    async fn last(&self) -> Result<Option<VertexName>> {
+        let last = async { self.iter_rev().await?.next().await.transpose() };
+        self.cache.last(last).await
+    }
+
+    async fn is_empty(&self) -> Result<bool> {
+        for set in &self.sets {
+            if !set.is_empty().await? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    async fn contains(&self, name: &VertexName) -> Result<bool> {
+        for set in &self.sets {
+            if set.contains(name).await? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    async fn contains_all(&self, names: &[VertexName]) -> Result<Vec<bool>> {
+        let mut result = vec![false; names.len()];
+        for set in &self.sets {
+            let not_found: Vec<bool> = result.iter().map(|&c| !c).collect();
+            let found = contains_selected(set, names, &not_found).await?;
+            for (r, f) in result.iter_mut().zip(found) {
+                *r = *r || f;
+            }
+        }
+        Ok(result)
+    }
+
+    async fn contains_fast(&self, name: &VertexName) -> Result<Option<bool>> {
+        let mut result = Some(false);
+        for set in &self.sets {
+            match set.contains_fast(name).await? {
+                Some(true) => return Ok(Some(true)),
+                Some(false) => {}
+                None => result = None,
+            }
+        }
+        Ok(result)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn hints(&self) -> &Hints {
+        &self.hints
+    }
+}
+
+impl fmt::Debug for UnionAllSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<or")?;
+        for set in &self.sets {
+            write_debug(f, set)?;
+        }
+        write!(f, ">")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
@@ -166,6 +386,28 @@ mod tests {
         Ok(())
     }
 
+    fn union_all(sets: &[&[u8]]) -> UnionAllSet {
+        let sets = sets
+            .iter()
+            .map(|s| NameSet::from_query(VecQuery::from_bytes(s)))
+            .collect();
+        UnionAllSet::new(sets)
+    }
+
+    #[test]
+    fn test_union_all_basic() -> Result<()> {
+        let set = union_all(&[b"\x11\x33", b"\x44\x11", b"\x33\x55\x44"]);
+        check_invariants(&set)?;
+        assert_eq!(shorten_iter(ni(set.iter())), ["11", "33", "44", "55"]);
+        assert_eq!(shorten_iter(ni(set.iter_rev())), ["55", "44", "33", "11"]);
+        assert_eq!(nb(set.count())?, 4);
+        assert_eq!(shorten_name(nb(set.first())?.unwrap()), "11");
+        assert_eq!(shorten_name(nb(set.last())?.unwrap()), "55");
+        assert!(nb(set.contains(&to_name(0x55)))?);
+        assert!(!nb(set.contains(&to_name(0x66)))?);
+        Ok(())
+    }
+
     quickcheck::quickcheck! {
         fn test_union_quickcheck(a: Vec<u8>, b: Vec<u8>) -> bool {
             let set = union(&a, &b);
@@ -180,6 +422,16 @@ mod tests {
             assert!(a.iter().all(|&b| nb(set.contains(&to_name(b))).ok() == Some(true)));
             assert!(b.iter().all(|&b| nb(set.contains(&to_name(b))).ok() == Some(true)));
 
+            true
+        }
+        fn test_union_all_quickcheck(a: Vec<u8>, b: Vec<u8>, c: Vec<u8>) -> bool {
+            let set = union_all(&[&a, &b, &c]);
+            check_invariants(&set).unwrap();
+
+            let expected: HashSet<_> = a.iter().chain(b.iter()).chain(c.iter()).cloned().collect();
+            assert_eq!(nb(set.count()).unwrap(), expected.len());
+            assert!(expected.iter().all(|&b| nb(set.contains(&to_name(b))).ok() == Some(true)));
+
             true
         }
     }
@@ -14,6 +14,7 @@ use futures::TryStreamExt;
 
 use crate::clone::CloneData;
 use crate::default_impl;
+use crate::errors::DagError;
 use crate::errors::NotFoundError;
 use crate::id::Group;
 use crate::id::Id;
@@ -63,6 +64,12 @@ pub trait DagAlgorithm: Send + Sync {
     /// Calculates all ancestors reachable from any name from the given set.
     async fn ancestors(&self, set: NameSet) -> Result<NameSet>;
 
+    /// Calculates ancestors that are at most `depth` edges away from the
+    /// given set. `ancestors_within(set, 0)` is `set`.
+    async fn ancestors_within(&self, set: NameSet, depth: u64) -> Result<NameSet> {
+        default_impl::ancestors_within(self, set, depth).await
+    }
+
     /// Calculates parents of the given set.
     ///
     /// Note: Parent order is not preserved. Use [`NameDag::parent_names`]
@@ -124,6 +131,23 @@ pub trait DagAlgorithm: Send + Sync {
         default_impl::is_ancestor(self, ancestor, descendant).await
     }
 
+    /// Calculates the depth (generation number) of `name`. That is, the
+    /// number of edges in the longest path from a root to `name`.
+    /// Roots have depth 0.
+    async fn depth(&self, name: VertexName) -> Result<u64> {
+        default_impl::depth(self, name).await
+    }
+
+    /// Find a shortest path from `ancestor` to `descendant`, including both
+    /// ends. Return `None` if `ancestor` is not an ancestor of `descendant`.
+    async fn shortest_path(
+        &self,
+        ancestor: VertexName,
+        descendant: VertexName,
+    ) -> Result<Option<Vec<VertexName>>> {
+        default_impl::shortest_path(self, ancestor, descendant).await
+    }
+
     /// Calculates "heads" of the ancestors of the given set. That is,
     /// Find Y, which is the smallest subset of set X, where `ancestors(Y)` is
     /// `ancestors(X)`.
@@ -418,6 +442,45 @@ pub trait PrefixLookup {
         hex_prefix: &[u8],
         limit: usize,
     ) -> Result<Vec<VertexName>>;
+
+    /// Lookup vertexes sharing the longest hex prefix with `name`.
+    ///
+    /// Useful for "did you mean" suggestions after a
+    /// [`VertexNotFound`](crate::Error::VertexNotFound) error.
+    async fn similar_vertexes(&self, name: &VertexName, limit: usize) -> Result<Vec<VertexName>> {
+        // Matches of a longer prefix are a subset of matches of a shorter
+        // prefix. Bisect the longest prefix that has matches.
+        let hex = name.to_hex();
+        let hex = hex.as_bytes();
+        let mut result = Vec::new();
+        let (mut low, mut high) = (1, hex.len());
+        while low <= high {
+            let mid = (low + high) / 2;
+            let found = self.vertexes_by_hex_prefix(&hex[..mid], limit).await?;
+            if found.is_empty() {
+                high = mid - 1;
+            } else {
+                result = found;
+                low = mid + 1;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Create a not found error for `name`, with up to `limit` similar
+    /// vertexes as suggestions.
+    ///
+    /// Suggestions are best-effort. If looking them up fails, the error has
+    /// no suggestions.
+    async fn vertex_not_found_error(&self, name: &VertexName, limit: usize) -> DagError {
+        match self.similar_vertexes(name, limit).await {
+            Ok(suggestions) => DagError::VertexNotFound {
+                name: name.clone(),
+                suggestions,
+            },
+            Err(_) => name.not_found_error(),
+        }
+    }
 }
 
 /// Convert between `Vertex` and `Id`.
@@ -15,6 +15,8 @@
 //!
 //! - Id -> Name: Id -> RequestLocationToName -> ResponseIdNamePair -> Name
 //! - Name -> Id: Name -> RequestNameToLocation -> ResponseIdNamePair -> Id
+//!
+//! Query results can be sent as [`WireNameSet`].
 
 use std::cell::RefCell;
 use std::fmt;
@@ -23,17 +25,25 @@ use std::thread_local;
 use futures::stream;
 use futures::stream::StreamExt;
 use futures::stream::TryStreamExt;
+use serde::Deserialize;
+use serde::Serialize;
 
+use crate::errors::DagError;
 use crate::id::VertexName;
 use crate::iddag::FirstAncestorConstraint;
 use crate::iddag::IdDag;
 use crate::iddagstore::IdDagStore;
+use crate::namedag::AbstractNameDag;
+use crate::ops::DagAlgorithm;
 use crate::ops::IdConvert;
+use crate::ops::TryClone;
+use crate::utils;
 use crate::Group;
 use crate::Id;
 #[cfg(any(test, feature = "indexedlog-backend"))]
 use crate::IdMap;
 use crate::IdSet;
+use crate::NameSet;
 use crate::Result;
 
 // Request and Response structures -------------------------------------------
@@ -310,7 +320,7 @@ impl<M: IdConvert, DagStore: IdDagStore> Process<RequestNameToLocation, Response
             for (name, id_result) in request.names.into_iter().zip(ids_result) {
                 match id_result {
                     // If one of the names cannot be resolved to id, just skip it.
-                    Err(crate::Error::VertexNotFound(n)) => {
+                    Err(crate::Error::VertexNotFound { name: n, .. }) => {
                         tracing::trace!(
                             "RequestNameToLocation -> ResponseIdNamePair: skip unknown name {:?}",
                             &n
@@ -435,6 +445,154 @@ impl<'a, DagStore: IdDagStore> Process<ResponseIdNamePair, ()>
     }
 }
 
+// Set exchange --------------------------------------------------------------
+
+/// Identifies how a graph assigns ids to vertexes in the master group.
+///
+/// Master group ids are not changed once assigned. Assuming vertex names
+/// commit to their ancestors, like commit hashes, graphs with the same
+/// master group flat segments, and the same names at the segment heads,
+/// assign the same ids to all vertexes in the master group.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdMapToken {
+    master_heads: Vec<(Id, VertexName)>,
+    /// Hash of the master group flat segments and the names of their heads.
+    /// Graphs with the same heads might still assign the interior ids
+    /// differently.
+    master_segments: u64,
+}
+
+impl IdMapToken {
+    /// Calculate the token of `dag`.
+    pub async fn from_dag<IS, M, P, S>(dag: &AbstractNameDag<IdDag<IS>, M, P, S>) -> Result<Self>
+    where
+        IS: IdDagStore,
+        IdDag<IS>: TryClone + 'static,
+        M: TryClone + IdConvert + Sync + Send + 'static,
+        P: TryClone + Sync + Send + 'static,
+        S: TryClone + Sync + Send + 'static,
+    {
+        let heads = dag.heads(dag.master_group().await?).await?;
+        let heads: Vec<VertexName> = heads.iter().await?.try_collect().await?;
+        let mut master_heads = Vec::with_capacity(heads.len());
+        for head in heads {
+            let id = dag.vertex_id(head.clone()).await?;
+            master_heads.push((id, head));
+        }
+        master_heads.sort_unstable();
+
+        let segments = dag.dag().flat_segments(Group::MASTER)?.segments;
+        let highs: Vec<Id> = segments.iter().map(|seg| seg.high).collect();
+        let names = dag.vertex_name_batch(&highs).await?;
+        let mut buf = Vec::new();
+        for (seg, name) in segments.iter().zip(names) {
+            let name = name?;
+            for id in [seg.low, seg.high].iter().chain(seg.parents.iter()) {
+                buf.extend_from_slice(&id.0.to_le_bytes());
+            }
+            buf.extend_from_slice(&(name.as_ref().len() as u64).to_le_bytes());
+            buf.extend_from_slice(name.as_ref());
+        }
+        let master_segments = utils::stable_hash(&buf);
+
+        Ok(Self {
+            master_heads,
+            master_segments,
+        })
+    }
+}
+
+/// A [`NameSet`], usually a query result, encoded for sending to another
+/// process.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WireNameSet {
+    /// Ids in the master group of the sender. Compact, but only meaningful
+    /// to a receiver with the same [`IdMapToken`].
+    Spans { token: IdMapToken, spans: IdSet },
+
+    /// Vertex names listed explicitly. Meaningful to any receiver.
+    Names(Vec<VertexName>),
+}
+
+impl WireNameSet {
+    /// Encode `set` evaluated by `dag`.
+    ///
+    /// `receiver` is the [`IdMapToken`] of the receiver, if known. If it
+    /// matches the token of `dag`, and `set` is backed by master group ids
+    /// of `dag`, then the set is encoded as `Spans`. Otherwise, the set is
+    /// iterated and encoded as `Names`.
+    pub async fn from_set<IS, M, P, S>(
+        set: &NameSet,
+        dag: &AbstractNameDag<IdDag<IS>, M, P, S>,
+        receiver: Option<&IdMapToken>,
+    ) -> Result<Self>
+    where
+        IS: IdDagStore,
+        IdDag<IS>: TryClone + 'static,
+        M: TryClone + IdConvert + Sync + Send + 'static,
+        P: TryClone + Sync + Send + 'static,
+        S: TryClone + Sync + Send + 'static,
+    {
+        if let Some(receiver) = receiver {
+            let token = IdMapToken::from_dag(dag).await?;
+            if &token == receiver {
+                let master = dag.master_group().await?;
+                if let (Some(spans), Some(master)) = (set.to_spans(dag), master.to_spans(dag)) {
+                    if spans.difference(&master).is_empty() {
+                        return Ok(Self::Spans { token, spans });
+                    }
+                }
+            }
+        }
+        let names: Vec<VertexName> = set.iter().await?.try_collect().await?;
+        Ok(Self::Names(names))
+    }
+
+    /// Decode into a `NameSet` using `dag` of the receiver.
+    ///
+    /// `Spans` are checked against the [`IdMapToken`] of `dag`. If they do
+    /// not match, a [`NeedSlowPath`](crate::Error::NeedSlowPath) error is
+    /// returned and the caller should request `Names` instead.
+    pub async fn to_set<IS, M, P, S>(
+        self,
+        dag: &AbstractNameDag<IdDag<IS>, M, P, S>,
+    ) -> Result<NameSet>
+    where
+        IS: IdDagStore,
+        IdDag<IS>: TryClone + 'static,
+        M: TryClone + IdConvert + Sync + Send + 'static,
+        P: TryClone + Sync + Send + 'static,
+        S: TryClone + Sync + Send + 'static,
+    {
+        match self {
+            Self::Spans { token, spans } => {
+                if token != IdMapToken::from_dag(dag).await? {
+                    return Err(DagError::NeedSlowPath(
+                        "spans are encoded for a different IdMap, request names instead"
+                            .to_string(),
+                    ));
+                }
+                NameSet::from_spans_dag(spans, dag)
+            }
+            Self::Names(names) => Ok(NameSet::from_static_names(names)),
+        }
+    }
+
+    /// Serialize to bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let data = mincode::serialize(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(data)
+    }
+
+    /// Deserialize from bytes produced by `to_bytes`.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let set = mincode::deserialize(data)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(set)
+    }
+}
+
 // Disable remote protocol temporarily ---------------------------------------
 // This can be useful for Debug::fmt to disable remote fetching which might
 // panic (ex. calling tokio without tokio runtime) when executing futures
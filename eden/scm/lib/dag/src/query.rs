@@ -0,0 +1,374 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! # query
+//!
+//! A small revset-like language to calculate [`NameSet`]s using
+//! [`DagAlgorithm`]s.
+//!
+//! ```plain,ignore
+//! ancestors(x) - ancestors(y)
+//! heads(a::b)
+//! (x | y) & ::z
+//! ```
+//!
+//! Operators, from the lowest precedence to the highest:
+//! - `x | y`, `x + y`: union.
+//! - `x & y`: intersection. `x - y`: difference.
+//! - `x::y`: range. `::y`: ancestors. `x::`: descendants.
+//!
+//! Symbols are resolved by [`ResolveSymbol`]. Symbols can be quoted using
+//! `"` or `'` if they contain special characters.
+//!
+//! Syntax errors and wrong function arguments are reported as
+//! [`DagError::InvalidQuery`].
+
+use futures::future::BoxFuture;
+
+use crate::errors::DagError;
+use crate::DagAlgorithm;
+use crate::NameSet;
+use crate::Result;
+use crate::VertexName;
+
+/// Parsed query expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// A symbol to be resolved by [`ResolveSymbol`].
+    Symbol(String),
+
+    /// A function call like `heads(x)`.
+    /// `x::y`, `::y` and `x::` are parsed as `range(x, y)`, `ancestors(y)`
+    /// and `descendants(x)`.
+    Function(String, Vec<Expr>),
+
+    /// `x | y`, or `x + y`.
+    Union(Box<Expr>, Box<Expr>),
+
+    /// `x & y`.
+    Intersection(Box<Expr>, Box<Expr>),
+
+    /// `x - y`.
+    Difference(Box<Expr>, Box<Expr>),
+}
+
+/// Resolve symbols in a query to sets.
+#[async_trait::async_trait]
+pub trait ResolveSymbol: Send + Sync {
+    async fn resolve_symbol(&self, name: &str) -> Result<NameSet>;
+}
+
+/// Resolve symbols as vertex names.
+pub struct VertexNameResolver;
+
+#[async_trait::async_trait]
+impl ResolveSymbol for VertexNameResolver {
+    async fn resolve_symbol(&self, name: &str) -> Result<NameSet> {
+        let vertex = VertexName::copy_from(name.as_bytes());
+        Ok(NameSet::from_static_names(vec![vertex]))
+    }
+}
+
+/// Parse and evaluate `query` using the given `dag` and `resolver`.
+pub async fn query(
+    dag: &dyn DagAlgorithm,
+    query: &str,
+    resolver: &dyn ResolveSymbol,
+) -> Result<NameSet> {
+    let expr = parse(query)?;
+    evaluate(dag, &expr, resolver).await
+}
+
+/// Evaluate `expr` using the given `dag` and `resolver`.
+pub fn evaluate<'a>(
+    dag: &'a dyn DagAlgorithm,
+    expr: &'a Expr,
+    resolver: &'a dyn ResolveSymbol,
+) -> BoxFuture<'a, Result<NameSet>> {
+    Box::pin(async move {
+        let set = match expr {
+            Expr::Symbol(name) => resolver.resolve_symbol(name).await?,
+            Expr::Union(lhs, rhs) => {
+                let lhs = evaluate(dag, lhs, resolver).await?;
+                let rhs = evaluate(dag, rhs, resolver).await?;
+                lhs.union(&rhs)
+            }
+            Expr::Intersection(lhs, rhs) => {
+                let lhs = evaluate(dag, lhs, resolver).await?;
+                let rhs = evaluate(dag, rhs, resolver).await?;
+                lhs.intersection(&rhs)
+            }
+            Expr::Difference(lhs, rhs) => {
+                let lhs = evaluate(dag, lhs, resolver).await?;
+                let rhs = evaluate(dag, rhs, resolver).await?;
+                lhs.difference(&rhs)
+            }
+            Expr::Function(name, args) => {
+                let mut sets = Vec::with_capacity(args.len());
+                for arg in args {
+                    sets.push(evaluate(dag, arg, resolver).await?);
+                }
+                evaluate_function(dag, name, sets).await?
+            }
+        };
+        Ok(set)
+    })
+}
+
+async fn evaluate_function(
+    dag: &dyn DagAlgorithm,
+    name: &str,
+    mut args: Vec<NameSet>,
+) -> Result<NameSet> {
+    let expect_args = |n: usize| -> Result<()> {
+        if args.len() != n {
+            return invalid_query(format!(
+                "{}() takes {} argument(s), but {} were given",
+                name,
+                n,
+                args.len()
+            ));
+        }
+        Ok(())
+    };
+    let set = match name {
+        "all" => {
+            expect_args(0)?;
+            dag.all().await?
+        }
+        "ancestors" | "descendants" | "parents" | "children" | "heads" | "roots" | "merges"
+        | "first_ancestors" | "heads_ancestors" => {
+            expect_args(1)?;
+            let set = args.pop().unwrap();
+            match name {
+                "ancestors" => dag.ancestors(set).await?,
+                "descendants" => dag.descendants(set).await?,
+                "parents" => dag.parents(set).await?,
+                "children" => dag.children(set).await?,
+                "heads" => dag.heads(set).await?,
+                "roots" => dag.roots(set).await?,
+                "merges" => dag.merges(set).await?,
+                "first_ancestors" => dag.first_ancestors(set).await?,
+                _ => dag.heads_ancestors(set).await?,
+            }
+        }
+        "gca" | "common_ancestors" => {
+            if args.is_empty() {
+                return invalid_query(format!("{}() takes at least 1 argument", name));
+            }
+            let set = args
+                .into_iter()
+                .fold(NameSet::empty(), |acc, set| acc.union(&set));
+            match name {
+                "gca" => dag.gca_all(set).await?,
+                _ => dag.common_ancestors(set).await?,
+            }
+        }
+        "only" | "range" => {
+            expect_args(2)?;
+            let rhs = args.pop().unwrap();
+            let lhs = args.pop().unwrap();
+            match name {
+                "only" => dag.only(lhs, rhs).await?,
+                _ => dag.range(lhs, rhs).await?,
+            }
+        }
+        _ => return invalid_query(format!("unknown function {}()", name)),
+    };
+    Ok(set)
+}
+
+fn invalid_query<T>(message: impl ToString) -> Result<T> {
+    Err(DagError::InvalidQuery(message.to_string()))
+}
+
+/// Parse a query string into an [`Expr`].
+pub fn parse(text: &str) -> Result<Expr> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser {
+        text,
+        tokens,
+        pos: 0,
+    };
+    let expr = parser.parse_union()?;
+    if parser.pos < parser.tokens.len() {
+        return parser.error("unexpected trailing input");
+    }
+    Ok(expr)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Symbol(String),
+    LeftParen,
+    RightParen,
+    Comma,
+    Union,
+    Intersection,
+    Difference,
+    DagRange,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let token = match ch {
+            ' ' | '\t' | '\n' | '\r' => continue,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            ',' => Token::Comma,
+            '|' | '+' => Token::Union,
+            '&' => Token::Intersection,
+            '-' => Token::Difference,
+            ':' => match chars.next() {
+                Some(':') => Token::DagRange,
+                _ => return invalid_query(format!("{:?}: expect '::'", text)),
+            },
+            '"' | '\'' => {
+                let quote = ch;
+                let mut symbol = String::new();
+                loop {
+                    match chars.next() {
+                        Some(ch) if ch == quote => break,
+                        Some(ch) => symbol.push(ch),
+                        None => {
+                            return invalid_query(format!("{:?}: unterminated quote", text));
+                        }
+                    }
+                }
+                Token::Symbol(symbol)
+            }
+            ch if is_symbol_char(ch) => {
+                let mut symbol = ch.to_string();
+                while let Some(&ch) = chars.peek() {
+                    if !is_symbol_char(ch) {
+                        break;
+                    }
+                    symbol.push(ch);
+                    chars.next();
+                }
+                Token::Symbol(symbol)
+            }
+            ch => return invalid_query(format!("{:?}: unexpected {:?}", text, ch)),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn is_symbol_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == '.'
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next_if(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T> {
+        invalid_query(format!(
+            "{:?}: {} at token {}",
+            self.text, message, self.pos
+        ))
+    }
+
+    /// `x | y`, `x + y`.
+    fn parse_union(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_intersection()?;
+        while self.next_if(&Token::Union) {
+            let rhs = self.parse_intersection()?;
+            expr = Expr::Union(Box::new(expr), Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    /// `x & y`, `x - y`.
+    fn parse_intersection(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_range()?;
+        loop {
+            if self.next_if(&Token::Intersection) {
+                let rhs = self.parse_range()?;
+                expr = Expr::Intersection(Box::new(expr), Box::new(rhs));
+            } else if self.next_if(&Token::Difference) {
+                let rhs = self.parse_range()?;
+                expr = Expr::Difference(Box::new(expr), Box::new(rhs));
+            } else {
+                break;
+            }
+        }
+        Ok(expr)
+    }
+
+    /// `x::y`, `::y`, `x::`.
+    fn parse_range(&mut self) -> Result<Expr> {
+        if self.next_if(&Token::DagRange) {
+            let heads = self.parse_primary()?;
+            return Ok(Expr::Function("ancestors".to_string(), vec![heads]));
+        }
+        let roots = self.parse_primary()?;
+        if !self.next_if(&Token::DagRange) {
+            return Ok(roots);
+        }
+        match self.peek() {
+            Some(Token::Symbol(_)) | Some(Token::LeftParen) => {
+                let heads = self.parse_primary()?;
+                Ok(Expr::Function("range".to_string(), vec![roots, heads]))
+            }
+            _ => Ok(Expr::Function("descendants".to_string(), vec![roots])),
+        }
+    }
+
+    /// `symbol`, `func(args)`, `(expr)`.
+    fn parse_primary(&mut self) -> Result<Expr> {
+        match self.peek().cloned() {
+            Some(Token::LeftParen) => {
+                self.pos += 1;
+                let expr = self.parse_union()?;
+                if !self.next_if(&Token::RightParen) {
+                    return self.error("expect ')'");
+                }
+                Ok(expr)
+            }
+            Some(Token::Symbol(name)) => {
+                self.pos += 1;
+                if !self.next_if(&Token::LeftParen) {
+                    return Ok(Expr::Symbol(name));
+                }
+                let mut args = Vec::new();
+                if !self.next_if(&Token::RightParen) {
+                    loop {
+                        args.push(self.parse_union()?);
+                        if self.next_if(&Token::RightParen) {
+                            break;
+                        }
+                        if !self.next_if(&Token::Comma) {
+                            return self.error("expect ',' or ')'");
+                        }
+                    }
+                }
+                Ok(Expr::Function(name, args))
+            }
+            _ => self.error("expect a symbol or '('"),
+        }
+    }
+}
@@ -17,6 +17,7 @@ use std::collections::BinaryHeap;
 use std::collections::VecDeque;
 use std::fmt;
 use std::fmt::Debug;
+use std::io::Cursor;
 use std::iter::Rev;
 use std::ops::Bound;
 use std::ops::RangeBounds;
@@ -25,10 +26,19 @@ use std::ops::RangeInclusive;
 use dag_types::FlatSegment;
 use serde::Deserialize;
 use serde::Serialize;
+use vlqencoding::VLQDecode;
+use vlqencoding::VLQEncode;
 
 use crate::bsearch::BinarySearchBy;
+use crate::errors::programming;
+use crate::errors::BackendError;
+use crate::errors::DagError;
 use crate::id::Id;
 
+/// If one set has this many times fewer spans than the other, `intersection`
+/// uses binary search instead of a linear merge.
+const BSEARCH_INTERSECTION_RATIO: usize = 8;
+
 /// Range `low..=high`. `low` must be <= `high`.
 #[derive(Copy, Clone, Debug, Eq, Serialize, Deserialize)]
 pub struct Span {
@@ -81,6 +91,16 @@ impl Span {
         self.high.0 - self.low.0 + 1
     }
 
+    /// The lowest [`Id`] in this [`Span`].
+    pub fn low(self) -> Id {
+        self.low
+    }
+
+    /// The highest [`Id`] in this [`Span`].
+    pub fn high(self) -> Id {
+        self.high
+    }
+
     /// Get the n-th [`Id`] in this [`Span`].
     ///
     /// Similar to [`SpanSet`], ids are sorted in descending order.
@@ -323,6 +343,69 @@ impl SpanSet {
         self.spans.is_empty()
     }
 
+    /// Encode the set in a compact binary format. Decode it using
+    /// [`SpanSet::from_bytes`].
+    ///
+    /// ```plain,ignore
+    /// SPANSET := vlq(SPAN_COUNT) + SPAN*
+    /// SPAN := vlq(GAP) + vlq(HIGH - LOW)
+    /// ```
+    ///
+    /// Spans are in descending order. `GAP` is `HIGH` for the first span, and
+    /// `PREVIOUS_LOW - HIGH - 1` for the rest.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.spans.len() * 4 + 1);
+        buf.write_vlq(self.spans.len()).unwrap();
+        let mut previous_low: Option<Id> = None;
+        for span in self.spans.iter() {
+            let gap = match previous_low {
+                None => span.high.0,
+                Some(low) => low.0 - span.high.0 - 1,
+            };
+            buf.write_vlq(gap).unwrap();
+            buf.write_vlq(span.high.0 - span.low.0).unwrap();
+            previous_low = Some(span.low);
+        }
+        buf
+    }
+
+    /// Decode a set encoded by [`SpanSet::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> crate::Result<Self> {
+        let invalid = || -> DagError {
+            BackendError::Generic(format!("invalid SpanSet encoding ({} bytes)", bytes.len()))
+                .into()
+        };
+        let mut cur = Cursor::new(bytes);
+        let count: usize = cur.read_vlq()?;
+        let mut spans = VecDeque::with_capacity(count.min(64));
+        let mut previous_low: Option<Id> = None;
+        for _ in 0..count {
+            let gap: u64 = cur.read_vlq()?;
+            let len: u64 = cur.read_vlq()?;
+            if previous_low.is_some() && gap == 0 {
+                // Adjacent spans should be merged.
+                return Err(invalid());
+            }
+            let high = match previous_low {
+                None if gap > Id::MAX.0 => return Err(invalid()),
+                None => gap,
+                Some(low) => low
+                    .0
+                    .checked_sub(gap)
+                    .and_then(|v| v.checked_sub(1))
+                    .ok_or_else(invalid)?,
+            };
+            let low = high.checked_sub(len).ok_or_else(invalid)?;
+            let span = Span::new(Id(low), Id(high));
+            spans.push_back(span);
+            previous_low = Some(span.low);
+        }
+        if cur.position() as usize != bytes.len() {
+            return Err(invalid());
+        }
+        Ok(SpanSet { spans })
+    }
+
     /// Validate the spans are in the expected order and there are no mergable
     /// adjacent spans.
     #[cfg(debug_assertions)]
@@ -345,6 +428,12 @@ impl SpanSet {
         self.spans.iter().fold(0, |acc, span| acc + span.count())
     }
 
+    /// Count [`Span`]s in this [`SpanSet`]. Adjacent spans are always merged,
+    /// so this is a measure of fragmentation.
+    pub fn span_count(&self) -> usize {
+        self.spans.len()
+    }
+
     /// Tests if a given [`Id`] or [`Span`] is covered by this set.
     pub fn contains(&self, value: impl Into<Span>) -> bool {
         self.span_contains(value).is_some()
@@ -461,8 +550,42 @@ impl SpanSet {
         }
     }
 
+    /// Calculates the union of many sets in one pass.
+    ///
+    /// This is O(N * log(K)), N is the total number of spans and K is the
+    /// number of sets. Calling `union` repetitively is O(N * K).
+    pub fn union_all(sets: &[SpanSet]) -> SpanSet {
+        // (span.high, set index, span index), pop in DESC order.
+        let mut heap: BinaryHeap<(Id, usize, usize)> = sets
+            .iter()
+            .enumerate()
+            .filter_map(|(i, set)| set.spans.front().map(|span| (span.high, i, 0)))
+            .collect();
+        let total: usize = sets.iter().map(|set| set.spans.len()).sum();
+        let mut spans = VecDeque::with_capacity(total.min(32));
+        while let Some((_, i, j)) = heap.pop() {
+            push_with_union(&mut spans, sets[i].spans[j]);
+            if let Some(span) = sets[i].spans.get(j + 1) {
+                heap.push((span.high, i, j + 1));
+            }
+        }
+        let result = SpanSet { spans };
+        #[cfg(debug_assertions)]
+        result.validate();
+        result
+    }
+
     /// Calculates the intersection of two sets.
     pub fn intersection(&self, rhs: &SpanSet) -> SpanSet {
+        let (small, large) = if self.spans.len() <= rhs.spans.len() {
+            (self, rhs)
+        } else {
+            (rhs, self)
+        };
+        if small.spans.len().saturating_mul(BSEARCH_INTERSECTION_RATIO) < large.spans.len() {
+            return small.intersection_bsearch(large);
+        }
+
         let mut spans = VecDeque::with_capacity(self.spans.len().max(rhs.spans.len()).min(32));
         let push = |span: Span| push_with_union(&mut spans, span);
         intersect_iter(self.spans.iter().cloned(), rhs.spans.iter().cloned(), push);
@@ -473,6 +596,33 @@ impl SpanSet {
         result
     }
 
+    /// Calculates the intersection by binary searching spans of `self` in
+    /// `large`. This is O(len(self) * log(len(large))), faster than the
+    /// linear merge if `self` has much fewer spans.
+    fn intersection_bsearch(&self, large: &SpanSet) -> SpanSet {
+        let mut spans = VecDeque::with_capacity(self.spans.len().min(32));
+        for span in self.spans.iter() {
+            // Find the first (highest) span in `large` with `low <= span.high`.
+            let mut idx = match large.spans.bsearch_by(|probe| span.high.cmp(&probe.low)) {
+                Ok(idx) => idx,
+                Err(idx) => idx,
+            };
+            while let Some(other) = large.spans.get(idx) {
+                if other.high < span.low {
+                    break;
+                }
+                let low = other.low.max(span.low);
+                let high = other.high.min(span.high);
+                push_with_union(&mut spans, Span::new(low, high));
+                idx += 1;
+            }
+        }
+        let result = SpanSet { spans };
+        #[cfg(debug_assertions)]
+        result.validate();
+        result
+    }
+
     /// Calculates spans that are included only by this set, not `rhs`.
     pub fn difference(&self, rhs: &SpanSet) -> SpanSet {
         let mut spans = VecDeque::with_capacity(self.spans.len().max(rhs.spans.len()).min(32));
@@ -516,6 +666,61 @@ impl SpanSet {
         }
     }
 
+    /// Calculates spans that are included by exactly one of this set and
+    /// `rhs`.
+    pub fn symmetric_difference(&self, rhs: &SpanSet) -> SpanSet {
+        let mut spans = VecDeque::with_capacity((self.spans.len() + rhs.spans.len()).min(32));
+        let mut iter_left = self.spans.iter().cloned();
+        let mut iter_right = rhs.spans.iter().cloned();
+        let mut next_left = iter_left.next();
+        let mut next_right = iter_right.next();
+        let mut push = |span: Span| push_with_union(&mut spans, span);
+
+        loop {
+            match (next_left, next_right) {
+                (Some(left), Some(right)) => {
+                    if left.low > right.high {
+                        push(left);
+                        next_left = iter_left.next();
+                    } else if right.low > left.high {
+                        push(right);
+                        next_right = iter_right.next();
+                    } else {
+                        // |------------ left ------------|
+                        //           |------------ right ------------|
+                        // |- rest -|                     |-- span --|
+                        if left.high > right.high {
+                            push(Span::new(right.high + 1, left.high));
+                        } else if right.high > left.high {
+                            push(Span::new(left.high + 1, right.high));
+                        }
+                        let (rest_left, rest_right) = if left.low < right.low {
+                            (Span::try_from_bounds(left.low..right.low), None)
+                        } else {
+                            (None, Span::try_from_bounds(right.low..left.low))
+                        };
+                        next_left = rest_left.or_else(|| iter_left.next());
+                        next_right = rest_right.or_else(|| iter_right.next());
+                    }
+                }
+                (Some(span), None) => {
+                    push(span);
+                    next_left = iter_left.next();
+                }
+                (None, Some(span)) => {
+                    push(span);
+                    next_right = iter_right.next();
+                }
+                (None, None) => {
+                    let result = SpanSet { spans };
+                    #[cfg(debug_assertions)]
+                    result.validate();
+                    return result;
+                }
+            }
+        }
+    }
+
     /// Iterate `Id`s in descending order.
     pub fn iter_desc(&self) -> SpanSetIter<&SpanSet> {
         let len = self.spans.len();
@@ -541,6 +746,8 @@ impl SpanSet {
     }
 
     /// Iterate `Span`s in descending order.
+    ///
+    /// Use [`Span::low`] and [`Span::high`] to get the boundaries.
     pub fn iter_span_desc(&self) -> impl Iterator<Item = &Span> {
         self.as_spans().iter()
     }
@@ -727,6 +934,51 @@ impl SpanSet {
     }
 }
 
+/// Build a [`SpanSet`] from ids or spans pushed in arbitrary order.
+///
+/// Adjacent spans are merged. Overlapping spans are rejected by
+/// [`SpanSetBuilder::build`].
+#[derive(Default, Debug)]
+pub struct SpanSetBuilder {
+    spans: Vec<Span>,
+}
+
+impl SpanSetBuilder {
+    /// Construct an empty [`SpanSetBuilder`].
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add an id or a span. The order does not matter.
+    pub fn push(&mut self, span: impl Into<Span>) -> &mut Self {
+        self.spans.push(span.into());
+        self
+    }
+
+    /// Sort and merge the spans into a [`SpanSet`].
+    ///
+    /// Return an error if any of the spans overlap.
+    pub fn build(mut self) -> crate::Result<SpanSet> {
+        self.spans.sort_unstable_by(|a, b| b.high.cmp(&a.high));
+        let mut spans = VecDeque::with_capacity(self.spans.len().min(64));
+        for span in self.spans {
+            if let Some(last) = spans.back() {
+                if span.high >= last.low {
+                    return programming(format!(
+                        "SpanSetBuilder: {:?} overlaps with {:?}",
+                        span, last
+                    ));
+                }
+            }
+            push_with_union(&mut spans, span);
+        }
+        let result = SpanSet { spans };
+        #[cfg(debug_assertions)]
+        result.validate();
+        Ok(result)
+    }
+}
+
 /// Push a span to `VecDeque<Span>`. Try to union them in-place.
 fn push_with_union(spans: &mut VecDeque<Span>, span: Span) {
     if spans.is_empty() {
@@ -1062,6 +1314,24 @@ mod tests {
         assert_eq!(set.count(), 10 + 1 + 10);
     }
 
+    #[test]
+    fn test_span_count_and_iter() {
+        assert_eq!(SpanSet::empty().span_count(), 0);
+
+        let set = SpanSet::from_spans(vec![1..=10, 11..=11, 20..=20, 31..=40]);
+        assert_eq!(set.span_count(), 3);
+        let desc: Vec<_> = set
+            .iter_span_desc()
+            .map(|s| (s.low().0, s.high().0))
+            .collect();
+        assert_eq!(desc, [(31, 40), (20, 20), (1, 11)]);
+        let asc: Vec<_> = set
+            .iter_span_asc()
+            .map(|s| (s.low().0, s.high().0))
+            .collect();
+        assert_eq!(asc, [(1, 11), (20, 20), (31, 40)]);
+    }
+
     #[test]
     fn test_skip() {
         let set = SpanSet::from_spans(vec![1..=10, 20..=20, 31..=40]);
@@ -1182,6 +1452,26 @@ mod tests {
         assert_eq!(intersect(vec![10, 9, 8, 7], vec![5..=8]), vec![7..=8]);
     }
 
+    #[test]
+    fn test_intersection_bsearch_brute_force() {
+        let large = SpanSet::from_spans((0..40).map(|i| i * 5..=i * 5 + 2));
+        for low in 0..=210 {
+            for high in low..=210 {
+                let small = SpanSet::from_spans(vec![low..=high, high + 10..=high + 12]);
+                let mut expected = SpanSet::empty();
+                intersect_iter(
+                    small.spans.iter().cloned(),
+                    large.spans.iter().cloned(),
+                    |span| expected.push_span(span),
+                );
+                let result = small.intersection_bsearch(&large);
+                assert_eq!(result.as_spans(), expected.as_spans());
+                assert_eq!(small.intersection(&large).as_spans(), expected.as_spans());
+                assert_eq!(large.intersection(&small).as_spans(), expected.as_spans());
+            }
+        }
+    }
+
     fn difference(a: Vec<impl Into<Span>>, b: Vec<impl Into<Span>>) -> Vec<RangeInclusive<u64>> {
         let a = SpanSet::from_spans(a);
         let b = SpanSet::from_spans(b);
@@ -1246,6 +1536,99 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_builder() {
+        let mut builder = SpanSetBuilder::new();
+        builder
+            .push(5..=6)
+            .push(Id(3))
+            .push(20..=30)
+            .push(Id(4))
+            .push(1..=1);
+        let set = builder.build().unwrap();
+        assert_eq!(
+            set.as_spans(),
+            &vec![Span::from(20..=30), Span::from(3..=6), Span::from(1..=1)]
+        );
+
+        assert!(SpanSetBuilder::new().build().unwrap().is_empty());
+
+        let mut builder = SpanSetBuilder::new();
+        builder.push(5..=10).push(1..=5);
+        assert!(builder.build().is_err());
+
+        let mut builder = SpanSetBuilder::new();
+        builder.push(1..=10).push(Id(5));
+        assert!(builder.build().is_err());
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        for set in vec![
+            SpanSet::empty(),
+            SpanSet::full(),
+            SpanSet::from(0..=0),
+            SpanSet::from_spans(vec![5..=10, 15..=16, 18..=20, 23..=23, 26..=30, 35..=40]),
+            SpanSet::from_spans(vec![1..=1, 3..=3, 1000..=100000]),
+        ] {
+            let bytes = set.to_bytes();
+            let decoded = SpanSet::from_bytes(&bytes).unwrap();
+            assert_eq!(decoded.as_spans(), set.as_spans());
+        }
+
+        let set = SpanSet::from_spans(vec![0..=10, 20..=30]);
+        assert_eq!(set.to_bytes(), [2, 30, 10, 9, 10]);
+
+        // Invalid: truncated, trailing bytes, overlapping or adjacent spans.
+        assert!(SpanSet::from_bytes(&[2, 30, 10]).is_err());
+        assert!(SpanSet::from_bytes(&[1, 30, 10, 0]).is_err());
+        assert!(SpanSet::from_bytes(&[2, 30, 10, 25, 10]).is_err());
+        assert!(SpanSet::from_bytes(&[2, 30, 10, 0, 1]).is_err());
+    }
+
+    #[test]
+    fn test_union_all() {
+        let sets = [
+            SpanSet::from_spans(vec![5..=10, 15..=16, 30..=30]),
+            SpanSet::empty(),
+            SpanSet::from_spans(vec![11..=12, 20..=25]),
+            SpanSet::from_spans(vec![1..=3, 14..=14, 24..=29]),
+        ];
+        let expected = sets
+            .iter()
+            .fold(SpanSet::empty(), |acc, set| acc.union(set));
+        assert_eq!(SpanSet::union_all(&sets).as_spans(), expected.as_spans());
+        assert_eq!(
+            format!("{:?}", SpanSet::union_all(&sets)),
+            "1 2 3 5..=12 14 15 16 20..=30"
+        );
+        assert!(SpanSet::union_all(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_symmetric_difference() {
+        let set = SpanSet::from_spans(vec![5..=10, 15..=16, 18..=20, 23..=23, 26..=30, 35..=40]);
+        for low in 1..=45 {
+            for high in low..=45 {
+                let other = SpanSet::from_spans(vec![low..=high]);
+                let expected = set.difference(&other).union(&other.difference(&set));
+                assert_eq!(
+                    set.symmetric_difference(&other).as_spans(),
+                    expected.as_spans()
+                );
+                assert_eq!(
+                    other.symmetric_difference(&set).as_spans(),
+                    expected.as_spans()
+                );
+            }
+        }
+        assert!(set.symmetric_difference(&set).is_empty());
+        assert_eq!(
+            set.symmetric_difference(&SpanSet::empty()).as_spans(),
+            set.as_spans()
+        );
+    }
+
     #[test]
     fn test_iter() {
         let set = SpanSet::empty();
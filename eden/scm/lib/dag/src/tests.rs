@@ -5,6 +5,11 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+#[cfg(test)]
+use std::sync::Arc;
+#[cfg(test)]
+use std::sync::Mutex;
+
 use nonblocking::non_blocking_result as r;
 use tempfile::tempdir;
 pub use test_dag::TestDag;
@@ -13,10 +18,12 @@ pub use self::drawdag::DrawDag;
 use crate::id::Group;
 use crate::id::VertexName;
 use crate::nameset::SyncNameSetQuery;
+use crate::ops::CheckIntegrity;
 use crate::ops::DagAddHeads;
 use crate::ops::DagPersistent;
 use crate::ops::ImportAscii;
 use crate::render::render_namedag;
+use crate::utils;
 use crate::DagAlgorithm;
 use crate::IdMap;
 use crate::IdSet;
@@ -42,6 +49,15 @@ mod test_to_parents;
 #[cfg(test)]
 mod test_discontinuous;
 
+#[cfg(test)]
+mod test_query;
+
+#[cfg(test)]
+mod test_bisect;
+
+#[cfg(test)]
+mod test_transaction;
+
 #[cfg(test)]
 mod test_server;
 
@@ -51,17 +67,31 @@ pub mod dummy_dag;
 #[cfg(test)]
 pub(crate) use test_dag::ProtocolMonitor;
 
+#[cfg(test)]
+use crate::errors::DagError;
 #[cfg(test)]
 use crate::iddag::FirstAncestorConstraint;
+#[cfg(test)]
+use crate::iddag::IdDagAlgorithm;
+#[cfg(test)]
+use crate::namedag::ancestors_id_iter;
+#[cfg(test)]
+use crate::namedag::DagChange;
 use crate::namedag::MemNameDag;
 use crate::ops::IdConvert;
 #[cfg(test)]
+use crate::ops::ToIdSet;
+#[cfg(test)]
+use crate::protocol::IdMapToken;
+#[cfg(test)]
 use crate::protocol::Process;
 #[cfg(test)]
 use crate::protocol::RequestLocationToName;
 #[cfg(test)]
 use crate::protocol::RequestNameToLocation;
 #[cfg(test)]
+use crate::protocol::WireNameSet;
+#[cfg(test)]
 use crate::render::render_segment_dag;
 #[cfg(test)]
 use crate::Id;
@@ -337,6 +367,16 @@ fn test_generic_dag2<T: DagAlgorithm + DagAddHeads>(dag: T) -> Result<T> {
 
     assert_eq!(expand(r(dag.all())?), "A B C D E F G H I J K");
     assert_eq!(expand(r(dag.ancestors(nameset("H I")))?), "A B C D E F H I");
+    assert_eq!(
+        expand(r(dag.ancestors(nameset("H")))? ^ r(dag.ancestors(nameset("I")))?),
+        "A D E H I"
+    );
+    assert_eq!(expand(r(dag.ancestors_within(nameset("K"), 0))?), "K");
+    assert_eq!(expand(r(dag.ancestors_within(nameset("K"), 1))?), "H I K");
+    assert_eq!(
+        expand(r(dag.ancestors_within(nameset("K"), 10))?),
+        expand(r(dag.ancestors(nameset("K")))?)
+    );
     assert_eq!(expand(r(dag.first_ancestors(nameset("H I")))?), "A D E H I");
     assert_eq!(
         expand(r(dag.first_ancestors(nameset("J G D")))?),
@@ -355,6 +395,20 @@ fn test_generic_dag2<T: DagAlgorithm + DagAddHeads>(dag: T) -> Result<T> {
     assert_eq!(expand(r(dag.common_ancestors(nameset("G H")))?), "A B E");
     assert!(r(dag.is_ancestor(v("B"), v("K")))?);
     assert!(!r(dag.is_ancestor(v("K"), v("B")))?);
+    assert_eq!(r(dag.depth(v("A")))?, 0);
+    assert_eq!(r(dag.depth(v("F")))?, 1);
+    assert_eq!(r(dag.depth(v("I")))?, 2);
+    assert_eq!(r(dag.depth(v("K")))?, 3);
+    assert_eq!(
+        format!("{:?}", r(dag.shortest_path(v("A"), v("K")))?),
+        "Some([A, E, H, K])"
+    );
+    assert_eq!(
+        format!("{:?}", r(dag.shortest_path(v("A"), v("A")))?),
+        "Some([A])"
+    );
+    assert!(r(dag.shortest_path(v("K"), v("A")))?.is_none());
+    assert!(r(dag.shortest_path(v("G"), v("K")))?.is_none());
     assert_eq!(
         expand(r(dag.heads_ancestors(nameset("A E F D G")))?),
         "D F G"
@@ -364,6 +418,10 @@ fn test_generic_dag2<T: DagAlgorithm + DagAddHeads>(dag: T) -> Result<T> {
     let (reachable, unreachable) = r(dag.only_both(nameset("I"), nameset("G")))?;
     assert_eq!(expand(reachable), "C D F I");
     assert_eq!(expand(unreachable), expand(r(dag.ancestors(nameset("G")))?));
+    assert_eq!(
+        expand(r(dag.only(nameset("I"), nameset("")))?),
+        expand(r(dag.ancestors(nameset("I")))?)
+    );
     assert_eq!(expand(r(dag.descendants(nameset("F E")))?), "E F G H I J K");
 
     assert!(r(dag.is_ancestor(v("B"), v("J")))?);
@@ -755,6 +813,508 @@ fn test_namedag_reassign_master() -> crate::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_namedag_try_reload() -> crate::Result<()> {
+    let mut t = TestDag::new();
+    t.drawdag("A--B", &["B"]);
+    let mut dag2 = NameDag::open(t.dir.path().join("n"))?;
+    assert!(!dag2.try_reload()?);
+
+    // dag2 does not see new vertexes written by t until reload.
+    t.drawdag("B--C", &["C"]);
+    assert_eq!(expand(r(dag2.all())?), "A B");
+    assert!(dag2.try_reload()?);
+    assert_eq!(expand(r(dag2.all())?), "A B C");
+    assert!(!dag2.try_reload()?);
+
+    // In-memory changes need to be flushed first.
+    dag2 = from_ascii(dag2, "C--D");
+    assert!(dag2.try_reload().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_add_heads_with_progress() {
+    let v = |s: &str| VertexName::copy_from(s.as_bytes());
+    let mut parents: std::collections::HashMap<VertexName, Vec<VertexName>> = [
+        ("A", vec![]),
+        ("B", vec!["A"]),
+        ("C", vec!["B"]),
+        ("X", vec![]),
+        ("Y", vec!["Z", "X"]),
+    ]
+    .iter()
+    .map(|(k, ps)| (v(k), ps.iter().map(|p| v(p)).collect()))
+    .collect();
+    let heads: VertexListWithOptions = vec![v("B"), v("Y"), v("C")].into();
+
+    // Parents of Z are unknown. B is added. Y and C are not.
+    let mut dag = MemNameDag::new();
+    let (completed, result) =
+        nonblocking::non_blocking(dag.add_heads_with_progress(&parents, &heads)).unwrap();
+    assert!(result.is_err());
+    assert_eq!(format!("{:?}", completed), "[B]");
+    assert_eq!(expand(r(dag.all()).unwrap()), "A B");
+
+    // X was assigned before Z failed. It is removed from the IdMap.
+    assert!(!r(dag.contains_vertex_name(&v("X"))).unwrap());
+    assert!(r(dag.contains_vertex_name(&v("B"))).unwrap());
+
+    // Resume with the remaining heads.
+    parents.insert(v("Z"), vec![v("A")]);
+    let heads: VertexListWithOptions = vec![v("Y"), v("C")].into();
+    let (completed, result) =
+        nonblocking::non_blocking(dag.add_heads_with_progress(&parents, &heads)).unwrap();
+    assert!(result.unwrap());
+    assert_eq!(format!("{:?}", completed), "[Y, C]");
+    assert_eq!(expand(r(dag.all()).unwrap()), "A B C X Y Z");
+    assert_eq!(expand(r(dag.parents(v("Y").into())).unwrap()), "X Z");
+}
+
+#[test]
+fn test_derived_set_cache() {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+
+    let mut t = TestDag::new();
+    t.drawdag("A--B--C", &["C"]);
+    let dir = tempdir().unwrap();
+    let cache = crate::namedag::DerivedSetCache::new(dir.path().join("cache"));
+    let calculated = AtomicUsize::new(0);
+    let get = |dag: &NameDag, signature: &str| -> String {
+        let calculate = async {
+            calculated.fetch_add(1, SeqCst);
+            dag.ancestors("C".into()).await
+        };
+        let set = r(cache.get_or_calculate(dag, signature, calculate)).unwrap();
+        expand(set)
+    };
+
+    assert_eq!(get(&t.dag, "ancestors(C)"), "A B C");
+    assert_eq!(get(&t.dag, "ancestors(C)"), "A B C");
+    assert_eq!(calculated.load(SeqCst), 1);
+
+    // Cache is reused by another NameDag at the same version.
+    let dag2 = NameDag::open(t.dir.path().join("n")).unwrap();
+    assert_eq!(get(&dag2, "ancestors(C)"), "A B C");
+    assert_eq!(calculated.load(SeqCst), 1);
+
+    // Flushing changes invalidates the cache.
+    t.drawdag("B--X", &["X"]);
+    assert_eq!(get(&t.dag, "ancestors(C)"), "A B C");
+    assert_eq!(calculated.load(SeqCst), 2);
+
+    // Long signatures do not exceed file name limits.
+    let signature = format!("ancestors(C){}", " ".repeat(1000));
+    assert_eq!(get(&t.dag, &signature), "A B C");
+    assert_eq!(get(&t.dag, &signature), "A B C");
+    assert_eq!(calculated.load(SeqCst), 3);
+}
+
+#[test]
+fn test_depth_hints() {
+    let dag = from_ascii(MemNameDag::new(), "A-B-C B-D-E");
+    let set = r(dag.ancestors(nameset("C D"))).unwrap();
+    assert_eq!(set.hints().min_depth(), Some(0));
+    assert_eq!(set.hints().max_depth(), Some(2));
+
+    // Set operations keep the bounds.
+    let subset = set.clone() & nameset("A D");
+    assert_eq!(subset.hints().max_depth(), Some(2));
+    let subset = set.clone() - nameset("A");
+    assert_eq!(subset.hints().min_depth(), Some(0));
+    let set = set | r(dag.ancestors(nameset("E"))).unwrap();
+    assert_eq!(set.hints().max_depth(), Some(3));
+
+    let set = r(dag.ancestors(NameSet::empty())).unwrap();
+    assert_eq!(set.hints().max_depth(), None);
+}
+
+#[test]
+fn test_optimize() {
+    let mut t = TestDag::new();
+    t.drawdag(ASCII_DAG2, &["W"]);
+    let ancestors = expand(r(t.dag.ancestors("K".into())).unwrap());
+
+    let report = t.dag.optimize().unwrap();
+    assert_eq!(report.after.vertex_count, report.before.vertex_count);
+    assert!(report.after.segments_per_level[0] <= report.before.segments_per_level[0]);
+
+    t.reopen();
+    assert_eq!(t.dag.dag().segment_stats().unwrap(), report.after);
+    assert_eq!(expand(r(t.dag.ancestors("K".into())).unwrap()), ancestors);
+    assert!(r(t.dag.check_segments()).unwrap().is_empty());
+
+    // Optimizing again does not write anything.
+    fn dir_size(path: &std::path::Path) -> u64 {
+        let mut size = 0;
+        for entry in std::fs::read_dir(path).unwrap() {
+            let entry = entry.unwrap();
+            let meta = entry.metadata().unwrap();
+            size += if meta.is_dir() {
+                dir_size(&entry.path())
+            } else {
+                meta.len()
+            };
+        }
+        size
+    }
+    let size = dir_size(t.dir.path());
+    let report = t.dag.optimize().unwrap();
+    assert_eq!(report.after, report.before);
+    assert_eq!(dir_size(t.dir.path()), size);
+}
+
+#[test]
+fn test_deferred_high_level_segments() {
+    let mut t1 = TestDag::new();
+    t1.drawdag(ASCII_DAG2, &["W"]);
+
+    let mut t2 = TestDag::new();
+    t2.dag.set_defer_high_level_segments(true);
+    t2.drawdag(ASCII_DAG2, &["W"]);
+    assert_eq!(t2.dag.dag().max_level().unwrap(), 0);
+    assert_eq!(
+        expand(r(t2.dag.ancestors("K".into())).unwrap()),
+        expand(r(t1.dag.ancestors("K".into())).unwrap())
+    );
+
+    let handle = t2.dag.spawn_build_high_level_segments();
+    assert!(handle.join().unwrap().unwrap() > 0);
+    assert!(t2.dag.try_reload().unwrap());
+    assert_eq!(format!("{:?}", t2.dag.dag()), format!("{:?}", t1.dag.dag()));
+
+    // The setting is kept by operations that reopen the dag.
+    nonblocking::non_blocking(t2.strip("W")).unwrap();
+    assert!(t2.dag.dag().get_defer_high_level_segments());
+}
+
+#[test]
+fn test_head_ordering() {
+    let text = "A-B-C-D-E-F B-X-Y";
+
+    // By default, heads are assigned in the given order.
+    let mut t1 = TestDag::new();
+    t1.drawdag(text, &["Y", "F"]);
+    let id = |t: &TestDag, v: &str| format!("{:?}", r(t.dag.vertex_id(v.into())).unwrap());
+    assert_eq!(id(&t1, "Y"), "3");
+    assert_eq!(id(&t1, "F"), "7");
+
+    // The longest branch is assigned first and is contiguous.
+    let mut t2 = TestDag::new();
+    t2.dag
+        .set_head_ordering(Arc::new(crate::head_ordering::LongestBranchFirst));
+    t2.drawdag(text, &["Y", "F"]);
+    assert_eq!(id(&t2, "F"), "5");
+    assert_eq!(id(&t2, "Y"), "7");
+    let ancestors = r(t2.dag.ancestors("F".into())).unwrap();
+    let spans = r(t2.dag.to_id_set(&ancestors)).unwrap();
+    assert_eq!(spans.as_spans().len(), 1);
+}
+
+#[test]
+fn test_namedag_diff() {
+    let t1 = TestDag::draw("A--B--C--D B--E # master: C");
+    let t2 = TestDag::draw("A--B--C--F C--G");
+    let d = r(utils::diff(&t1.dag, &t2.dag)).unwrap();
+    assert_eq!(expand(d.only_in_left), "D E");
+    assert_eq!(expand(d.only_in_right), "F G");
+
+    let d = r(utils::diff(&t1.dag, &t1.dag)).unwrap();
+    assert_eq!(expand(d.only_in_left), "");
+    assert_eq!(expand(d.only_in_right), "");
+
+    let d = r(utils::diff_heads(&t1.dag, nameset("D F"))).unwrap();
+    assert_eq!(expand(d.only_in_left), "E");
+    assert_eq!(expand(d.only_in_right), "F");
+}
+
+#[test]
+fn test_sort_topo_by() {
+    let t = TestDag::draw(
+        r#"
+        A--B--C--D
+            \
+             E--F"#,
+    );
+    let sort = |set: &str, compare: fn(&VertexName, &VertexName) -> std::cmp::Ordering| {
+        let sorted = r(utils::sort_topo_by(&t.dag, nameset(set), compare)).unwrap();
+        format!("{:?}", sorted)
+    };
+    assert_eq!(sort("A B C D E F", |a, b| a.cmp(b)), "[F, E, D, C, B, A]");
+    assert_eq!(sort("A B C D E F", |a, b| b.cmp(a)), "[D, C, F, E, B, A]");
+
+    // A is an ancestor of C and F, even if B is not in the set.
+    assert_eq!(sort("A C F", |a, b| b.cmp(a)), "[C, F, A]");
+}
+
+#[test]
+fn test_namedag_octopus_merge() {
+    // E has 3 parents: B, C, D.
+    for master in ["", "# master: F"] {
+        let t = TestDag::draw(&format!("A-B-E C-E D-E E-F {}", master));
+        let dag = &t.dag;
+        assert_eq!(expand(r(dag.parents(nameset("E"))).unwrap()), "B C D");
+        assert_eq!(expand(r(dag.children(nameset("C D"))).unwrap()), "E");
+        assert_eq!(
+            expand(r(dag.ancestors(nameset("F"))).unwrap()),
+            "A B C D E F"
+        );
+        assert_eq!(
+            expand(r(dag.roots(r(dag.all()).unwrap())).unwrap()),
+            "A C D"
+        );
+        assert_eq!(expand(r(dag.heads(nameset("A B C D"))).unwrap()), "B C D");
+        assert_eq!(expand(r(dag.descendants(nameset("D"))).unwrap()), "D E F");
+        assert_eq!(
+            expand(r(dag.range(nameset("C"), nameset("F"))).unwrap()),
+            "C E F"
+        );
+        assert_eq!(
+            expand(r(dag.only(nameset("F"), nameset("B"))).unwrap()),
+            "C D E F"
+        );
+        assert_eq!(expand(r(dag.gca_all(nameset("B C"))).unwrap()), "");
+        assert_eq!(expand(r(dag.gca_all(nameset("E D"))).unwrap()), "D");
+        assert_eq!(
+            r(dag.first_ancestor_nth("F".into(), 2)).unwrap(),
+            Some("B".into())
+        );
+    }
+}
+
+#[test]
+fn test_namedag_count_ancestors() {
+    let t = TestDag::draw("A--B--C--D B--E--F # master: D");
+    let v = |name: &str| VertexName::copy_from(name.as_bytes());
+    assert_eq!(r(t.dag.count_ancestors(v("D"))).unwrap(), 4);
+    assert_eq!(r(t.dag.count_ancestors(v("B"))).unwrap(), 2);
+    assert_eq!(r(t.dag.count_ancestors(v("F"))).unwrap(), 4);
+    assert_eq!(
+        r(t.dag.count_range(nameset("B"), nameset("D F"))).unwrap(),
+        5
+    );
+    assert_eq!(r(t.dag.count_range(nameset("C"), nameset("F"))).unwrap(), 0);
+}
+
+#[test]
+fn test_namedag_ancestors_iter() {
+    let t = TestDag::draw(&format!("{} # master: W", ASCII_DAG2));
+    let names = |set: NameSet| -> Vec<String> {
+        set.iter()
+            .unwrap()
+            .map(|v| String::from_utf8_lossy(v.unwrap().as_ref()).to_string())
+            .collect()
+    };
+    for heads in ["W", "K N", "E T S", "A"] {
+        let iter = r(t.dag.ancestors_iter(nameset(heads))).unwrap();
+        let set = r(t.dag.ancestors(nameset(heads))).unwrap();
+        assert_eq!(names(iter), names(set));
+    }
+
+    // Taking a few items does not visit all ancestors.
+    let iter = r(t.dag.ancestors_iter(nameset("W"))).unwrap();
+    let set = r(t.dag.ancestors(nameset("W"))).unwrap();
+    assert_eq!(names(iter.take(3)), names(set.take(3)));
+
+    // Parents are looked up only for yielded items, except the last one.
+    let heads = r(t.dag.to_id_set(&nameset("W"))).unwrap();
+    let lookups = std::cell::Cell::new(0);
+    let parent_ids = |id| {
+        lookups.set(lookups.get() + 1);
+        t.dag.dag().parent_ids(id)
+    };
+    let ids: Vec<Id> = ancestors_id_iter(heads.clone(), parent_ids)
+        .take(3)
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(ids.len(), 3);
+    assert_eq!(lookups.get(), 2);
+
+    lookups.set(0);
+    let ids: Vec<Id> = ancestors_id_iter(heads, parent_ids)
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(ids.len(), set.count().unwrap());
+    assert_eq!(lookups.get(), ids.len());
+}
+
+#[test]
+fn test_namedag_phases() {
+    let mut t = TestDag::draw("A--B--C--D B--E--F");
+    t.dag.set_public_heads(Some(vec!["C".into(), "E".into()]));
+    assert_eq!(expand(r(t.dag.public()).unwrap()), "A B C E");
+    assert_eq!(expand(r(t.dag.draft()).unwrap()), "D F");
+
+    // Public heads are kept after reassigning ids.
+    nonblocking::non_blocking(t.flush("D")).unwrap();
+    assert_eq!(expand(r(t.dag.public()).unwrap()), "A B C E");
+    assert_eq!(expand(r(t.dag.draft()).unwrap()), "D F");
+
+    // Stripped heads are ignored.
+    nonblocking::non_blocking(t.strip("E")).unwrap();
+    assert_eq!(expand(r(t.dag.public()).unwrap()), "A B C");
+    assert_eq!(expand(r(t.dag.draft()).unwrap()), "D");
+
+    // Public heads and spans are persisted on flush.
+    let dag2 = NameDag::open(t.dir.path().join("n")).unwrap();
+    assert_eq!(expand(r(dag2.public()).unwrap()), "A B C");
+    assert_eq!(expand(r(dag2.draft()).unwrap()), "D");
+
+    // Without public heads, the master group is public.
+    t.dag.set_public_heads(None);
+    assert_eq!(expand(r(t.dag.public()).unwrap()), "A B C D");
+    assert_eq!(expand(r(t.dag.draft()).unwrap()), "");
+}
+
+#[test]
+fn test_namedag_stats() {
+    let t = TestDag::draw(&format!("{} # master: W", ASCII_DAG2));
+    let stats = t.dag.stats().unwrap();
+    let count = r(t.dag.all()).unwrap().count().unwrap();
+    assert_eq!(stats.id_count, count as u64);
+    assert_eq!(stats.idmap_entry_count, count);
+    assert_eq!(stats.segment_counts.len(), stats.max_level as usize + 1);
+    assert!(stats.max_level > 0);
+    assert_eq!(
+        stats.average_flat_segment_length,
+        count as f64 / stats.segment_counts[0] as f64
+    );
+    assert!(stats.idmap_disk_size > 0);
+    assert!(stats.iddag_disk_size > 0);
+}
+
+#[test]
+fn test_namedag_change_listener() {
+    let mut t = TestDag::new();
+    let changes: Arc<Mutex<Vec<DagChange>>> = Default::default();
+    let changes_clone = changes.clone();
+    t.dag.subscribe(Arc::new(move |change: &DagChange| {
+        changes_clone.lock().unwrap().push(change.clone())
+    }));
+    let take = || -> Vec<String> {
+        let changes = std::mem::take(&mut *changes.lock().unwrap());
+        changes
+            .into_iter()
+            .map(|c| format!("+{:?} -{:?}", c.added_heads, c.removed_heads))
+            .collect()
+    };
+
+    // flush does not report heads again.
+    t.drawdag("A-B-C", &["C"]);
+    assert_eq!(take(), ["+[C] -[]"]);
+
+    // Existing heads are not reported.
+    t.drawdag("B-D C-E", &[]);
+    assert_eq!(take(), ["+[D, E] -[]"]);
+    nonblocking::non_blocking(t.flush("")).unwrap();
+    assert_eq!(take(), [] as [String; 0]);
+
+    nonblocking::non_blocking(t.strip("C")).unwrap();
+    assert_eq!(take(), ["+[] -[E]"]);
+
+    // Listeners are kept after strip.
+    t.drawdag("D-F", &[]);
+    assert_eq!(take(), ["+[F] -[]"]);
+}
+
+#[test]
+fn test_namedag_version() {
+    let version = |t: &TestDag| nonblocking::non_blocking(t.dag.version()).unwrap().unwrap();
+
+    // Same vertexes and edges, different id assignments.
+    let mut t1 = TestDag::draw("A-B-C B-D # master: C");
+    let t2 = TestDag::draw("A-B-D B-C # master: D");
+    assert_eq!(version(&t1), version(&t2));
+
+    // Same vertexes and edges, different insertion order.
+    let mut t3 = TestDag::new();
+    t3.drawdag("A-B-D", &[]);
+    t3.drawdag("B-C", &["C"]);
+    assert_eq!(version(&t1), version(&t3));
+
+    let v1 = version(&t1);
+    t1.drawdag("C-E", &[]);
+    assert_ne!(version(&t1), v1);
+    nonblocking::non_blocking(t1.strip("E")).unwrap();
+    assert_eq!(version(&t1), v1);
+
+    // Segment layout does not matter.
+    t1.dag.optimize().unwrap();
+    assert_eq!(version(&t1), v1);
+
+    // MemNameDag has a version too.
+    let mem = from_ascii(MemNameDag::new(), "A-B-C B-D");
+    assert_eq!(r(mem.version()).unwrap(), v1);
+}
+
+#[test]
+fn test_wire_name_set() {
+    let server = TestDag::draw("A-B-C-D-E B-X # master: E");
+    let client = TestDag::draw("A-B-C-D-E # master: E");
+    let other = TestDag::draw("A-B-C # master: C");
+    let token = r(IdMapToken::from_dag(&client.dag)).unwrap();
+    let set = r(server.dag.ancestors("D".into())).unwrap();
+
+    // Same ids: encoded as spans.
+    let wire = r(WireNameSet::from_set(&set, &server.dag, Some(&token))).unwrap();
+    assert!(matches!(wire, WireNameSet::Spans { .. }));
+    let wire = WireNameSet::from_bytes(&wire.to_bytes().unwrap()).unwrap();
+    assert_eq!(
+        expand(r(wire.clone().to_set(&client.dag)).unwrap()),
+        "A B C D"
+    );
+
+    // Spans are rejected by a receiver with different ids.
+    assert!(matches!(
+        r(wire.to_set(&other.dag)),
+        Err(DagError::NeedSlowPath(_))
+    ));
+
+    // Different or unknown ids, or not backed by master ids: encoded as names.
+    let other_token = r(IdMapToken::from_dag(&other.dag)).unwrap();
+    let wire = r(WireNameSet::from_set(&set, &server.dag, Some(&other_token))).unwrap();
+    assert!(matches!(wire, WireNameSet::Names(_)));
+    assert_eq!(expand(r(wire.to_set(&other.dag)).unwrap()), "A B C D");
+    let wire = r(WireNameSet::from_set(&set, &server.dag, None)).unwrap();
+    assert!(matches!(wire, WireNameSet::Names(_)));
+    let set = r(server.dag.ancestors("X".into())).unwrap();
+    let wire = r(WireNameSet::from_set(&set, &server.dag, Some(&token))).unwrap();
+    assert!(matches!(wire, WireNameSet::Names(_)));
+    let set = nameset("X E");
+    let wire = r(WireNameSet::from_set(&set, &server.dag, Some(&token))).unwrap();
+    let wire = WireNameSet::from_bytes(&wire.to_bytes().unwrap()).unwrap();
+    assert_eq!(expand(r(wire.to_set(&client.dag)).unwrap()), "E X");
+
+    // Same master heads at the same ids, different interior ids.
+    let v = |name: &str| VertexName::copy_from(name.as_bytes());
+    let mut t1 = TestDag::new();
+    t1.drawdag("A-X", &["X"]);
+    t1.drawdag("A-X-M A-Y-M", &["M"]);
+    let mut t2 = TestDag::new();
+    t2.drawdag("A-Y", &["Y"]);
+    t2.drawdag("A-X-M A-Y-M", &["M"]);
+    assert_eq!(r(t1.dag.vertex_id(v("M"))).unwrap(), Id(3));
+    assert_eq!(r(t2.dag.vertex_id(v("M"))).unwrap(), Id(3));
+    assert_ne!(
+        r(t1.dag.vertex_id(v("X"))).unwrap(),
+        r(t2.dag.vertex_id(v("X"))).unwrap()
+    );
+    let token1 = r(IdMapToken::from_dag(&t1.dag)).unwrap();
+    let token2 = r(IdMapToken::from_dag(&t2.dag)).unwrap();
+    assert_ne!(token1, token2);
+    let set = r(t1.dag.ancestors("X".into())).unwrap();
+    let wire = r(WireNameSet::from_set(&set, &t1.dag, Some(&token2))).unwrap();
+    assert!(matches!(wire, WireNameSet::Names(_)));
+    assert_eq!(expand(r(wire.to_set(&t2.dag)).unwrap()), "A X");
+    let wire = r(WireNameSet::from_set(&set, &t1.dag, Some(&token1))).unwrap();
+    assert!(matches!(
+        r(wire.to_set(&t2.dag)),
+        Err(DagError::NeedSlowPath(_))
+    ));
+}
+
 #[test]
 fn test_namedag_reassign_non_master() {
     let mut t = TestDag::new();
@@ -851,6 +1411,15 @@ fn test_segment_ancestors_example1() {
             ancestors.into_iter().map(Id).collect::<Vec<Id>>(),
         );
     }
+
+    // is_ancestor matches ancestors for all pairs.
+    for a in 0..=11 {
+        for b in 0..=11 {
+            let (a, b) = (Id(a), Id(b));
+            let expected = dag.ancestors(b.into()).unwrap().contains(a);
+            assert_eq!(dag.is_ancestor(a, b).unwrap(), expected, "{:?} {:?}", a, b);
+        }
+    }
 }
 
 #[test]
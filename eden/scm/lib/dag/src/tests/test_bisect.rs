@@ -0,0 +1,75 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use futures::TryStreamExt;
+
+use super::nameset;
+use super::TestDag;
+use crate::bisect::bisect;
+use crate::NameDag;
+
+async fn bisect_str(dag: &NameDag, good: &str, bad: &str, skip: &str) -> (String, String) {
+    let step = bisect(dag, nameset(good), nameset(bad), nameset(skip))
+        .await
+        .unwrap();
+    let mut candidates: Vec<String> = step
+        .candidates
+        .iter()
+        .await
+        .unwrap()
+        .map_ok(|v| String::from_utf8_lossy(v.as_ref()).to_string())
+        .try_collect()
+        .await
+        .unwrap();
+    candidates.sort();
+    let next = match step.next {
+        Some(v) => String::from_utf8_lossy(v.as_ref()).to_string(),
+        None => "None".to_string(),
+    };
+    (candidates.join(" "), next)
+}
+
+#[tokio::test]
+async fn test_bisect_linear() {
+    let dag = TestDag::draw("A-B-C-D-E-F-G-H-I-J");
+    let dag = &dag.dag;
+
+    let (candidates, next) = bisect_str(dag, "A", "J", "").await;
+    assert_eq!(candidates, "B C D E F G H I J");
+    assert_eq!(next, "F");
+
+    assert_eq!(bisect_str(dag, "A", "F", "").await.1, "D");
+    assert_eq!(bisect_str(dag, "A", "F", "D").await.1, "C");
+
+    // Nothing left to test.
+    let (candidates, next) = bisect_str(dag, "A", "F", "B C D E").await;
+    assert_eq!(candidates, "B C D E F");
+    assert_eq!(next, "None");
+    let (candidates, next) = bisect_str(dag, "E", "F", "").await;
+    assert_eq!(candidates, "F");
+    assert_eq!(next, "None");
+}
+
+#[tokio::test]
+async fn test_bisect_merge() {
+    let dag = TestDag::draw(
+        r#"
+        A---B---C---D---E
+             \         /
+              F---G---H"#,
+    );
+    let dag = &dag.dag;
+
+    let (candidates, next) = bisect_str(dag, "A", "E", "").await;
+    assert_eq!(candidates, "B C D E F G H");
+    // Testing D, G or H leaves at most 4 candidates.
+    assert!(["D", "G", "H"].contains(&next.as_str()), "{}", next);
+
+    let (candidates, next) = bisect_str(dag, "A H", "E", "").await;
+    assert_eq!(candidates, "C D E");
+    assert_eq!(next, "D");
+}
@@ -6,9 +6,11 @@
  */
 
 use super::TestDag;
+use crate::idmap::IdMapWrite;
 use crate::ops::CheckIntegrity;
 use crate::ops::DagAlgorithm;
 use crate::Group;
+use crate::VerifyLevel;
 
 #[tokio::test]
 async fn test_isomorphic_graph_with_different_segments() {
@@ -85,10 +87,25 @@ async fn test_non_isomorphic_graphs() {
     );
     assert_eq!(
         quick_check_graphs("A B C", "A B").await,
-        ["range C::C with parents []: cannot resolve range on the other graph: VertexNotFound(C)"]
+        ["range C::C with parents []: cannot resolve range on the other graph: VertexNotFound { name: C, suggestions: [] }"]
     );
 }
 
+#[tokio::test]
+async fn test_verify() {
+    let mut dag = TestDag::draw("A--B--C--D # master: B");
+    for level in [VerifyLevel::Quick, VerifyLevel::Segments, VerifyLevel::Full] {
+        assert!(dag.dag.verify(level).await.unwrap().is_ok());
+    }
+
+    // Remove C (N0) from the IdMap. Only the full check notices.
+    let id = Group::NON_MASTER.min_id();
+    dag.dag.remove_range(id, id).await.unwrap();
+    assert!(dag.dag.verify(VerifyLevel::Segments).await.unwrap().is_ok());
+    let report = dag.dag.verify(VerifyLevel::Full).await.unwrap();
+    assert_eq!(report.idmap_problems, ["N0 is missing in IdMap"]);
+}
+
 async fn quick_check_graphs(ascii1: &str, ascii2: &str) -> Vec<String> {
     let dag1 = TestDag::draw(ascii1);
     let dag2 = TestDag::draw(ascii2);
@@ -0,0 +1,98 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use futures::TryStreamExt;
+
+use super::TestDag;
+use crate::errors::DagError;
+use crate::query::parse;
+use crate::query::query;
+use crate::query::Expr;
+use crate::query::VertexNameResolver;
+
+#[test]
+fn test_parse() {
+    let s = |name: &str| Expr::Symbol(name.to_string());
+    let f = |name: &str, args: Vec<Expr>| Expr::Function(name.to_string(), args);
+
+    assert_eq!(parse("A").unwrap(), s("A"));
+    assert_eq!(parse("'a b'").unwrap(), s("a b"));
+    assert_eq!(parse("all()").unwrap(), f("all", vec![]));
+    assert_eq!(parse("A::B").unwrap(), f("range", vec![s("A"), s("B")]));
+    assert_eq!(parse("::B").unwrap(), f("ancestors", vec![s("B")]));
+    assert_eq!(parse("A::").unwrap(), f("descendants", vec![s("A")]));
+    assert_eq!(
+        parse("A | B & C").unwrap(),
+        Expr::Union(
+            Box::new(s("A")),
+            Box::new(Expr::Intersection(Box::new(s("B")), Box::new(s("C"))))
+        )
+    );
+    assert_eq!(
+        parse("(A + B) - C").unwrap(),
+        Expr::Difference(
+            Box::new(Expr::Union(Box::new(s("A")), Box::new(s("B")))),
+            Box::new(s("C"))
+        )
+    );
+    assert_eq!(
+        parse("only(A, B::)").unwrap(),
+        f("only", vec![s("A"), f("descendants", vec![s("B")])])
+    );
+
+    for invalid in ["", "A B", "(A", "f(A,", "A:B", "'A", "A ! B", "A & "] {
+        assert!(
+            matches!(parse(invalid), Err(DagError::InvalidQuery(_))),
+            "{:?} should be invalid",
+            invalid
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_query() {
+    let dag = TestDag::draw(
+        r#"
+        A---B---C---D---E
+              /  \     /
+         F---G    H---I"#,
+    );
+    let q = |text: &'static str| {
+        let dag = &dag.dag;
+        async move {
+            let set = query(dag, text, &VertexNameResolver).await?;
+            let mut names: Vec<String> = set
+                .iter()
+                .await?
+                .map_ok(|v| String::from_utf8_lossy(v.as_ref()).to_string())
+                .try_collect()
+                .await?;
+            names.sort();
+            Ok::<_, crate::Error>(names.join(" "))
+        }
+    };
+
+    assert_eq!(q("::C").await.unwrap(), "A B C F G");
+    assert_eq!(q("H::").await.unwrap(), "E H I");
+    assert_eq!(q("B::E").await.unwrap(), "B C D E H I");
+    assert_eq!(q("ancestors(E) - ancestors(D)").await.unwrap(), "E H I");
+    assert_eq!(q("heads(A::C | G)").await.unwrap(), "C");
+    assert_eq!(q("roots(all()) & ::E").await.unwrap(), "A F");
+    assert_eq!(q("only(E, D)").await.unwrap(), "E H I");
+    assert_eq!(q("gca(D, I)").await.unwrap(), "C");
+    assert_eq!(q("parents(merges(all()))").await.unwrap(), "B D G I");
+    assert!(q("unknown(A)").await.is_err());
+    assert!(q("heads(A, B)").await.is_err());
+    for invalid in ["gca()", "common_ancestors()", "only(A)", "all(A)"] {
+        assert!(
+            matches!(q(invalid).await, Err(DagError::InvalidQuery(_))),
+            "{:?} should be invalid",
+            invalid
+        );
+    }
+    assert!(q("::Z").await.is_err());
+}
@@ -11,6 +11,7 @@ use futures::TryStreamExt;
 
 use super::ProtocolMonitor;
 use super::TestDag;
+use crate::errors::DagError;
 use crate::ops::DagAddHeads;
 use crate::ops::DagAlgorithm;
 use crate::ops::DagExportPullData;
@@ -298,6 +299,50 @@ async fn test_pull_overlap() {
     );
 }
 
+#[tokio::test]
+async fn test_graft_master() {
+    let mut server = TestDag::new();
+    server.drawdag("A-B-C-D-E-F", &["F"]);
+    let mut client = server.client().await;
+    client.drawdag("A-B", &["B"]);
+
+    client.dag.graft_master(&server.dag).await.unwrap();
+    assert_eq!(server.render_graph(), client.render_graph());
+
+    // No-op if already up-to-date.
+    client.dag.graft_master(&server.dag).await.unwrap();
+    assert_eq!(server.render_graph(), client.render_graph());
+
+    // Local master heads must be known by the other graph.
+    let mut client = server.client().await;
+    client.drawdag("A-X", &["X"]);
+    let e = client.dag.graft_master(&server.dag).await.unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "master diverged: local master heads ([X]) are not known by the other graph"
+    );
+
+    // Local master heads must be in the master group of the other graph.
+    server.drawdag("F-Y", &[]);
+    let mut client = server.client().await;
+    client.drawdag("F-Y", &["Y"]);
+    let e = client.dag.graft_master(&server.dag).await.unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "master diverged: local master heads ([Y]) are not in the master group of the other graph"
+    );
+
+    // Local master group must match the ancestors in the other graph.
+    let mut client = TestDag::new();
+    client.drawdag("A-C", &["C"]);
+    let e = client.dag.graft_master(&server.dag).await.unwrap_err();
+    assert!(matches!(e, DagError::MasterDiverged(_)));
+    assert_eq!(
+        e.to_string(),
+        "master diverged: local master group has 2 vertexes but their ancestors in the other graph have 3"
+    );
+}
+
 #[tokio::test]
 async fn test_pull_lazy_with_merges() {
     // Test fast-forward pull on a lazy graph with merges.
@@ -544,7 +589,7 @@ async fn test_resolve_mixed_result() {
         let ids = client.dag.vertex_id_batch(&names).await;
         assert_eq!(
             format!("{:?}", ids),
-            "Ok([Ok(0), Ok(1), Ok(2), Ok(3), Ok(4), Ok(5), Ok(6), Ok(7), Err(VertexNotFound(I)), Err(VertexNotFound(J)), Err(VertexNotFound(X))])",
+            "Ok([Ok(0), Ok(1), Ok(2), Ok(3), Ok(4), Ok(5), Ok(6), Ok(7), Err(VertexNotFound { name: I, suggestions: [] }), Err(VertexNotFound { name: J, suggestions: [] }), Err(VertexNotFound { name: X, suggestions: [] })])",
         );
         assert_eq!(
             client.output(),
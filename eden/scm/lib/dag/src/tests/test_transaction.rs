@@ -0,0 +1,67 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use futures::TryStreamExt;
+
+use super::nameset;
+use super::DrawDag;
+use super::TestDag;
+use crate::ops::DagAlgorithm;
+use crate::Group;
+use crate::NameDag;
+use crate::VertexListWithOptions;
+use crate::VertexName;
+
+#[tokio::test]
+async fn test_transaction_commit_and_discard() {
+    let mut dag = TestDag::draw("A-B-C # master: C");
+    let parents = DrawDag::from("A-B-C-D-E B-F");
+    let heads = |names: &str| {
+        let names: Vec<VertexName> = names
+            .split_whitespace()
+            .map(|n| VertexName::copy_from(n.as_bytes()))
+            .collect();
+        VertexListWithOptions::from(names).with_highest_group(Group::MASTER)
+    };
+
+    // Dropped transactions do not change the graph, in memory or on disk.
+    {
+        let mut tx = dag.dag.transaction().unwrap();
+        tx.add_heads(&parents, &heads("E")).await.unwrap();
+        assert_eq!(all(tx.staged()).await, "A B C D E");
+        tx.strip(&nameset("C")).await.unwrap();
+        assert_eq!(all(tx.staged()).await, "A B");
+    }
+    assert_eq!(all(&dag.dag).await, "A B C");
+    dag.reopen();
+    assert_eq!(all(&dag.dag).await, "A B C");
+
+    // Committed transactions apply all changes together.
+    let mut tx = dag.dag.transaction().unwrap();
+    tx.add_heads(&parents, &heads("E F")).await.unwrap();
+    tx.strip(&nameset("D")).await.unwrap();
+    tx.commit().unwrap();
+    assert_eq!(all(&dag.dag).await, "A B C F");
+    dag.reopen();
+    assert_eq!(all(&dag.dag).await, "A B C F");
+}
+
+async fn all(dag: &NameDag) -> String {
+    let mut names: Vec<String> = dag
+        .all()
+        .await
+        .unwrap()
+        .iter()
+        .await
+        .unwrap()
+        .map_ok(|v| String::from_utf8_lossy(v.as_ref()).to_string())
+        .try_collect()
+        .await
+        .unwrap();
+    names.sort();
+    names.join(" ")
+}
@@ -5,6 +5,7 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
@@ -14,6 +15,7 @@ use futures::future::BoxFuture;
 use futures::TryStreamExt;
 
 use crate::errors::programming;
+use crate::DagAlgorithm;
 use crate::Result;
 use crate::Set;
 use crate::Vertex;
@@ -177,6 +179,109 @@ pub async fn filter_known<'a>(
     Ok(known)
 }
 
+/// Vertexes that exist in one graph but not the other.
+/// See [`diff`] and [`diff_heads`].
+#[derive(Debug, Clone)]
+pub struct DagDiff {
+    /// Vertexes only in the left graph.
+    pub only_in_left: Set,
+
+    /// Vertexes only in the right graph.
+    pub only_in_right: Set,
+}
+
+/// Compare vertexes in two graphs.
+///
+/// Each vertex of one graph is looked up by name in the other graph, so
+/// this takes `O(N)` lookups, `N` being the size of both graphs. Ids are
+/// not compared across graphs, since the same Id might be assigned to
+/// different vertexes. Span arithmetic is only used if both graphs share
+/// the same in-memory IdMap version history, for example, comparing a
+/// graph with its own snapshot.
+///
+/// Only vertex names are compared. Use
+/// `CheckIntegrity::check_isomorphic_graph` to compare the shape of graphs.
+pub async fn diff(left: &dyn DagAlgorithm, right: &dyn DagAlgorithm) -> Result<DagDiff> {
+    let left_all = left.all().await?;
+    let right_all = right.all().await?;
+    let only_in_left = left_all.difference(&right_all);
+    let only_in_right = right_all.difference(&left_all);
+    Ok(DagDiff {
+        only_in_left,
+        only_in_right,
+    })
+}
+
+/// Compare vertexes in `dag` with a graph that is only known by its `heads`,
+/// for example, a remote replica.
+///
+/// `only_in_left` contains vertexes in `dag` that are not ancestors of
+/// `heads`. `only_in_right` contains `heads` that are unknown to `dag`.
+pub async fn diff_heads(dag: &dyn DagAlgorithm, heads: Set) -> Result<DagDiff> {
+    let all = dag.all().await?;
+    let known_heads = heads.intersection(&all);
+    let only_in_right = heads.difference(&all);
+    let only_in_left = all.difference(&dag.ancestors(known_heads).await?);
+    Ok(DagDiff {
+        only_in_left,
+        only_in_right,
+    })
+}
+
+/// Sort `set` topologically, heads first. When multiple vertexes can be
+/// output next, the one that is the greatest according to `compare` is
+/// picked. For example, `compare` can compare commit dates so newer commits
+/// are output first.
+///
+/// Ancestry between vertexes in `set` is preserved even if they are not
+/// directly connected (see `DagAlgorithm::subdag`).
+///
+/// Time complexity is `O(N * W)`, `W` is the width of the graph.
+pub async fn sort_topo_by(
+    dag: &dyn DagAlgorithm,
+    set: Set,
+    compare: impl Fn(&Vertex, &Vertex) -> Ordering,
+) -> Result<Vec<Vertex>> {
+    let subdag = dag.subdag(set).await?;
+    let vertexes: Vec<Vertex> = subdag.all().await?.iter().await?.try_collect().await?;
+
+    let mut parents: HashMap<Vertex, Vec<Vertex>> = HashMap::with_capacity(vertexes.len());
+    let mut child_count: HashMap<Vertex, usize> = HashMap::with_capacity(vertexes.len());
+    for vertex in &vertexes {
+        let vertex_parents = subdag.parent_names(vertex.clone()).await?;
+        for parent in &vertex_parents {
+            *child_count.entry(parent.clone()).or_default() += 1;
+        }
+        parents.insert(vertex.clone(), vertex_parents);
+    }
+
+    // Vertexes with all children output.
+    let mut ready: Vec<Vertex> = vertexes
+        .iter()
+        .filter(|v| !child_count.contains_key(v))
+        .cloned()
+        .collect();
+    let mut result = Vec::with_capacity(vertexes.len());
+    while let Some(index) = ready
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| compare(a, b))
+        .map(|(i, _)| i)
+    {
+        let vertex = ready.swap_remove(index);
+        for parent in &parents[&vertex] {
+            let count = child_count.get_mut(parent).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                ready.push(parent.clone());
+            }
+        }
+        result.push(vertex);
+    }
+
+    Ok(result)
+}
+
 /// Produce an order of "nodes" in a graph for cleaner graph output.
 /// For example, turn:
 ///
@@ -277,6 +382,19 @@ pub fn beautify_graph(parents: &[Vec<usize>], priorities: &[usize]) -> Vec<usize
     output
 }
 
+/// 64-bit FNV-1a hash of `data`.
+///
+/// Unlike `DefaultHasher`, the result does not change across processes or
+/// Rust versions, so it can be compared with hashes calculated elsewhere.
+pub(crate) fn stable_hash(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
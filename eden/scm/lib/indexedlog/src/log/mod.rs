@@ -507,6 +507,11 @@ impl Log {
                 return Ok(self.meta.primary_len);
             }
 
+            if self.open_options.read_only {
+                let dir = self.dir.as_opt_path().unwrap();
+                return Err(crate::Error::path(dir, "cannot write to a read-only Log"));
+            }
+
             // Take the lock so no other `flush` runs for this directory. Then reload meta, append
             // log, then update indexes.
             let dir = self.dir.as_opt_path().unwrap().to_path_buf();
@@ -647,6 +652,7 @@ impl Log {
                     Some(&self.indexes)
                 },
                 self.open_options.fsync,
+                self.open_options.read_only,
             )?;
 
             self.disk_buf = disk_buf;
@@ -1299,6 +1305,7 @@ impl Log {
         mem_buf: &Pin<Box<Vec<u8>>>,
         reuse_indexes: Option<&Vec<Index>>,
         fsync: bool,
+        read_only: bool,
     ) -> crate::Result<(Bytes, Vec<Index>)> {
         let primary_buf = match dir.as_opt_path() {
             Some(dir) => mmap_path(&dir.join(PRIMARY_FILE), meta.primary_len)?,
@@ -1325,6 +1332,7 @@ impl Log {
                         index_len,
                         key_buf.clone(),
                         fsync,
+                        read_only,
                     )?);
                 }
                 indexes
@@ -1337,7 +1345,7 @@ impl Log {
                 for (index, def) in indexes.iter().zip(index_defs) {
                     let index_len = meta.indexes.get(&def.metaname()).cloned().unwrap_or(0);
                     let index = if index_len > Self::get_index_log_len(index, true).unwrap_or(0) {
-                        Self::load_index(dir, &def, index_len, key_buf.clone(), fsync)?
+                        Self::load_index(dir, &def, index_len, key_buf.clone(), fsync, read_only)?
                     } else {
                         let mut index = index.try_clone()?;
                         index.key_buf = key_buf.clone();
@@ -1363,18 +1371,22 @@ impl Log {
         len: u64,
         buf: Arc<dyn ReadonlyBuffer + Send + Sync>,
         fsync: bool,
+        read_only: bool,
     ) -> crate::Result<Index> {
         match dir.as_opt_path() {
-            Some(dir) => {
+            // A read-only Log cannot create missing index files. An empty
+            // in-memory index is built from the primary log instead.
+            Some(dir) if !read_only || len > 0 || dir.join(def.filename()).exists() => {
                 let path = dir.join(def.filename());
                 index::OpenOptions::new()
                     .checksum_chunk_size_logarithm(INDEX_CHECKSUM_CHUNK_SIZE_LOGARITHM)
                     .logical_len(Some(len))
                     .key_buf(Some(buf))
                     .fsync(fsync)
+                    .write(if read_only { Some(false) } else { None })
                     .open(path)
             }
-            None => index::OpenOptions::new()
+            _ => index::OpenOptions::new()
                 .logical_len(Some(len))
                 .key_buf(Some(buf))
                 .fsync(fsync)
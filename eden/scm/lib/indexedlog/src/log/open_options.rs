@@ -126,6 +126,7 @@ pub struct OpenOptions {
     pub(crate) flush_filter: Option<FlushFilterFunc>,
     pub(crate) fsync: bool,
     pub(crate) auto_sync_threshold: Option<u64>,
+    pub(crate) read_only: bool,
 }
 
 pub type FlushFilterFunc =
@@ -245,6 +246,7 @@ impl OpenOptions {
             flush_filter: None,
             fsync: false,
             auto_sync_threshold: None,
+            read_only: false,
         }
     }
 
@@ -300,6 +302,20 @@ impl OpenOptions {
         self
     }
 
+    /// Sets whether to open the [`Log`] in read-only mode.
+    ///
+    /// A read-only [`Log`] does not create the [`Log`], and does not write
+    /// lagging indexes to disk. Lagging index entries are built in memory
+    /// instead. [`Log::sync`] fails if there are pending in-memory entries,
+    /// and only reloads from disk otherwise.
+    ///
+    /// Like other readers, it holds the shared reader lock, so repair from
+    /// another process waits until it is dropped.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
     /// Sets the checksum type.
     ///
     /// See [`ChecksumType`] for details.
@@ -379,6 +395,7 @@ impl OpenOptions {
                 &mem_buf,
                 None,
                 self.fsync,
+                self.read_only,
             )?;
             let disk_folds = self.empty_folds();
             let all_folds = disk_folds.clone();
@@ -417,10 +434,14 @@ impl OpenOptions {
         lock: Option<&ScopedDirLock>,
     ) -> crate::Result<Log> {
         let reader_lock = match dir.as_opt_path() {
+            // Taking the reader lock would create the directory.
+            Some(d) if self.read_only && !d.is_dir() => {
+                return Err(crate::Error::path(d, "cannot open missing Log read-only"));
+            }
             Some(d) => Some(ScopedDirLock::new_with_options(d, &READER_LOCK_OPTS)?),
             None => None,
         };
-        let create = self.create;
+        let create = self.create && !self.read_only;
 
         // Do a lock-less load_or_create_meta to avoid the flock overhead.
         let meta = Log::load_or_create_meta(dir, false).or_else(|err| {
@@ -448,6 +469,7 @@ impl OpenOptions {
             &mem_buf,
             reuse_indexes,
             self.fsync,
+            self.read_only,
         )?;
         let disk_folds = self.empty_folds();
         let all_folds = disk_folds.clone();
@@ -467,7 +489,7 @@ impl OpenOptions {
         log.update_and_flush_disk_folds()?;
         log.all_folds = log.disk_folds.clone();
         let lagging_index_ids = log.lagging_index_ids();
-        if !lagging_index_ids.is_empty() {
+        if !lagging_index_ids.is_empty() && !self.read_only {
             // Update indexes.
             // NOTE: Consider ignoring failures if they are caused by permission
             // issues.
@@ -538,6 +560,7 @@ impl fmt::Debug for OpenOptions {
         write!(f, "create: {}, ", self.create)?;
         write!(f, "checksum_type: {:?}, ", self.checksum_type)?;
         write!(f, "auto_sync_threshold: {:?}, ", self.auto_sync_threshold)?;
+        write!(f, "read_only: {}, ", self.read_only)?;
         let flush_filter_desc = match self.flush_filter {
             Some(ref _buf) => "Some(_)",
             None => "None",
@@ -15,6 +15,7 @@ use quickcheck::quickcheck;
 use tempfile::tempdir;
 
 use super::*;
+use crate::lock::DirLockOptions;
 
 #[derive(Debug)]
 struct DummyError(&'static str);
@@ -810,6 +811,47 @@ fn test_auto_sync_threshold() {
     assert_eq!(log.iter_dirty().count(), 0);
 }
 
+#[test]
+fn test_read_only() {
+    let dir = tempdir().unwrap();
+    let def_a = IndexDef::new("a", |_| vec![IndexOutput::Reference(0..1)]).lag_threshold(1000);
+    let def_b = IndexDef::new("b", |_| vec![IndexOutput::Reference(1..2)]);
+    let index_b_path = dir.path().join(def_b.filename());
+
+    let open_opts = OpenOptions::new().create(true).index_defs(vec![def_a.clone()]);
+    let mut log = open_opts.open(dir.path()).unwrap();
+    log.append(b"xy").unwrap();
+    log.sync().unwrap();
+    drop(log);
+
+    // Cannot create a Log.
+    let ro_opts = OpenOptions::new().create(true).read_only(true);
+    assert!(ro_opts.open(dir.path().join("missing")).is_err());
+
+    // Lagging and missing indexes are built in memory.
+    let ro_opts = ro_opts.index_defs(vec![def_a, def_b]);
+    let mut log = ro_opts.open(dir.path()).unwrap();
+    assert_eq!(log.lookup(0, b"x").unwrap().count(), 1);
+    assert_eq!(log.lookup(1, b"y").unwrap().count(), 1);
+    assert!(!index_b_path.exists());
+
+    // The shared reader lock is held, so repair cannot start.
+    static CHECK_READER_LOCK_OPTS: DirLockOptions = DirLockOptions {
+        exclusive: true,
+        non_blocking: true,
+        ..READER_LOCK_OPTS
+    };
+    assert!(ScopedDirLock::new_with_options(dir.path(), &CHECK_READER_LOCK_OPTS).is_err());
+
+    // Reloading works. Writing does not.
+    log.sync().unwrap();
+    log.append(b"zw").unwrap();
+    log.sync().unwrap_err();
+
+    drop(log);
+    assert!(ScopedDirLock::new_with_options(dir.path(), &CHECK_READER_LOCK_OPTS).is_ok());
+}
+
 #[test]
 fn test_sync_missing_meta() {
     let dir = tempdir().unwrap();
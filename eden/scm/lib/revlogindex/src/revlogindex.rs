@@ -1680,7 +1680,7 @@ impl DagAlgorithm for RevlogIndex {
         let is_public = move |_: &MetaSet, v: &Vertex| -> dag::Result<bool> {
             let id = match non_blocking_result(dag.vertex_id(v.clone())) {
                 Ok(id) => id,
-                Err(DagError::VertexNotFound(_)) => return Ok(false),
+                Err(DagError::VertexNotFound { .. }) => return Ok(false),
                 Err(e) => {
                     return Err(e);
                 }
@@ -10,6 +10,7 @@
 use std::borrow::Cow;
 use std::env;
 use std::ffi::OsStr;
+use std::ffi::OsString;
 use std::fs;
 use std::fs::remove_file as fs_remove_file;
 use std::io;
@@ -396,9 +397,8 @@ pub fn create_shared_dir_all(path: impl AsRef<Path>) -> io::Result<()> {
 /// the given path.
 ///
 /// This function is designed to emulate the behavior of Mercurial's `util.expandpath`
-/// function, which in turn uses Python's `os.path.expand{user,vars}` functions. This
-/// results in behavior that is notably different from the default expansion behavior
-/// of the `shellexpand` crate. In particular:
+/// function, which in turn uses Python's `os.path.expand{user,vars}` functions. In
+/// particular:
 ///
 /// - If a reference to an environment variable is missing or invalid, the reference
 ///   is left unchanged in the resulting path rather than emitting an error.
@@ -408,20 +408,22 @@ pub fn create_shared_dir_all(path: impl AsRef<Path>) -> io::Result<()> {
 ///   string starting with a tilde (`~`), the tilde will be expanded into the
 ///   user's home directory.
 ///
+/// Environment variables and the home directory are not required to be valid
+/// UTF-8. They are joined as `OsString`s without lossy conversion.
+///
 pub fn expand_path(path: impl AsRef<str>) -> PathBuf {
-    expand_path_impl(path.as_ref(), |k| env::var(k).ok(), dirs::home_dir)
+    expand_path_impl(path.as_ref(), |k| env::var_os(k), dirs::home_dir)
 }
 
 /// Same as `expand_path` but explicitly takes closures for environment variable
 /// and home directory lookup for the sake of testability.
 fn expand_path_impl<E, H>(path: &str, getenv: E, homedir: H) -> PathBuf
 where
-    E: FnMut(&str) -> Option<String>,
+    E: FnMut(&str) -> Option<OsString>,
     H: FnOnce() -> Option<PathBuf>,
 {
-    // The shellexpand crate does not expand Windows environment variables
-    // like `%PROGRAMDATA%`. We'd like to expand them too. So let's do some
-    // pre-processing.
+    // Windows environment variables like `%PROGRAMDATA%` are translated to
+    // `${PROGRAMDATA}` so they can be expanded the same way.
     //
     // XXX: Doing this preprocessing has the unfortunate side-effect that
     // if an environment variable fails to expand on Windows, the resulting
@@ -453,10 +455,86 @@ where
         new_path
     };
 
-    let path = shellexpand::env_with_context_no_errors(&path, getenv);
-    shellexpand::tilde_with_context(&path, homedir)
-        .as_ref()
-        .into()
+    let path = expand_env_vars(&path, getenv);
+    expand_tilde(path, homedir).into()
+}
+
+/// Expand `$NAME` and `${NAME}` references. Missing variables are left as-is.
+fn expand_env_vars<E>(path: &str, mut getenv: E) -> OsString
+where
+    E: FnMut(&str) -> Option<OsString>,
+{
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut result = OsString::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(pos) = rest.find('$') {
+        result.push(&rest[..pos]);
+        rest = &rest[pos..];
+        // `reference` is the full `$NAME` or `${NAME}` text.
+        let (reference, name) = if let Some(braced) = rest.strip_prefix("${") {
+            match braced.find('}') {
+                Some(end) => (&rest[..end + 3], &braced[..end]),
+                None => (rest, ""),
+            }
+        } else {
+            let len = rest[1..]
+                .find(|c| !is_name_char(c))
+                .unwrap_or(rest.len() - 1);
+            (&rest[..len + 1], &rest[1..len + 1])
+        };
+        let value = if name.is_empty() { None } else { getenv(name) };
+        match value {
+            Some(value) => result.push(value),
+            None => result.push(reference),
+        }
+        rest = &rest[reference.len()..];
+    }
+    result.push(rest);
+    result
+}
+
+/// Expand `~` at the start of the path to the home directory.
+fn expand_tilde<H>(path: OsString, homedir: H) -> OsString
+where
+    H: FnOnce() -> Option<PathBuf>,
+{
+    let rest = match strip_tilde(&path) {
+        Some(rest) => rest,
+        None => return path,
+    };
+    match homedir() {
+        Some(home) => {
+            let mut result = home.into_os_string();
+            result.push(rest);
+            result
+        }
+        None => path,
+    }
+}
+
+/// Return the remaining part if `path` is `~`, or starts with `~/`
+/// (or `~\` on Windows).
+#[cfg(unix)]
+fn strip_tilde(path: &OsStr) -> Option<OsString> {
+    use std::os::unix::ffi::OsStrExt;
+    let rest = path.as_bytes().strip_prefix(b"~")?;
+    match rest.first() {
+        None | Some(b'/') => Some(OsStr::from_bytes(rest).to_os_string()),
+        _ => None,
+    }
+}
+
+#[cfg(windows)]
+fn strip_tilde(path: &OsStr) -> Option<OsString> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::ffi::OsStringExt;
+    let wide: Vec<u16> = path.encode_wide().collect();
+    let rest = wide.strip_prefix(&[b'~' as u16])?;
+    match rest.first() {
+        None => Some(OsString::new()),
+        Some(&c) if c == b'/' as u16 || c == b'\\' as u16 => Some(OsString::from_wide(rest)),
+        _ => None,
+    }
 }
 
 /// Return a relative [`PathBuf`] to the path from the base path.
@@ -764,7 +842,7 @@ mod tests {
 
     #[test]
     fn test_path_expansion() {
-        fn getenv(key: &str) -> Option<String> {
+        fn getenv(key: &str) -> Option<OsString> {
             match key {
                 "foo" => Some("~/a".into()),
                 "bar" => Some("b".into()),
@@ -780,6 +858,26 @@ mod tests {
         let expected = PathBuf::from("/home/user/a/b/$baz");
 
         assert_eq!(expand_path_impl(&path, getenv, homedir), expected);
+
+        let path = "~x/${bar/$/$bar_";
+        let expected = PathBuf::from("~x/${bar/$/$bar_");
+        assert_eq!(expand_path_impl(&path, getenv, homedir), expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_path_expansion_non_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let getenv = |key: &str| match key {
+            "user" => Some(OsStr::from_bytes(b"u\xff").to_os_string()),
+            _ => None,
+        };
+        let homedir = || Some(PathBuf::from(OsStr::from_bytes(b"/home/\xfe")));
+
+        let path = "~/$user/a.rc";
+        let expected = PathBuf::from(OsStr::from_bytes(b"/home/\xfe/u\xff/a.rc"));
+        assert_eq!(expand_path_impl(&path, getenv, homedir), expected);
     }
 
     #[test]